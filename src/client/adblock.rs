@@ -0,0 +1,132 @@
+use crate::client::{nvram, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+
+// FreshTomato's built-in adblock turns the downloaded blocklists into a dnsmasq config with one
+// blocked domain per line
+#[derive(Clone)]
+pub struct AdblockClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct Adblock {
+    pub enabled: bool,
+    pub blocked_domains: u64,
+}
+
+impl AdblockClient {
+    pub fn new(client: TomatoClientInternal) -> AdblockClient {
+        AdblockClient { client }
+    }
+
+    async fn get_adblock(&self) -> Result<Adblock, ExporterError> {
+        let body = self
+            .client
+            .run_command(format!(
+                "{}; echo \"blocked_domains=$(cat /etc/dnsmasq.adblock 2>/dev/null | wc -l)\"",
+                nvram::get_command(&["adblock_enable"])
+            ))
+            .await?;
+        Ok(AdblockClient::parse_body(body))
+    }
+
+    fn parse_body(body: String) -> Adblock {
+        let vars = nvram::parse(body.as_str());
+        Adblock {
+            enabled: vars.get("adblock_enable").is_some_and(|value| value == "1"),
+            blocked_domains: vars
+                .get("blocked_domains")
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(0),
+        }
+    }
+
+    fn raw_to_prom(raw_metrics: Adblock) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "adblock_enabled",
+                "Whether adblock is enabled",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    Vec::new(),
+                    if raw_metrics.enabled { 1f64 } else { 0f64 },
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "adblock_blocked_domains",
+                "Number of domains on the active blocklist",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    Vec::new(),
+                    raw_metrics.blocked_domains as f64,
+                    None,
+                )],
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for AdblockClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_adblock().await?;
+        Ok(AdblockClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "adblock".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            AdblockClient::parse_body("adblock_enable=1\nblocked_domains=   84213\n".to_string()),
+            Adblock {
+                enabled: true,
+                blocked_domains: 84213,
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_disabled() {
+        assert_eq!(
+            AdblockClient::parse_body("adblock_enable=\nblocked_domains=0\n".to_string()),
+            Adblock {
+                enabled: false,
+                blocked_domains: 0,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            AdblockClient::raw_to_prom(Adblock {
+                enabled: true,
+                blocked_domains: 84213,
+            }),
+            vec![
+                PromMetric::new(
+                    "adblock_enabled",
+                    "Whether adblock is enabled",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 1f64, None)],
+                ),
+                PromMetric::new(
+                    "adblock_blocked_domains",
+                    "Number of domains on the active blocklist",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 84213f64, None)],
+                ),
+            ]
+        )
+    }
+}
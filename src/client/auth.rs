@@ -0,0 +1,378 @@
+use std::sync::{Arc, Mutex};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::{COOKIE, REFERER, SET_COOKIE};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use tracing::{debug, error, info};
+
+use crate::config::RouterAuth;
+use crate::error::ExporterError;
+
+// what AsusWRT serves in place of the page asked for when the request isn't logged in
+const LOGIN_PAGE_MARKERS: [&str; 1] = ["Main_Login.asp"];
+
+// attaches the admin credentials to every request sent to the router
+#[derive(Clone)]
+pub struct Authenticator {
+    username: String,
+    password: String,
+    session: Option<SessionLogin>,
+    // whether the router accepted the credentials the last time it was asked, or None before then
+    accepted: Arc<Mutex<Option<bool>>>,
+}
+
+// the login form is only posted when there is no session yet or the router has rejected the last one,
+// and the cookie is shared by every scraper
+#[derive(Clone)]
+struct SessionLogin {
+    path: String,
+    form: Vec<(String, String)>,
+    // page sent as the Referer of every request, for routers that refuse requests without one
+    referer: Option<String>,
+    // the session cookie is set by the login response itself, which is often a redirect, so the login
+    // has to be sent without following redirects
+    client: Client,
+    cookie: Arc<Mutex<Option<String>>>,
+}
+
+impl Authenticator {
+    pub fn new(
+        username: String,
+        password: String,
+        auth: &RouterAuth,
+        login_client: Client,
+    ) -> Authenticator {
+        let session = match auth {
+            RouterAuth::Basic => None,
+            RouterAuth::Form {
+                path,
+                username_field,
+                password_field,
+            } => Some(SessionLogin {
+                path: path.clone(),
+                form: vec![
+                    (username_field.clone(), username.clone()),
+                    (password_field.clone(), password.clone()),
+                ],
+                referer: None,
+                client: login_client,
+                cookie: Arc::new(Mutex::new(None)),
+            }),
+            RouterAuth::AsusToken => Some(SessionLogin {
+                path: "login.cgi".to_string(),
+                form: vec![(
+                    "login_authorization".to_string(),
+                    STANDARD.encode(format!("{}:{}", username, password)),
+                )],
+                referer: Some("index.asp".to_string()),
+                client: login_client,
+                cookie: Arc::new(Mutex::new(None)),
+            }),
+        };
+        Authenticator {
+            username,
+            password,
+            session,
+            accepted: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn accepted(&self) -> Option<bool> {
+        *self.accepted.lock().unwrap()
+    }
+
+    // a router that doesn't take the credentials answers every request with a 401 or 403, or on some
+    // firmware with its login page, which no parser would make sense of
+    pub fn check(
+        &self,
+        endpoint: &str,
+        status: StatusCode,
+        body: &str,
+    ) -> Result<(), ExporterError> {
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            self.record(false);
+            return Err(ExporterError::Auth(format!(
+                "{} returned {}",
+                endpoint, status
+            )));
+        }
+        if Authenticator::is_login_page(body) {
+            self.record(false);
+            return Err(ExporterError::Auth(format!(
+                "{} returned the login page",
+                endpoint
+            )));
+        }
+        self.record(true);
+        Ok(())
+    }
+
+    // only changes are logged, so that a wrong password doesn't log an error for every request of
+    // every scrape
+    fn record(&self, accepted: bool) {
+        let previous = self.accepted.lock().unwrap().replace(accepted);
+        if accepted && previous == Some(false) {
+            info!("Authentication with the router succeeded again");
+        } else if !accepted && previous != Some(false) {
+            error!(
+                "Authentication with the router failed, check admin_username, admin_password and \
+                http_id"
+            );
+        }
+    }
+
+    pub async fn authorize(
+        &self,
+        base_url: &str,
+        request: RequestBuilder,
+    ) -> Result<RequestBuilder, ExporterError> {
+        let Some(session) = &self.session else {
+            return Ok(request.basic_auth(self.username.clone(), Some(self.password.clone())));
+        };
+        let cookie = session.cookie.lock().unwrap().clone();
+        let cookie = match cookie {
+            Some(cookie) => cookie,
+            None => self.login(session, base_url).await?,
+        };
+        let request = request.header(COOKIE, cookie);
+        Ok(match &session.referer {
+            Some(referer) => request.header(REFERER, format!("{}/{}", base_url, referer)),
+            None => request,
+        })
+    }
+
+    // whether a request should be retried after logging in again.  a session that has run out is
+    // answered with a 401 by some firmware, but AsusWRT and many others answer 200 with their login
+    // page or a page redirecting to it
+    pub fn expire(&self, status: StatusCode, content_type: Option<&str>, body: &str) -> bool {
+        let Some(session) = &self.session else {
+            return false;
+        };
+        if status == StatusCode::UNAUTHORIZED
+            || Authenticator::is_login_page(body)
+            || super::is_redirect(content_type, body)
+        {
+            debug!("Router session expired, logging in again");
+            *session.cookie.lock().unwrap() = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_login_page(body: &str) -> bool {
+        LOGIN_PAGE_MARKERS
+            .iter()
+            .any(|marker| body.contains(marker))
+    }
+
+    async fn login(&self, session: &SessionLogin, base_url: &str) -> Result<String, ExporterError> {
+        let mut request = session
+            .client
+            .post(format!("{}/{}", base_url, session.path).as_str())
+            .form(&session.form);
+        if let Some(referer) = &session.referer {
+            request = request.header(REFERER, format!("{}/{}", base_url, referer));
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::UNAUTHORIZED
+            || response.status() == StatusCode::FORBIDDEN
+        {
+            self.record(false);
+        }
+        let response = response.error_for_status()?;
+        let cookie = Authenticator::parse_cookies(
+            response
+                .headers()
+                .get_all(SET_COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok()),
+        );
+        // a router that turns the login down often answers with its login form again rather than an
+        // error status, and without a cookie every request after it would be bounced as well
+        if cookie.is_empty() {
+            self.record(false);
+            return Err(ExporterError::Auth(format!(
+                "{} didn't set a session cookie",
+                session.path
+            )));
+        }
+        *session.cookie.lock().unwrap() = Some(cookie.clone());
+        Ok(cookie)
+    }
+
+    // only the name=value pair of each Set-Cookie header is sent back, the attributes after it are
+    // meant for the client
+    fn parse_cookies<'a>(set_cookies: impl Iterator<Item = &'a str>) -> String {
+        set_cookies
+            .filter_map(|set_cookie| set_cookie.split(';').next())
+            .map(str::trim)
+            .filter(|cookie| cookie.contains('='))
+            .collect::<Vec<&str>>()
+            .join("; ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_cookies() {
+        assert_eq!(
+            Authenticator::parse_cookies(
+                vec![
+                    "asus_token=Xd8kq2; HttpOnly; Path=/",
+                    "clickedItem_tab=0",
+                    "garbage",
+                ]
+                .into_iter()
+            ),
+            "asus_token=Xd8kq2; clickedItem_tab=0".to_string()
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let auth = Authenticator::new(
+            "admin".to_string(),
+            "password123".to_string(),
+            &RouterAuth::Basic,
+            Client::new(),
+        );
+        assert_eq!(auth.accepted(), None);
+        assert!(auth
+            .check("status-data.jsx", StatusCode::OK, "nvram = {};")
+            .is_ok());
+        assert_eq!(auth.accepted(), Some(true));
+        assert!(matches!(
+            auth.check("status-data.jsx", StatusCode::UNAUTHORIZED, "Unauthorized"),
+            Err(ExporterError::Auth(_))
+        ));
+        assert_eq!(auth.accepted(), Some(false));
+        assert!(matches!(
+            auth.check(
+                "appGet.cgi",
+                StatusCode::OK,
+                "<script>top.location.href='/Main_Login.asp';</script>"
+            ),
+            Err(ExporterError::Auth(_))
+        ));
+        assert_eq!(auth.accepted(), Some(false));
+    }
+
+    #[test]
+    fn test_expire() {
+        let auth = Authenticator::new(
+            "admin".to_string(),
+            "password123".to_string(),
+            &RouterAuth::AsusToken,
+            Client::new(),
+        );
+        let session = auth.session.as_ref().unwrap();
+        let login = || *session.cookie.lock().unwrap() = Some("asus_token=Xd8kq2".to_string());
+
+        login();
+        assert!(!auth.expire(StatusCode::OK, Some("application/json"), "{\"uptime\": 1}"));
+        assert!(session.cookie.lock().unwrap().is_some());
+
+        // an expired AsusWRT session is answered with a 200 and the login page
+        assert!(auth.expire(
+            StatusCode::OK,
+            Some("text/html"),
+            "<html><head><title>ASUS Login</title></head><form action=\"Main_Login.asp\">"
+        ));
+        assert!(session.cookie.lock().unwrap().is_none());
+
+        login();
+        assert!(auth.expire(
+            StatusCode::OK,
+            Some("text/html"),
+            "<script>top.location.href='/';</script>"
+        ));
+        assert!(session.cookie.lock().unwrap().is_none());
+
+        login();
+        assert!(auth.expire(StatusCode::UNAUTHORIZED, None, ""));
+        assert!(session.cookie.lock().unwrap().is_none());
+
+        let basic = Authenticator::new(
+            "admin".to_string(),
+            "password123".to_string(),
+            &RouterAuth::Basic,
+            Client::new(),
+        );
+        assert!(!basic.expire(StatusCode::UNAUTHORIZED, None, ""));
+    }
+
+    #[test]
+    fn test_expire_form_redirect() {
+        let auth = Authenticator::new(
+            "admin".to_string(),
+            "password123".to_string(),
+            &RouterAuth::Form {
+                path: "login.cgi".to_string(),
+                username_field: "username".to_string(),
+                password_field: "password".to_string(),
+            },
+            Client::new(),
+        );
+        let session = auth.session.as_ref().unwrap();
+        *session.cookie.lock().unwrap() = Some("SESSID=7f3a".to_string());
+        assert!(auth.expire(
+            StatusCode::OK,
+            None,
+            "<meta http-equiv=\"refresh\" content=\"0; url=/login.html\">"
+        ));
+        assert!(session.cookie.lock().unwrap().is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_login_without_cookie() {
+        // a router that turns the login down with its login form again and no cookie
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        actix_web::rt::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+            let body = "<html><form action=\"login.cgi\"></form></html>";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\
+                Connection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let auth = Authenticator::new(
+            "admin".to_string(),
+            "password123".to_string(),
+            &RouterAuth::Form {
+                path: "login.cgi".to_string(),
+                username_field: "username".to_string(),
+                password_field: "password".to_string(),
+            },
+            Client::new(),
+        );
+        let request = Client::new().get(format!("{}/status.cgi", base_url));
+        assert!(matches!(
+            auth.authorize(base_url.as_str(), request).await,
+            Err(ExporterError::Auth(_))
+        ));
+        assert_eq!(auth.accepted(), Some(false));
+        assert!(auth
+            .session
+            .as_ref()
+            .unwrap()
+            .cookie
+            .lock()
+            .unwrap()
+            .is_none());
+    }
+}
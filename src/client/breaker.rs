@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::config::CircuitBreakerConfig;
+
+// stops querying a router that keeps failing for a while, so that scrapes against a dead router fail
+// straight away instead of each waiting out its timeouts.  shared by every scraper
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    failures: u32,
+    cooldown: Duration,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl BreakerState {
+    fn is_open(&self) -> bool {
+        self.open_until
+            .is_some_and(|open_until| Instant::now() < open_until)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(conf: &CircuitBreakerConfig) -> CircuitBreaker {
+        CircuitBreaker {
+            failures: conf.failures,
+            cooldown: Duration::from_secs_f64(conf.cooldown),
+            state: Arc::new(Mutex::new(BreakerState::default())),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().is_open()
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.open_until = None;
+    }
+
+    // once tripped, the first failure after the cooldown trips it again straight away
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failures {
+            if !state.is_open() {
+                warn!(
+                    "Router failed {} times in a row, not querying it for {:?}",
+                    state.consecutive_failures, self.cooldown
+                );
+            }
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new(&CircuitBreakerConfig {
+            failures: 2,
+            cooldown: 60f64,
+        });
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+        breaker.record_failure();
+        assert!(breaker.is_open());
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+    }
+
+    #[test]
+    fn test_circuit_breaker_cooldown() {
+        let breaker = CircuitBreaker::new(&CircuitBreakerConfig {
+            failures: 1,
+            cooldown: 0f64,
+        });
+        breaker.record_failure();
+        assert!(!breaker.is_open());
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+
+use regex::Regex;
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::client::RawResponse;
+use crate::config::CaptureConfig;
+use crate::error::ExporterError;
+
+// saves what the router sent a collector whose parser failed, so that it can be attached to a bug
+// report as a fixture.  nvram dumps carry the router's passwords and keys, so those are removed
+// before anything is written
+#[derive(Clone)]
+pub struct Capture {
+    directory: Option<PathBuf>,
+    max_bytes: usize,
+    // credentials from the config, which can turn up anywhere in a response
+    secrets: Vec<String>,
+    secret_re: Regex,
+}
+
+impl Capture {
+    pub fn new(conf: &CaptureConfig, secrets: Vec<String>) -> Capture {
+        Capture {
+            directory: conf.directory.as_ref().map(PathBuf::from),
+            max_bytes: conf.max_bytes,
+            secrets: secrets
+                .into_iter()
+                .filter(|secret| !secret.is_empty())
+                .collect(),
+            // nvram variables such as http_passwd, wl0_wpa_psk or wan_pppoe_passwd, written as
+            // name: 'value', name = 'value' or name=value
+            secret_re: Regex::new(
+                r#"(?i)(['"]?[a-z0-9_]*(?:passwd|password|psk|secret|key)[a-z0-9_]*['"]?\s*[:=]\s*)(?:'[^']*'|"[^"]*"|[^\s,}]+)"#,
+            )
+            .unwrap(),
+        }
+    }
+
+    pub fn save(&self, collector: &str, err: &ExporterError, responses: &[RawResponse]) {
+        let capture = self.render(collector, err, responses);
+        let Some(directory) = &self.directory else {
+            warn!(
+                "Captured response for failed collector {}:\n{}",
+                collector, capture
+            );
+            return;
+        };
+        let path = directory.join(format!(
+            "{}-{}.txt",
+            collector,
+            OffsetDateTime::now_utc().unix_timestamp()
+        ));
+        match std::fs::write(&path, capture) {
+            Ok(()) => warn!(
+                "Captured response for failed collector {} in {}",
+                collector,
+                path.display()
+            ),
+            Err(err) => warn!("Unable to write {}: {}", path.display(), err),
+        }
+    }
+
+    fn render(&self, collector: &str, err: &ExporterError, responses: &[RawResponse]) -> String {
+        let mut capture = format!("# collector: {}\n# error: {}\n", collector, err);
+        for response in responses {
+            capture.push_str(format!("# request: {}\n", self.scrub(&response.request)).as_str());
+            match &response.body {
+                Ok(body) => {
+                    capture.push_str(self.truncate(self.scrub(body)).as_str());
+                    capture.push('\n');
+                }
+                Err(err) => capture.push_str(format!("# failed: {}\n", err).as_str()),
+            }
+        }
+        capture
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        let mut text = self
+            .secret_re
+            .replace_all(text, "${1}<redacted>")
+            .into_owned();
+        for secret in &self.secrets {
+            text = text.replace(secret.as_str(), "<redacted>");
+        }
+        text
+    }
+
+    fn truncate(&self, mut body: String) -> String {
+        if body.len() <= self.max_bytes {
+            return body;
+        }
+        let mut end = self.max_bytes;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let cut = body.len() - end;
+        body.truncate(end);
+        body.push_str(format!("\n# truncated {} bytes", cut).as_str());
+        body
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn capture(max_bytes: usize) -> Capture {
+        Capture::new(
+            &CaptureConfig {
+                directory: None,
+                max_bytes,
+            },
+            vec!["hunter2".to_string(), "TIDcf40d0bd0eba4ba0".to_string()],
+        )
+    }
+
+    #[test]
+    fn test_scrub() {
+        assert_eq!(
+            capture(1024).scrub(
+                "nvram = {'http_passwd': 'hunter2', 'wl0_wpa_psk': 'letmein', 'wan_proto': 'dhcp'};"
+            ),
+            "nvram = {'http_passwd': <redacted>, 'wl0_wpa_psk': <redacted>, 'wan_proto': 'dhcp'};"
+        );
+        assert_eq!(
+            capture(1024).scrub("update.cgi _http_id=TIDcf40d0bd0eba4ba0 exec=bandwidth"),
+            "update.cgi _http_id=<redacted> exec=bandwidth"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            capture(8).render(
+                "load",
+                &ExporterError::Parse("unable to parse load".to_string()),
+                &[
+                    RawResponse {
+                        collector: "load".to_string(),
+                        request: "cat /proc/loadavg".to_string(),
+                        body: Ok("0.01 0.02 garbage".to_string()),
+                    },
+                    RawResponse {
+                        collector: "load".to_string(),
+                        request: "uptime".to_string(),
+                        body: Err("connection refused".to_string()),
+                    },
+                ]
+            ),
+            "# collector: load
+# error: parse: unable to parse load
+# request: cat /proc/loadavg
+0.01 0.0
+# truncated 9 bytes
+# request: uptime
+# failed: connection refused
+"
+        );
+    }
+}
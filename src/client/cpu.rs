@@ -1,8 +1,9 @@
 use std::collections::BTreeMap;
 
-use regex::Regex;
+use regex::{Captures, Regex};
 
-use crate::client::{Scraper, TomatoClientInternal};
+use crate::client::{parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
 use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
 
 #[derive(Clone)]
@@ -22,55 +23,97 @@ struct CpuStats {
     steal: Option<f32>,
 }
 
+#[derive(Debug, PartialEq)]
+struct SystemCounters {
+    intr: Option<u64>,
+    ctxt: Option<u64>,
+    processes: Option<u64>,
+    procs_running: Option<u64>,
+    procs_blocked: Option<u64>,
+}
+
 impl CpuClient {
     pub fn new(client: TomatoClientInternal) -> CpuClient {
         CpuClient { client }
     }
 
-    async fn get_cpu(&self) -> Result<BTreeMap<u8, CpuStats>, reqwest::Error> {
+    async fn get_cpu(&self) -> Result<(BTreeMap<u8, CpuStats>, SystemCounters), ExporterError> {
         let body = self
             .client
             .run_command("cat /proc/stat".to_string())
             .await?;
-        Ok(CpuClient::parse_body(body))
+        Ok((
+            CpuClient::parse_body(body.clone()),
+            CpuClient::parse_counters(body),
+        ))
     }
 
+    // a cpu line that can't be parsed is skipped, leaving the other cpus to be reported
     fn parse_body(body: String) -> BTreeMap<u8, CpuStats> {
-        let cpu_re = Regex::new(r"cpu(?P<cpu>[0-9]+) (?P<jiffies>.*)").unwrap();
-        cpu_re
-            .captures_iter(body.as_str().trim())
-            .map(|raw_cpu| {
-                let cpu_id = raw_cpu.name("cpu").unwrap().as_str().parse::<u8>().unwrap();
-                let jiffies: Vec<u32> = raw_cpu
-                    .name("jiffies")
-                    .unwrap()
-                    .as_str()
-                    .split(' ')
-                    .map(|jif| jif.parse::<u32>().unwrap())
-                    .collect();
-
-                (
-                    cpu_id,
-                    CpuStats {
-                        user: CpuClient::get_jiffie(&jiffies, 0),
-                        nice: CpuClient::get_jiffie(&jiffies, 1),
-                        system: CpuClient::get_jiffie(&jiffies, 2),
-                        idle: CpuClient::get_jiffie(&jiffies, 3),
-                        iowait: CpuClient::opt_jiffie(&jiffies, 4),
-                        irq: CpuClient::opt_jiffie(&jiffies, 5),
-                        softirq: CpuClient::opt_jiffie(&jiffies, 6),
-                        steal: CpuClient::opt_jiffie(&jiffies, 7),
-                    },
-                )
+        let cpu_re = Regex::new(r"^cpu(?P<cpu>[0-9]+) (?P<jiffies>.*)$").unwrap();
+        body.lines()
+            .filter_map(|line| {
+                let raw_cpu = cpu_re.captures(line.trim())?;
+                let cpu = CpuClient::parse_cpu(&raw_cpu);
+                if cpu.is_none() {
+                    parse_warning(line);
+                }
+                cpu
             })
             .collect()
     }
 
-    fn get_jiffie(jiffies: &[u32], i: usize) -> f32 {
+    fn parse_cpu(raw_cpu: &Captures) -> Option<(u8, CpuStats)> {
+        let cpu_id = raw_cpu["cpu"].parse::<u8>().ok()?;
+        let jiffies = raw_cpu["jiffies"]
+            .split_whitespace()
+            .map(|jif| jif.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        if jiffies.len() < 4 {
+            return None;
+        }
+        Some((
+            cpu_id,
+            CpuStats {
+                user: CpuClient::get_jiffie(&jiffies, 0),
+                nice: CpuClient::get_jiffie(&jiffies, 1),
+                system: CpuClient::get_jiffie(&jiffies, 2),
+                idle: CpuClient::get_jiffie(&jiffies, 3),
+                iowait: CpuClient::opt_jiffie(&jiffies, 4),
+                irq: CpuClient::opt_jiffie(&jiffies, 5),
+                softirq: CpuClient::opt_jiffie(&jiffies, 6),
+                steal: CpuClient::opt_jiffie(&jiffies, 7),
+            },
+        ))
+    }
+
+    fn parse_counters(body: String) -> SystemCounters {
+        let counter = |name: &str| {
+            Regex::new(format!(r"(?m)^{} (?P<value>[0-9]+)", name).as_str())
+                .unwrap()
+                .captures(body.as_str())
+                .and_then(|capture| {
+                    let value = capture["value"].parse::<u64>().ok();
+                    if value.is_none() {
+                        parse_warning(&capture[0]);
+                    }
+                    value
+                })
+        };
+        SystemCounters {
+            intr: counter("intr"),
+            ctxt: counter("ctxt"),
+            processes: counter("processes"),
+            procs_running: counter("procs_running"),
+            procs_blocked: counter("procs_blocked"),
+        }
+    }
+
+    fn get_jiffie(jiffies: &[u64], i: usize) -> f32 {
         jiffies[i] as f32 / 100f32
     }
 
-    fn opt_jiffie(jiffies: &[u32], i: usize) -> Option<f32> {
+    fn opt_jiffie(jiffies: &[u64], i: usize) -> Option<f32> {
         if jiffies.len() > i {
             Some(jiffies[i] as f32 / 100f32)
         } else {
@@ -165,13 +208,61 @@ impl CpuClient {
                 .collect(),
         )]
     }
+
+    fn counters_to_prom(counters: SystemCounters) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "node_intr_total",
+                "Total number of interrupts serviced",
+                PromMetricType::Counter,
+                counters.intr.map_or_else(Vec::new, |intr| {
+                    vec![PromSample::new(Vec::new(), intr as f64, None)]
+                }),
+            ),
+            PromMetric::new(
+                "node_context_switches_total",
+                "Total number of context switches",
+                PromMetricType::Counter,
+                counters.ctxt.map_or_else(Vec::new, |ctxt| {
+                    vec![PromSample::new(Vec::new(), ctxt as f64, None)]
+                }),
+            ),
+            PromMetric::new(
+                "node_forks_total",
+                "Total number of forks",
+                PromMetricType::Counter,
+                counters.processes.map_or_else(Vec::new, |processes| {
+                    vec![PromSample::new(Vec::new(), processes as f64, None)]
+                }),
+            ),
+            PromMetric::new(
+                "node_procs_running",
+                "Number of processes in runnable state",
+                PromMetricType::Gauge,
+                counters.procs_running.map_or_else(Vec::new, |running| {
+                    vec![PromSample::new(Vec::new(), running as f64, None)]
+                }),
+            ),
+            PromMetric::new(
+                "node_procs_blocked",
+                "Number of processes blocked waiting for I/O to complete",
+                PromMetricType::Gauge,
+                counters.procs_blocked.map_or_else(Vec::new, |blocked| {
+                    vec![PromSample::new(Vec::new(), blocked as f64, None)]
+                }),
+            ),
+        ]
+    }
 }
 
 #[async_trait]
 impl Scraper for CpuClient {
-    async fn get_metrics(&self) -> Result<Vec<PromMetric>, reqwest::Error> {
-        let raw_metrics = self.get_cpu().await?;
-        Ok(CpuClient::raw_to_prom(raw_metrics))
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let (cpus, counters) = self.get_cpu().await?;
+        Ok(CpuClient::raw_to_prom(cpus)
+            .into_iter()
+            .chain(CpuClient::counters_to_prom(counters))
+            .collect())
     }
 
     fn get_name(&self) -> String {
@@ -180,6 +271,7 @@ impl Scraper for CpuClient {
 }
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_cast)]
 mod test {
     use super::*;
 
@@ -213,6 +305,53 @@ procs_blocked 0"
         )
     }
 
+    #[test]
+    fn test_parse_body_malformed() {
+        assert_eq!(
+            CpuClient::parse_body(
+                "cpu  5000000000 0 230563 168024492
+cpu0 2500000000 0 115281 84012246
+cpu1 2500000000 0 115282
+cpu2 2500000000 0 x 84012246"
+                    .to_string()
+            ),
+            btreemap!(0u8 => CpuStats {
+                user: 25000000f32,
+                nice: 0f32,
+                system: 115281f32 / 100f32,
+                idle: 84012246f32 / 100f32,
+                iowait: None,
+                irq: None,
+                softirq: None,
+                steal: None,
+            })
+        )
+    }
+
+    #[test]
+    fn test_parse_counters() {
+        assert_eq!(
+            CpuClient::parse_counters(
+                "cpu  162283 0 230563 168024492 2376 293698 4732481 0
+cpu0 162283 0 230563 168024492 2376 293698 4732481 0
+intr 846816216 0 0 0 203721765 315990752 153649036 8769 173445893 1 0 0 0 0 0 0 0 0 0 0 0 0 0
+ctxt 15743031
+btime 1596584154
+processes 391097
+procs_running 2
+procs_blocked 0"
+                    .to_string()
+            ),
+            SystemCounters {
+                intr: Some(846816216),
+                ctxt: Some(15743031),
+                processes: Some(391097),
+                procs_running: Some(2),
+                procs_blocked: Some(0),
+            }
+        )
+    }
+
     #[test]
     fn test_raw_to_prom() {
         assert_eq!(
@@ -299,4 +438,49 @@ procs_blocked 0"
             )]
         )
     }
+
+    #[test]
+    fn test_counters_to_prom() {
+        assert_eq!(
+            CpuClient::counters_to_prom(SystemCounters {
+                intr: Some(846816216),
+                ctxt: Some(15743031),
+                processes: None,
+                procs_running: Some(2),
+                procs_blocked: Some(0),
+            }),
+            vec![
+                PromMetric::new(
+                    "node_intr_total",
+                    "Total number of interrupts serviced",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(Vec::new(), 846816216f64, None)],
+                ),
+                PromMetric::new(
+                    "node_context_switches_total",
+                    "Total number of context switches",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(Vec::new(), 15743031f64, None)],
+                ),
+                PromMetric::new(
+                    "node_forks_total",
+                    "Total number of forks",
+                    PromMetricType::Counter,
+                    vec![],
+                ),
+                PromMetric::new(
+                    "node_procs_running",
+                    "Number of processes in runnable state",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 2f64, None)],
+                ),
+                PromMetric::new(
+                    "node_procs_blocked",
+                    "Number of processes blocked waiting for I/O to complete",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 0f64, None)],
+                ),
+            ]
+        )
+    }
 }
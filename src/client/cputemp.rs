@@ -0,0 +1,123 @@
+use regex::Regex;
+
+use crate::client::{parse_capture, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+// FreshTomato reports the SoC temperature on the status page, read from the Broadcom DMU on ARM
+// builds or from the thermal zone on newer kernels
+#[derive(Clone)]
+pub struct CpuTempClient {
+    client: TomatoClientInternal,
+}
+
+impl CpuTempClient {
+    pub fn new(client: TomatoClientInternal) -> CpuTempClient {
+        CpuTempClient { client }
+    }
+
+    async fn get_temperature(&self) -> Result<Option<f64>, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "cat /proc/dmu/temperature 2>/dev/null; \
+                cat /sys/class/thermal/thermal_zone0/temp 2>/dev/null"
+                    .to_string(),
+            )
+            .await?;
+        Ok(CpuTempClient::parse_body(body))
+    }
+
+    // the DMU reports whole degrees with a unit after them, the thermal zone reports millidegrees.
+    // routers without either, such as MIPS builds, report nothing
+    fn parse_body(body: String) -> Option<f64> {
+        let dmu_re = Regex::new(r"CPU temperature\s*:\s*(?P<celsius>-?[0-9]+)").unwrap();
+        if let Some(capture) = dmu_re.captures(body.as_str()) {
+            return parse_capture::<f64>(&capture, "celsius").ok();
+        }
+        body.trim()
+            .lines()
+            .next()
+            .and_then(|line| line.trim().parse::<f64>().ok())
+            .map(|millicelsius| millicelsius / 1000f64)
+    }
+
+    fn raw_to_prom(raw_metrics: Option<f64>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "node_thermal_zone_temp",
+            "Zone temperature in Celsius",
+            PromMetricType::Gauge,
+            raw_metrics
+                .map(|celsius| {
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("type", "cpu".to_string()),
+                            PromLabel::new("zone", "0".to_string()),
+                        ],
+                        celsius,
+                        None,
+                    )
+                })
+                .into_iter()
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for CpuTempClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_temperature().await?;
+        Ok(CpuTempClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "cputemp".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body_dmu() {
+        assert_eq!(
+            CpuTempClient::parse_body("CPU temperature\t: 71\u{2103}\n".to_string()),
+            Some(71f64)
+        )
+    }
+
+    #[test]
+    fn test_parse_body_thermal_zone() {
+        assert_eq!(
+            CpuTempClient::parse_body("58500\n".to_string()),
+            Some(58.5f64)
+        )
+    }
+
+    #[test]
+    fn test_parse_body_unsupported() {
+        assert_eq!(CpuTempClient::parse_body("".to_string()), None)
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            CpuTempClient::raw_to_prom(Some(71f64)),
+            vec![PromMetric::new(
+                "node_thermal_zone_temp",
+                "Zone temperature in Celsius",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    vec![
+                        PromLabel::new("type", "cpu".to_string()),
+                        PromLabel::new("zone", "0".to_string()),
+                    ],
+                    71f64,
+                    None
+                )],
+            )]
+        )
+    }
+}
@@ -0,0 +1,131 @@
+use regex::Regex;
+
+use crate::client::ddwrt::parse_live;
+use crate::client::{parse_capture, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::PromMetric;
+
+#[derive(Clone)]
+pub struct DdwrtLoadClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct LoadAverage {
+    pub load_1m: f64,
+    pub load_5m: f64,
+    pub load_15m: f64,
+}
+
+impl DdwrtLoadClient {
+    pub fn new(client: TomatoClientInternal) -> DdwrtLoadClient {
+        DdwrtLoadClient { client }
+    }
+
+    async fn get_load(&self) -> Result<LoadAverage, ExporterError> {
+        let body = self.client.get_page("Status_Router.live.asp").await?;
+        DdwrtLoadClient::parse_body(body)
+    }
+
+    // the load averages are only reported as part of the output of uptime
+    fn parse_body(body: String) -> Result<LoadAverage, ExporterError> {
+        let load_re = Regex::new(
+            r"load average: (?P<load_1m>[0-9.]+), (?P<load_5m>[0-9.]+), (?P<load_15m>[0-9.]+)",
+        )
+        .unwrap();
+        let vars = parse_live(body.as_str());
+        let capture = vars
+            .get("uptime")
+            .and_then(|uptime| load_re.captures(uptime))
+            .ok_or_else(|| ExporterError::Parse("unable to parse load".to_string()))?;
+        Ok(LoadAverage {
+            load_1m: parse_capture(&capture, "load_1m")?,
+            load_5m: parse_capture(&capture, "load_5m")?,
+            load_15m: parse_capture(&capture, "load_15m")?,
+        })
+    }
+
+    fn raw_to_prom(raw_metrics: LoadAverage) -> Vec<PromMetric> {
+        vec![
+            PromMetric::build("node_load1")
+                .help("1m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_1m))
+                .finish(),
+            PromMetric::build("node_load5")
+                .help("5m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_5m))
+                .finish(),
+            PromMetric::build("node_load15")
+                .help("15m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_15m))
+                .finish(),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for DdwrtLoadClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_load().await?;
+        Ok(DdwrtLoadClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "load".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prometheus::{PromMetricType, PromSample};
+
+    #[test]
+    fn test_parse_body() {
+        let body = "{router_time::Thu, 12 Oct 2023 10:11:12}
+{uptime:: 10:11:12 up 3 days,  4:05,  load average: 0.08, 0.03, 0.01}
+{ipinfo::&nbsp;IP: 192.168.1.1}";
+        assert_eq!(
+            DdwrtLoadClient::parse_body(body.to_string()).unwrap(),
+            LoadAverage {
+                load_1m: 0.08f64,
+                load_5m: 0.03f64,
+                load_15m: 0.01f64,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            DdwrtLoadClient::raw_to_prom(LoadAverage {
+                load_1m: 0.08f64,
+                load_5m: 0.03f64,
+                load_15m: 0.01f64,
+            }),
+            vec![
+                PromMetric::new(
+                    "node_load1",
+                    "1m load average",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 0.08f64, None)],
+                ),
+                PromMetric::new(
+                    "node_load5",
+                    "5m load average",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 0.03f64, None)],
+                ),
+                PromMetric::new(
+                    "node_load15",
+                    "15m load average",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 0.01f64, None)],
+                ),
+            ]
+        )
+    }
+}
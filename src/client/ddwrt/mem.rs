@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use crate::client::ddwrt::{parse_list, parse_live};
+use crate::client::mem::MemClient;
+use crate::client::{Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::PromMetric;
+
+#[derive(Clone)]
+pub struct DdwrtMemClient {
+    client: TomatoClientInternal,
+}
+
+impl DdwrtMemClient {
+    pub fn new(client: TomatoClientInternal) -> DdwrtMemClient {
+        DdwrtMemClient { client }
+    }
+
+    async fn get_mem(&self) -> Result<BTreeMap<String, u64>, ExporterError> {
+        let body = self.client.get_page("Status_Router.live.asp").await?;
+        Ok(DdwrtMemClient::parse_body(body))
+    }
+
+    // mem_info is /proc/meminfo split into a list, so each field is a name, a value and a unit.  the
+    // older summary table at the start of it has no units and is skipped
+    fn parse_body(body: String) -> BTreeMap<String, u64> {
+        let vars = parse_live(body.as_str());
+        let items = parse_list(vars.get("mem_info").map_or("", String::as_str));
+        items
+            .windows(3)
+            .filter_map(|field| {
+                let [name, value, unit] = field else {
+                    return None;
+                };
+                if unit != "kB" {
+                    return None;
+                }
+                Some((
+                    name.strip_suffix(':')?.replace('(', "_").replace(')', ""),
+                    value.parse::<u64>().ok()?.checked_mul(1024)?,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Scraper for DdwrtMemClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_mem().await?;
+        Ok(MemClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "{uptime:: 10:11:12 up 3 days,  4:05,  load average: 0.08, 0.03, 0.01}
+{mem_info::'total:','used:','free:','shared:','buffers:','cached:','Mem:','129740800','67723264','62017536','0','4358144','22679552','Swap:','0','0','0','MemTotal:','126700','kB','MemFree:','60564','kB','Buffers:','4256','kB','Cached:','22148','kB','Active(file):','9120','kB'}";
+        assert_eq!(
+            DdwrtMemClient::parse_body(body.to_string()),
+            btreemap! {
+                "MemTotal".to_string() => 126700 * 1024,
+                "MemFree".to_string() => 60564 * 1024,
+                "Buffers".to_string() => 4256 * 1024,
+                "Cached".to_string() => 22148 * 1024,
+                "Active_file".to_string() => 9120 * 1024,
+            }
+        )
+    }
+}
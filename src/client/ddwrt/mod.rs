@@ -0,0 +1,67 @@
+mod load;
+mod mem;
+mod wificlients;
+
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::capture_str;
+pub use crate::client::ddwrt::load::DdwrtLoadClient;
+pub use crate::client::ddwrt::mem::DdwrtMemClient;
+pub use crate::client::ddwrt::wificlients::DdwrtWifiClientsClient;
+
+// DD-WRT's status pages are refreshed from *.live.asp endpoints that return a list of {name::value}
+// pairs
+pub fn parse_live(body: &str) -> BTreeMap<String, String> {
+    let pair_re = Regex::new(r"\{(?P<name>[a-zA-Z0-9_]+)::(?P<value>[^}]*)\}").unwrap();
+    pair_re
+        .captures_iter(body)
+        .filter_map(|capture| {
+            Some((
+                capture_str(&capture, "name").ok()?.to_string(),
+                capture_str(&capture, "value").ok()?.trim().to_string(),
+            ))
+        })
+        .collect()
+}
+
+// list values are written as comma separated, single quoted strings
+pub fn parse_list(value: &str) -> Vec<String> {
+    let item_re = Regex::new(r"'(?P<item>[^']*)'").unwrap();
+    item_re
+        .captures_iter(value)
+        .filter_map(|capture| capture_str(&capture, "item").ok().map(str::to_string))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_live() {
+        assert_eq!(
+            parse_live(
+                "{router_time::Thu, 12 Oct 2023 10:11:12}\n{uptime:: 10:11:12 up 3 days}\n{ip_conntrack::42}"
+            ),
+            btreemap! {
+                "router_time".to_string() => "Thu, 12 Oct 2023 10:11:12".to_string(),
+                "uptime".to_string() => "10:11:12 up 3 days".to_string(),
+                "ip_conntrack".to_string() => "42".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            parse_list("'MemTotal:','126700','kB'"),
+            vec![
+                "MemTotal:".to_string(),
+                "126700".to_string(),
+                "kB".to_string()
+            ]
+        )
+    }
+}
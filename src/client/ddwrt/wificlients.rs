@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::ddwrt::{parse_list, parse_live};
+use crate::client::{Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct DdwrtWifiClientsClient {
+    client: TomatoClientInternal,
+}
+
+impl DdwrtWifiClientsClient {
+    pub fn new(client: TomatoClientInternal) -> DdwrtWifiClientsClient {
+        DdwrtWifiClientsClient { client }
+    }
+
+    async fn get_clients(&self) -> Result<BTreeMap<String, u32>, ExporterError> {
+        let body = self.client.get_page("Status_Wireless.live.asp").await?;
+        Ok(DdwrtWifiClientsClient::parse_body(body))
+    }
+
+    // active_wireless lists every associated client starting with its MAC address and interface,
+    // followed by a number of fields that differs between builds
+    fn parse_body(body: String) -> BTreeMap<String, u32> {
+        let mac_re = Regex::new(r"^([0-9A-Fa-f]{2}:){5}[0-9A-Fa-f]{2}$").unwrap();
+        let vars = parse_live(body.as_str());
+        let items = parse_list(vars.get("active_wireless").map_or("", String::as_str));
+        let mut clients: BTreeMap<String, u32> = BTreeMap::new();
+        for (i, item) in items.iter().enumerate() {
+            if mac_re.is_match(item) {
+                if let Some(iface) = items.get(i + 1) {
+                    *clients.entry(iface.clone()).or_default() += 1;
+                }
+            }
+        }
+        clients
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, u32>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "wifi_clients",
+            "Number of clients associated with a wireless interface",
+            PromMetricType::Gauge,
+            raw_metrics
+                .into_iter()
+                .map(|(iface, clients)| {
+                    PromSample::new(vec![PromLabel::new("iface", iface)], clients as f64, None)
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for DdwrtWifiClientsClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_clients().await?;
+        Ok(DdwrtWifiClientsClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "wificlients".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "{wl_mac::00:11:22:33:44:55}
+{active_wireless::'AA:BB:CC:DD:EE:01','eth1','1:02:03','72M','144M','-45','-92','47','1000','AA:BB:CC:DD:EE:02','eth2','0:10:00','433M','433M','-61','-92','31','620','AA:BB:CC:DD:EE:03','eth1','0:00:12','6M','1M','-80','-92','12','200'}";
+        assert_eq!(
+            DdwrtWifiClientsClient::parse_body(body.to_string()),
+            btreemap! {
+                "eth1".to_string() => 2,
+                "eth2".to_string() => 1,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            DdwrtWifiClientsClient::raw_to_prom(btreemap! { "eth1".to_string() => 2 }),
+            vec![PromMetric::new(
+                "wifi_clients",
+                "Number of clients associated with a wireless interface",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    vec![PromLabel::new("iface", "eth1".to_string())],
+                    2f64,
+                    None
+                )],
+            )]
+        )
+    }
+}
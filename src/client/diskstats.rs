@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct DiskstatsClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct DiskStats {
+    pub sectors_read: u64,
+    pub sectors_written: u64,
+    pub io_time_ms: u64,
+}
+
+const SECTOR_SIZE: u64 = 512;
+
+impl DiskstatsClient {
+    pub fn new(client: TomatoClientInternal) -> DiskstatsClient {
+        DiskstatsClient { client }
+    }
+
+    async fn get_diskstats(&self) -> Result<BTreeMap<String, DiskStats>, ExporterError> {
+        let body = self
+            .client
+            .run_command("cat /proc/diskstats".to_string())
+            .await?;
+        Ok(DiskstatsClient::parse_body(body))
+    }
+
+    fn parse_disk(capture: &Captures) -> Result<(String, DiskStats), ExporterError> {
+        Ok((
+            capture_str(capture, "device")?.to_string(),
+            DiskStats {
+                sectors_read: parse_capture(capture, "sectors_read")?,
+                sectors_written: parse_capture(capture, "sectors_written")?,
+                io_time_ms: parse_capture(capture, "io_time_ms")?,
+            },
+        ))
+    }
+
+    // only whole disks are exported; partitions, ramdisks and flash blocks are skipped
+    fn parse_body(body: String) -> BTreeMap<String, DiskStats> {
+        let disk_re = Regex::new(r"(?m)^\s*[0-9]+\s+[0-9]+ (?P<device>sd[a-z]+|mmcblk[0-9]+) [0-9]+ [0-9]+ (?P<sectors_read>[0-9]+) [0-9]+ [0-9]+ [0-9]+ (?P<sectors_written>[0-9]+) [0-9]+ [0-9]+ (?P<io_time_ms>[0-9]+)").unwrap();
+        disk_re
+            .captures_iter(body.as_str())
+            .filter_map(|capture| {
+                DiskstatsClient::parse_disk(&capture)
+                    .inspect_err(|_| parse_warning(capture.get(0).map_or("", |m| m.as_str())))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, DiskStats>) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "node_disk_read_bytes_total",
+                "The total number of bytes read successfully",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .map(|(device, stats)| {
+                        PromSample::new(
+                            vec![PromLabel::new("device", device.to_string())],
+                            stats.sectors_read as f64 * SECTOR_SIZE as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "node_disk_written_bytes_total",
+                "The total number of bytes written successfully",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .map(|(device, stats)| {
+                        PromSample::new(
+                            vec![PromLabel::new("device", device.to_string())],
+                            stats.sectors_written as f64 * SECTOR_SIZE as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "node_disk_io_time_seconds_total",
+                "Total seconds spent doing I/Os",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .map(|(device, stats)| {
+                        PromSample::new(
+                            vec![PromLabel::new("device", device.to_string())],
+                            stats.io_time_ms as f64 / 1000f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for DiskstatsClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_diskstats().await?;
+        Ok(DiskstatsClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "diskstats".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "   1       0 ram0 0 0 0 0 0 0 0 0 0 0 0
+  31       0 mtdblock0 12 0 96 20 0 0 0 0 0 20 20
+   8       0 sda 12345 678 987654 4321 2345 123 456789 9876 0 7654 14197
+   8       1 sda1 12000 678 980000 4300 2345 123 456789 9876 0 7600 14176";
+        assert_eq!(
+            DiskstatsClient::parse_body(body.to_string()),
+            btreemap! {
+                "sda".to_string() => DiskStats {
+                    sectors_read: 987654,
+                    sectors_written: 456789,
+                    io_time_ms: 7654,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body =
+            "   8       0 sda 12345 678 99999999999999999999 4321 2345 123 456789 9876 0 7654 14197
+ 179       0 mmcblk0 100 0 2000 50 10 0 300 20 0 60 70";
+        assert_eq!(
+            DiskstatsClient::parse_body(body.to_string()),
+            btreemap! {
+                "mmcblk0".to_string() => DiskStats {
+                    sectors_read: 2000,
+                    sectors_written: 300,
+                    io_time_ms: 60,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            DiskstatsClient::raw_to_prom(btreemap! {
+                "sda".to_string() => DiskStats {
+                    sectors_read: 1000,
+                    sectors_written: 2000,
+                    io_time_ms: 7654,
+                },
+            }),
+            vec![
+                PromMetric::new(
+                    "node_disk_read_bytes_total",
+                    "The total number of bytes read successfully",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "sda".to_string())],
+                        512000f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "node_disk_written_bytes_total",
+                    "The total number of bytes written successfully",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "sda".to_string())],
+                        1024000f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "node_disk_io_time_seconds_total",
+                    "Total seconds spent doing I/Os",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "sda".to_string())],
+                        7.654f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
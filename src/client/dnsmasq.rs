@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct DnsmasqClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct DnsmasqStats {
+    pub cache_size: u64,
+    pub cache_evictions: u64,
+    pub cache_insertions: u64,
+    pub queries_forwarded: u64,
+    pub queries_local: u64,
+    pub servers: BTreeMap<String, DnsmasqServer>,
+}
+
+#[derive(Debug, PartialEq)]
+struct DnsmasqServer {
+    pub queries_sent: u64,
+    pub queries_failed: u64,
+}
+
+impl DnsmasqClient {
+    pub fn new(client: TomatoClientInternal) -> DnsmasqClient {
+        DnsmasqClient { client }
+    }
+
+    async fn get_dnsmasq(&self) -> Result<DnsmasqStats, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "kill -USR1 $(pidof dnsmasq) && sleep 1 && tail -n 100 /var/log/messages | grep dnsmasq"
+                    .to_string(),
+            )
+            .await?;
+        DnsmasqClient::parse_body(body)
+    }
+
+    // the log may hold several dumps, so the most recent one wins
+    fn parse_body(body: String) -> Result<DnsmasqStats, ExporterError> {
+        let cache_re = Regex::new(r"cache size (?P<size>[0-9]+), (?P<evictions>[0-9]+)/(?P<insertions>[0-9]+) cache insertions").unwrap();
+        let queries_re = Regex::new(
+            r"queries forwarded (?P<forwarded>[0-9]+), queries answered locally (?P<local>[0-9]+)",
+        )
+        .unwrap();
+        let server_re = Regex::new(r"server (?P<server>[^ ]+): queries sent (?P<sent>[0-9]+), retried or failed (?P<failed>[0-9]+)").unwrap();
+
+        let cache = cache_re
+            .captures_iter(body.as_str())
+            .last()
+            .ok_or_else(|| {
+                ExporterError::Parse("unable to parse dnsmasq cache statistics".to_string())
+            })?;
+        let queries = queries_re
+            .captures_iter(body.as_str())
+            .last()
+            .ok_or_else(|| {
+                ExporterError::Parse("unable to parse dnsmasq query statistics".to_string())
+            })?;
+        Ok(DnsmasqStats {
+            cache_size: parse_capture(&cache, "size")?,
+            cache_evictions: parse_capture(&cache, "evictions")?,
+            cache_insertions: parse_capture(&cache, "insertions")?,
+            queries_forwarded: parse_capture(&queries, "forwarded")?,
+            queries_local: parse_capture(&queries, "local")?,
+            servers: server_re
+                .captures_iter(body.as_str())
+                .filter_map(|capture| {
+                    DnsmasqClient::parse_server(&capture)
+                        .inspect_err(|_| parse_warning(capture.get(0).map_or("", |m| m.as_str())))
+                        .ok()
+                })
+                .collect(),
+        })
+    }
+
+    fn parse_server(capture: &Captures) -> Result<(String, DnsmasqServer), ExporterError> {
+        Ok((
+            capture_str(capture, "server")?.to_string(),
+            DnsmasqServer {
+                queries_sent: parse_capture(capture, "sent")?,
+                queries_failed: parse_capture(capture, "failed")?,
+            },
+        ))
+    }
+
+    fn raw_to_prom(raw_metrics: DnsmasqStats) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "dnsmasq_cache_size",
+                "Configured size of the DNS cache",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    Vec::new(),
+                    raw_metrics.cache_size as f64,
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "dnsmasq_cache_insertions_total",
+                "DNS cache insertions",
+                PromMetricType::Counter,
+                vec![PromSample::new(
+                    Vec::new(),
+                    raw_metrics.cache_insertions as f64,
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "dnsmasq_cache_evictions_total",
+                "DNS cache insertions that re-used unexpired cache entries",
+                PromMetricType::Counter,
+                vec![PromSample::new(
+                    Vec::new(),
+                    raw_metrics.cache_evictions as f64,
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "dnsmasq_cache_hits_total",
+                "DNS queries answered locally",
+                PromMetricType::Counter,
+                vec![PromSample::new(
+                    Vec::new(),
+                    raw_metrics.queries_local as f64,
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "dnsmasq_cache_misses_total",
+                "DNS queries forwarded to an upstream server",
+                PromMetricType::Counter,
+                vec![PromSample::new(
+                    Vec::new(),
+                    raw_metrics.queries_forwarded as f64,
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "dnsmasq_server_queries_total",
+                "DNS queries sent to an upstream server",
+                PromMetricType::Counter,
+                raw_metrics
+                    .servers
+                    .iter()
+                    .map(|(server, stats)| {
+                        PromSample::new(
+                            vec![PromLabel::new("server", server.to_string())],
+                            stats.queries_sent as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "dnsmasq_server_queries_failed_total",
+                "DNS queries to an upstream server that were retried or failed",
+                PromMetricType::Counter,
+                raw_metrics
+                    .servers
+                    .iter()
+                    .map(|(server, stats)| {
+                        PromSample::new(
+                            vec![PromLabel::new("server", server.to_string())],
+                            stats.queries_failed as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for DnsmasqClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_dnsmasq().await?;
+        Ok(DnsmasqClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "dnsmasq".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "Jun  5 15:54:45 karabor daemon.info dnsmasq[1234]: time 1622901285
+Jun  5 15:54:45 karabor daemon.info dnsmasq[1234]: cache size 4096, 0/5000 cache insertions re-used unexpired cache entries.
+Jun  5 15:54:45 karabor daemon.info dnsmasq[1234]: queries forwarded 12000, queries answered locally 6000
+Jun  5 15:54:45 karabor daemon.info dnsmasq[1234]: server 1.1.1.1#53: queries sent 6000, retried or failed 10
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: time 1622901345
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: cache size 4096, 12/5678 cache insertions re-used unexpired cache entries.
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: queries forwarded 12345, queries answered locally 6789
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: queries for authoritative zones 0
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: pool memory in use 0, max 0, allocated 0
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: server 1.1.1.1#53: queries sent 6100, retried or failed 12
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: server 8.8.8.8#53: queries sent 6245, retried or failed 3";
+        assert_eq!(
+            DnsmasqClient::parse_body(body.to_string()).unwrap(),
+            DnsmasqStats {
+                cache_size: 4096,
+                cache_evictions: 12,
+                cache_insertions: 5678,
+                queries_forwarded: 12345,
+                queries_local: 6789,
+                servers: btreemap! {
+                    "1.1.1.1#53".to_string() => DnsmasqServer { queries_sent: 6100, queries_failed: 12 },
+                    "8.8.8.8#53".to_string() => DnsmasqServer { queries_sent: 6245, queries_failed: 3 },
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed_server() {
+        let body = "Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: cache size 4096, 12/5678 cache insertions re-used unexpired cache entries.
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: queries forwarded 12345, queries answered locally 6789
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: server 1.1.1.1#53: queries sent 99999999999999999999, retried or failed 12
+Jun  5 15:55:45 karabor daemon.info dnsmasq[1234]: server 8.8.8.8#53: queries sent 6245, retried or failed 3";
+        assert_eq!(
+            DnsmasqClient::parse_body(body.to_string()).unwrap().servers,
+            btreemap! {
+                "8.8.8.8#53".to_string() => DnsmasqServer { queries_sent: 6245, queries_failed: 3 },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            DnsmasqClient::raw_to_prom(DnsmasqStats {
+                cache_size: 4096,
+                cache_evictions: 12,
+                cache_insertions: 5678,
+                queries_forwarded: 12345,
+                queries_local: 6789,
+                servers: btreemap! {
+                    "1.1.1.1#53".to_string() => DnsmasqServer { queries_sent: 6100, queries_failed: 12 },
+                },
+            }),
+            vec![
+                PromMetric::new(
+                    "dnsmasq_cache_size",
+                    "Configured size of the DNS cache",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 4096f64, None)],
+                ),
+                PromMetric::new(
+                    "dnsmasq_cache_insertions_total",
+                    "DNS cache insertions",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(Vec::new(), 5678f64, None)],
+                ),
+                PromMetric::new(
+                    "dnsmasq_cache_evictions_total",
+                    "DNS cache insertions that re-used unexpired cache entries",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(Vec::new(), 12f64, None)],
+                ),
+                PromMetric::new(
+                    "dnsmasq_cache_hits_total",
+                    "DNS queries answered locally",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(Vec::new(), 6789f64, None)],
+                ),
+                PromMetric::new(
+                    "dnsmasq_cache_misses_total",
+                    "DNS queries forwarded to an upstream server",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(Vec::new(), 12345f64, None)],
+                ),
+                PromMetric::new(
+                    "dnsmasq_server_queries_total",
+                    "DNS queries sent to an upstream server",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("server", "1.1.1.1#53".to_string())],
+                        6100f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "dnsmasq_server_queries_failed_total",
+                    "DNS queries to an upstream server that were retried or failed",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("server", "1.1.1.1#53".to_string())],
+                        12f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
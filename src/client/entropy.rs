@@ -0,0 +1,123 @@
+use regex::Regex;
+
+use crate::client::{parse_capture, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct EntropyClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct Entropy {
+    pub available_bits: u32,
+    pub pool_size_bits: u32,
+}
+
+impl EntropyClient {
+    pub fn new(client: TomatoClientInternal) -> EntropyClient {
+        EntropyClient { client }
+    }
+
+    async fn get_entropy(&self) -> Result<Entropy, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "cat /proc/sys/kernel/random/entropy_avail /proc/sys/kernel/random/poolsize"
+                    .to_string(),
+            )
+            .await?;
+        EntropyClient::parse_body(body)
+    }
+
+    fn parse_body(body: String) -> Result<Entropy, ExporterError> {
+        let body_parser_re = Regex::new(r"(?P<available>[0-9]+)\s+(?P<pool_size>[0-9]+)").unwrap();
+        let capture = body_parser_re
+            .captures(body.as_str().trim())
+            .ok_or_else(|| ExporterError::Parse("unable to parse entropy".to_string()))?;
+        Ok(Entropy {
+            available_bits: parse_capture(&capture, "available")?,
+            pool_size_bits: parse_capture(&capture, "pool_size")?,
+        })
+    }
+
+    fn raw_to_prom(raw_metrics: Entropy) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "node_entropy_available_bits",
+                "Bits of available entropy",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    Vec::new(),
+                    raw_metrics.available_bits as f64,
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "node_entropy_pool_size_bits",
+                "Bits of entropy pool",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    Vec::new(),
+                    raw_metrics.pool_size_bits as f64,
+                    None,
+                )],
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for EntropyClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_entropy().await?;
+        Ok(EntropyClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "entropy".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "163
+4096";
+        assert_eq!(
+            EntropyClient::parse_body(body.to_string()).unwrap(),
+            Entropy {
+                available_bits: 163,
+                pool_size_bits: 4096,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            EntropyClient::raw_to_prom(Entropy {
+                available_bits: 163,
+                pool_size_bits: 4096,
+            }),
+            vec![
+                PromMetric::new(
+                    "node_entropy_available_bits",
+                    "Bits of available entropy",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 163f64, None)],
+                ),
+                PromMetric::new(
+                    "node_entropy_pool_size_bits",
+                    "Bits of entropy pool",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 4096f64, None)],
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+
+use crate::client::{parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct FilesystemClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct Filesystem {
+    pub device: String,
+    pub mountpoint: String,
+    pub size_bytes: u64,
+    pub avail_bytes: u64,
+    pub files: Option<u64>,
+}
+
+const SECTION_SEPARATOR: &str = "-----";
+
+impl FilesystemClient {
+    pub fn new(client: TomatoClientInternal) -> FilesystemClient {
+        FilesystemClient { client }
+    }
+
+    async fn get_filesystems(&self) -> Result<BTreeMap<String, Filesystem>, ExporterError> {
+        let body = self
+            .client
+            .run_command(format!("df -k; echo '{}'; df -i", SECTION_SEPARATOR))
+            .await?;
+        Ok(FilesystemClient::parse_body(body))
+    }
+
+    // busybox wraps long device names onto their own line, which is joined with the row that
+    // follows it.  the mountpoint is everything after the fifth column, so it keeps any spaces
+    fn parse_df_rows(section: &str) -> Vec<Vec<String>> {
+        let mut device: Option<&str> = None;
+        let mut rows = Vec::new();
+        for line in section.trim().lines().skip(1) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if !line.contains(char::is_whitespace) && device.is_none() {
+                device = Some(line);
+                continue;
+            }
+            let mut row: Vec<String> = device.take().map(str::to_string).into_iter().collect();
+            let mut rest = line;
+            while row.len() < 5 {
+                let Some((field, tail)) = rest.split_once(char::is_whitespace) else {
+                    break;
+                };
+                row.push(field.to_string());
+                rest = tail.trim_start();
+            }
+            row.push(rest.to_string());
+            rows.push(row);
+        }
+        rows
+    }
+
+    fn parse_body(body: String) -> BTreeMap<String, Filesystem> {
+        let mut sections = body.split(SECTION_SEPARATOR);
+        let blocks = sections.next().unwrap_or_default();
+        let inodes: BTreeMap<String, u64> = sections
+            .next()
+            .map(|section| {
+                FilesystemClient::parse_df_rows(section)
+                    .into_iter()
+                    .filter_map(|row| match row.as_slice() {
+                        [_, files, _, _, _, mountpoint] => files
+                            .parse::<u64>()
+                            .ok()
+                            .map(|files| (mountpoint.clone(), files)),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        FilesystemClient::parse_df_rows(blocks)
+            .into_iter()
+            .filter_map(|row| {
+                let filesystem = match row.as_slice() {
+                    [device, size, _, avail, _, mountpoint] => size
+                        .parse::<u64>()
+                        .ok()
+                        .and_then(|size| size.checked_mul(1024))
+                        .zip(
+                            avail
+                                .parse::<u64>()
+                                .ok()
+                                .and_then(|avail| avail.checked_mul(1024)),
+                        )
+                        .map(|(size_bytes, avail_bytes)| Filesystem {
+                            device: device.clone(),
+                            mountpoint: mountpoint.clone(),
+                            size_bytes,
+                            avail_bytes,
+                            files: inodes.get(mountpoint).copied(),
+                        }),
+                    _ => None,
+                };
+                if filesystem.is_none() {
+                    parse_warning(row.join(" ").as_str());
+                }
+                filesystem.map(|fs| (fs.mountpoint.clone(), fs))
+            })
+            .collect()
+    }
+
+    fn labels(fs: &Filesystem) -> Vec<PromLabel> {
+        vec![
+            PromLabel::new("device", fs.device.clone()),
+            PromLabel::new("mountpoint", fs.mountpoint.clone()),
+        ]
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, Filesystem>) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "node_filesystem_size_bytes",
+                "Filesystem size in bytes",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .values()
+                    .map(|fs| {
+                        PromSample::new(FilesystemClient::labels(fs), fs.size_bytes as f64, None)
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "node_filesystem_avail_bytes",
+                "Filesystem space available to non-root users in bytes",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .values()
+                    .map(|fs| {
+                        PromSample::new(FilesystemClient::labels(fs), fs.avail_bytes as f64, None)
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "node_filesystem_files",
+                "Filesystem total file nodes",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .values()
+                    .filter_map(|fs| {
+                        fs.files.map(|files| {
+                            PromSample::new(FilesystemClient::labels(fs), files as f64, None)
+                        })
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for FilesystemClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_filesystems().await?;
+        Ok(FilesystemClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "filesystem".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "Filesystem           1K-blocks      Used Available Use% Mounted on
+/dev/root                30720     30720         0 100% /
+devfs                   127852         0    127852   0% /dev
+tmpfs                   127852      1236    126616   1% /tmp
+/dev/mtdblock/5
+                         32768      1420     31348   4% /jffs
+/dev/sda1             30234500  12345678  16351542  43% /mnt/usb
+-----
+Filesystem              Inodes      Used Available Use% Mounted on
+/dev/root                  652       652         0 100% /
+devfs                        0         0         0   0% /dev
+tmpfs                    31963        98     31865   0% /tmp
+/dev/mtdblock/5              0         0         0   0% /jffs
+/dev/sda1              1892352     10532   1881820   1% /mnt/usb";
+        assert_eq!(
+            FilesystemClient::parse_body(body.to_string()),
+            btreemap! {
+                "/".to_string() => Filesystem {
+                    device: "/dev/root".to_string(),
+                    mountpoint: "/".to_string(),
+                    size_bytes: 30720 * 1024,
+                    avail_bytes: 0,
+                    files: Some(652),
+                },
+                "/dev".to_string() => Filesystem {
+                    device: "devfs".to_string(),
+                    mountpoint: "/dev".to_string(),
+                    size_bytes: 127852 * 1024,
+                    avail_bytes: 127852 * 1024,
+                    files: Some(0),
+                },
+                "/tmp".to_string() => Filesystem {
+                    device: "tmpfs".to_string(),
+                    mountpoint: "/tmp".to_string(),
+                    size_bytes: 127852 * 1024,
+                    avail_bytes: 126616 * 1024,
+                    files: Some(31963),
+                },
+                "/jffs".to_string() => Filesystem {
+                    device: "/dev/mtdblock/5".to_string(),
+                    mountpoint: "/jffs".to_string(),
+                    size_bytes: 32768 * 1024,
+                    avail_bytes: 31348 * 1024,
+                    files: Some(0),
+                },
+                "/mnt/usb".to_string() => Filesystem {
+                    device: "/dev/sda1".to_string(),
+                    mountpoint: "/mnt/usb".to_string(),
+                    size_bytes: 30234500 * 1024,
+                    avail_bytes: 16351542 * 1024,
+                    files: Some(1892352),
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_mountpoint_with_space() {
+        let body = "Filesystem           1K-blocks      Used Available Use% Mounted on
+/dev/sda1             30234500  12345678  16351542  43% /mnt/My Passport
+tmpfs                   127852      1236    126616   1% /tmp";
+        assert_eq!(
+            FilesystemClient::parse_body(body.to_string()),
+            btreemap! {
+                "/mnt/My Passport".to_string() => Filesystem {
+                    device: "/dev/sda1".to_string(),
+                    mountpoint: "/mnt/My Passport".to_string(),
+                    size_bytes: 30234500 * 1024,
+                    avail_bytes: 16351542 * 1024,
+                    files: None,
+                },
+                "/tmp".to_string() => Filesystem {
+                    device: "tmpfs".to_string(),
+                    mountpoint: "/tmp".to_string(),
+                    size_bytes: 127852 * 1024,
+                    avail_bytes: 126616 * 1024,
+                    files: None,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "Filesystem           1K-blocks      Used Available Use% Mounted on
+tmpfs                   127852      1236    126616   1% /tmp
+/dev/sda1      99999999999999999999  12345678  16351542  43% /mnt/usb";
+        assert_eq!(
+            FilesystemClient::parse_body(body.to_string()),
+            btreemap! {
+                "/tmp".to_string() => Filesystem {
+                    device: "tmpfs".to_string(),
+                    mountpoint: "/tmp".to_string(),
+                    size_bytes: 127852 * 1024,
+                    avail_bytes: 126616 * 1024,
+                    files: None,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_no_inodes() {
+        let body = "Filesystem           1K-blocks      Used Available Use% Mounted on
+tmpfs                   127852      1236    126616   1% /tmp
+-----
+df: invalid option -- 'i'";
+        assert_eq!(
+            FilesystemClient::parse_body(body.to_string()),
+            btreemap! {
+                "/tmp".to_string() => Filesystem {
+                    device: "tmpfs".to_string(),
+                    mountpoint: "/tmp".to_string(),
+                    size_bytes: 127852 * 1024,
+                    avail_bytes: 126616 * 1024,
+                    files: None,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            FilesystemClient::raw_to_prom(btreemap! {
+                "/jffs".to_string() => Filesystem {
+                    device: "/dev/mtdblock/5".to_string(),
+                    mountpoint: "/jffs".to_string(),
+                    size_bytes: 32768 * 1024,
+                    avail_bytes: 31348 * 1024,
+                    files: None,
+                },
+                "/tmp".to_string() => Filesystem {
+                    device: "tmpfs".to_string(),
+                    mountpoint: "/tmp".to_string(),
+                    size_bytes: 127852 * 1024,
+                    avail_bytes: 126616 * 1024,
+                    files: Some(31963),
+                },
+            }),
+            vec![
+                PromMetric::new(
+                    "node_filesystem_size_bytes",
+                    "Filesystem size in bytes",
+                    PromMetricType::Gauge,
+                    vec![
+                        PromSample::new(
+                            vec![
+                                PromLabel::new("device", "/dev/mtdblock/5".to_string()),
+                                PromLabel::new("mountpoint", "/jffs".to_string()),
+                            ],
+                            (32768 * 1024) as f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![
+                                PromLabel::new("device", "tmpfs".to_string()),
+                                PromLabel::new("mountpoint", "/tmp".to_string()),
+                            ],
+                            (127852 * 1024) as f64,
+                            None
+                        ),
+                    ],
+                ),
+                PromMetric::new(
+                    "node_filesystem_avail_bytes",
+                    "Filesystem space available to non-root users in bytes",
+                    PromMetricType::Gauge,
+                    vec![
+                        PromSample::new(
+                            vec![
+                                PromLabel::new("device", "/dev/mtdblock/5".to_string()),
+                                PromLabel::new("mountpoint", "/jffs".to_string()),
+                            ],
+                            (31348 * 1024) as f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![
+                                PromLabel::new("device", "tmpfs".to_string()),
+                                PromLabel::new("mountpoint", "/tmp".to_string()),
+                            ],
+                            (126616 * 1024) as f64,
+                            None
+                        ),
+                    ],
+                ),
+                PromMetric::new(
+                    "node_filesystem_files",
+                    "Filesystem total file nodes",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(
+                        vec![
+                            PromLabel::new("device", "tmpfs".to_string()),
+                            PromLabel::new("mountpoint", "/tmp".to_string()),
+                        ],
+                        31963f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
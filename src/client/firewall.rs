@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct FirewallClient {
+    client: TomatoClientInternal,
+    chains: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct ChainCounters {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+impl FirewallClient {
+    pub fn new(client: TomatoClientInternal, chains: Vec<String>) -> FirewallClient {
+        FirewallClient { client, chains }
+    }
+
+    async fn get_chains(&self) -> Result<BTreeMap<String, ChainCounters>, ExporterError> {
+        // counters are read without -Z so that they keep increasing like any other counter
+        let body = self
+            .client
+            .run_command(format!(
+                "for c in {}; do iptables -L $c -v -n -x; done",
+                self.chains.join(" ")
+            ))
+            .await?;
+        Ok(FirewallClient::parse_body(body))
+    }
+
+    // a chain's counters are the sum of its rules' counters plus its policy counters, if it has one
+    fn parse_body(body: String) -> BTreeMap<String, ChainCounters> {
+        let chain_re = Regex::new(
+            r"^Chain (?P<chain>\S+) \((?:policy \S+ (?P<packets>[0-9]+) packets, (?P<bytes>[0-9]+) bytes)?",
+        )
+        .unwrap();
+        let rule_re = Regex::new(r"^\s*(?P<packets>[0-9]+)\s+(?P<bytes>[0-9]+)\s").unwrap();
+
+        let mut chains: BTreeMap<String, ChainCounters> = BTreeMap::new();
+        let mut current: Option<String> = None;
+        for line in body.lines() {
+            if let Some(capture) = chain_re.captures(line) {
+                // a builtin chain has policy counters, a user defined chain has none
+                let counters = match capture.name("packets") {
+                    Some(_) => FirewallClient::parse_counters(&capture),
+                    None => Ok(ChainCounters {
+                        packets: 0,
+                        bytes: 0,
+                    }),
+                };
+                current = match (capture_str(&capture, "chain"), counters) {
+                    (Ok(chain), Ok(counters)) => {
+                        chains.insert(chain.to_string(), counters);
+                        Some(chain.to_string())
+                    }
+                    _ => {
+                        parse_warning(line);
+                        None
+                    }
+                };
+            } else if let (Some(chain), Some(capture)) = (&current, rule_re.captures(line)) {
+                let Some(counters) = chains.get_mut(chain) else {
+                    continue;
+                };
+                match FirewallClient::parse_counters(&capture) {
+                    Ok(rule) => {
+                        counters.packets = counters.packets.saturating_add(rule.packets);
+                        counters.bytes = counters.bytes.saturating_add(rule.bytes);
+                    }
+                    Err(_) => parse_warning(line),
+                }
+            }
+        }
+        chains
+    }
+
+    fn parse_counters(capture: &Captures) -> Result<ChainCounters, ExporterError> {
+        Ok(ChainCounters {
+            packets: parse_capture(capture, "packets")?,
+            bytes: parse_capture(capture, "bytes")?,
+        })
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, ChainCounters>) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "firewall_chain_packets_total",
+                "Packets matched by the rules of an iptables chain",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .map(|(chain, counters)| {
+                        PromSample::new(
+                            vec![PromLabel::new("chain", chain.to_string())],
+                            counters.packets as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "firewall_chain_bytes_total",
+                "Bytes matched by the rules of an iptables chain",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .map(|(chain, counters)| {
+                        PromSample::new(
+                            vec![PromLabel::new("chain", chain.to_string())],
+                            counters.bytes as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for FirewallClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_chains().await?;
+        Ok(FirewallClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "firewall".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "Chain INPUT (policy DROP 120 packets, 8000 bytes)
+    pkts      bytes target     prot opt in     out     source               destination
+    4000   300000 ACCEPT     all  --  lo     *       0.0.0.0/0            0.0.0.0/0
+      12      720 ACCEPT     tcp  --  *      *       0.0.0.0/0            0.0.0.0/0           tcp dpt:22
+Chain wanin (1 references)
+    pkts      bytes target     prot opt in     out     source               destination
+      30     1800 ACCEPT     tcp  --  *      *       0.0.0.0/0            192.168.1.10        tcp dpt:443
+       5      300 ACCEPT     udp  --  *      *       0.0.0.0/0            192.168.1.10        udp dpt:1194";
+        assert_eq!(
+            FirewallClient::parse_body(body.to_string()),
+            btreemap! {
+                "INPUT".to_string() => ChainCounters { packets: 4132, bytes: 308720 },
+                "wanin".to_string() => ChainCounters { packets: 35, bytes: 2100 },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "Chain INPUT (policy DROP 99999999999999999999 packets, 8000 bytes)
+    4000   300000 ACCEPT     all  --  lo     *       0.0.0.0/0            0.0.0.0/0
+Chain wanin (1 references)
+    pkts      bytes target     prot opt in     out     source               destination
+      30     1800 ACCEPT     tcp  --  *      *       0.0.0.0/0            192.168.1.10        tcp dpt:443
+99999999999999999999 300 ACCEPT     udp  --  *      *       0.0.0.0/0            192.168.1.10        udp dpt:1194";
+        assert_eq!(
+            FirewallClient::parse_body(body.to_string()),
+            btreemap! {
+                "wanin".to_string() => ChainCounters { packets: 30, bytes: 1800 },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            FirewallClient::raw_to_prom(btreemap! {
+                "wanin".to_string() => ChainCounters { packets: 35, bytes: 2100 },
+            }),
+            vec![
+                PromMetric::new(
+                    "firewall_chain_packets_total",
+                    "Packets matched by the rules of an iptables chain",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("chain", "wanin".to_string())],
+                        35f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "firewall_chain_bytes_total",
+                    "Bytes matched by the rules of an iptables chain",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("chain", "wanin".to_string())],
+                        2100f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct HwmonClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Sensor {
+    pub chip: String,
+    pub sensor: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct HwmonReadings {
+    pub volts: BTreeMap<Sensor, f64>,
+    pub fan_rpm: BTreeMap<Sensor, f64>,
+}
+
+impl HwmonClient {
+    pub fn new(client: TomatoClientInternal) -> HwmonClient {
+        HwmonClient { client }
+    }
+
+    async fn get_hwmon(&self) -> Result<HwmonReadings, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "for f in /sys/class/hwmon/hwmon*/in*_input /sys/class/hwmon/hwmon*/fan*_input; do \
+                    [ -f \"$f\" ] && echo \"$f $(cat $f)\"; \
+                done"
+                    .to_string(),
+            )
+            .await?;
+        Ok(HwmonClient::parse_body(body))
+    }
+
+    // boards without sensors produce no output, which yields empty metrics rather than an error
+    fn parse_body(body: String) -> HwmonReadings {
+        let sensor_re = Regex::new(
+            r"(?m)^/sys/class/hwmon/(?P<chip>hwmon[0-9]+)/(?P<sensor>(?P<kind>in|fan)[0-9]+)_input (?P<value>-?[0-9]+)$",
+        )
+        .unwrap();
+        let mut readings = HwmonReadings {
+            volts: BTreeMap::new(),
+            fan_rpm: BTreeMap::new(),
+        };
+        for capture in sensor_re.captures_iter(body.as_str()) {
+            let reading = (
+                capture_str(&capture, "chip"),
+                capture_str(&capture, "sensor"),
+                capture_str(&capture, "kind"),
+                parse_capture::<f64>(&capture, "value"),
+            );
+            let (Ok(chip), Ok(sensor), Ok(kind), Ok(value)) = reading else {
+                parse_warning(capture.get(0).map_or("", |m| m.as_str()));
+                continue;
+            };
+            let sensor = Sensor {
+                chip: chip.to_string(),
+                sensor: sensor.to_string(),
+            };
+            match kind {
+                "in" => readings.volts.insert(sensor, value / 1000f64),
+                _ => readings.fan_rpm.insert(sensor, value),
+            };
+        }
+        readings
+    }
+
+    fn samples(readings: BTreeMap<Sensor, f64>) -> Vec<PromSample> {
+        readings
+            .into_iter()
+            .map(|(sensor, value)| {
+                PromSample::new(
+                    vec![
+                        PromLabel::new("chip", sensor.chip),
+                        PromLabel::new("sensor", sensor.sensor),
+                    ],
+                    value,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: HwmonReadings) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "node_hwmon_in_volts",
+                "Hardware monitor for voltage (input)",
+                PromMetricType::Gauge,
+                HwmonClient::samples(raw_metrics.volts),
+            ),
+            PromMetric::new(
+                "node_hwmon_fan_rpm",
+                "Hardware monitor for fan revolutions per minute (input)",
+                PromMetricType::Gauge,
+                HwmonClient::samples(raw_metrics.fan_rpm),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for HwmonClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_hwmon().await?;
+        Ok(HwmonClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "hwmon".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "/sys/class/hwmon/hwmon0/in0_input 1200
+/sys/class/hwmon/hwmon0/in1_input 3312
+/sys/class/hwmon/hwmon0/fan1_input 2400";
+        assert_eq!(
+            HwmonClient::parse_body(body.to_string()),
+            HwmonReadings {
+                volts: btreemap! {
+                    Sensor { chip: "hwmon0".to_string(), sensor: "in0".to_string() } => 1.2f64,
+                    Sensor { chip: "hwmon0".to_string(), sensor: "in1".to_string() } => 3.312f64,
+                },
+                fan_rpm: btreemap! {
+                    Sensor { chip: "hwmon0".to_string(), sensor: "fan1".to_string() } => 2400f64,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_unsupported() {
+        assert_eq!(
+            HwmonClient::parse_body("".to_string()),
+            HwmonReadings {
+                volts: BTreeMap::new(),
+                fan_rpm: BTreeMap::new(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            HwmonClient::raw_to_prom(HwmonReadings {
+                volts: btreemap! {
+                    Sensor { chip: "hwmon0".to_string(), sensor: "in0".to_string() } => 1.2f64,
+                },
+                fan_rpm: BTreeMap::new(),
+            }),
+            vec![
+                PromMetric::new(
+                    "node_hwmon_in_volts",
+                    "Hardware monitor for voltage (input)",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(
+                        vec![
+                            PromLabel::new("chip", "hwmon0".to_string()),
+                            PromLabel::new("sensor", "in0".to_string()),
+                        ],
+                        1.2f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "node_hwmon_fan_rpm",
+                    "Hardware monitor for fan revolutions per minute (input)",
+                    PromMetricType::Gauge,
+                    vec![],
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct IpTrafficClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct ClientTraffic {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+impl IpTrafficClient {
+    pub fn new(client: TomatoClientInternal) -> IpTrafficClient {
+        IpTrafficClient { client }
+    }
+
+    async fn get_traffic(&self) -> Result<BTreeMap<String, ClientTraffic>, ExporterError> {
+        let body = self
+            .client
+            .make_request(
+                "update.cgi".to_string(),
+                Some(hashmap! {
+                    "exec".to_string() => "iptmon".to_string(),
+                }),
+            )
+            .await?;
+        Ok(IpTrafficClient::parse_body(body))
+    }
+
+    fn parse_cap_hex(capture: &Captures, field: &str) -> Result<u64, ExporterError> {
+        let value = capture_str(capture, field)?;
+        u64::from_str_radix(value, 16)
+            .map_err(|_| ExporterError::Parse(format!("{} is out of range: 0x{}", field, value)))
+    }
+
+    fn parse_client(capture: &Captures) -> Result<(String, ClientTraffic), ExporterError> {
+        Ok((
+            capture_str(capture, "ip")?.to_string(),
+            ClientTraffic {
+                rx_bytes: IpTrafficClient::parse_cap_hex(capture, "rx")?,
+                tx_bytes: IpTrafficClient::parse_cap_hex(capture, "tx")?,
+            },
+        ))
+    }
+
+    fn parse_body(body: String) -> BTreeMap<String, ClientTraffic> {
+        let client_re = Regex::new(
+            r"'(?P<ip>[0-9a-fA-F.:]+)':\s*\{\s*rx:\s*0x(?P<rx>[0-9a-fA-F]+),\s*tx:\s*0x(?P<tx>[0-9a-fA-F]+)\s*\}",
+        )
+        .unwrap();
+        client_re
+            .captures_iter(body.as_str().trim())
+            .filter_map(|capture| {
+                IpTrafficClient::parse_client(&capture)
+                    .inspect_err(|_| parse_warning(capture.get(0).map_or("", |m| m.as_str())))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, ClientTraffic>) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "lan_client_receive_bytes_total",
+                "IP Traffic statistic receive_bytes per LAN client",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .map(|(ip, traffic)| {
+                        PromSample::new(
+                            vec![PromLabel::new("ip", ip.to_string())],
+                            traffic.rx_bytes as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "lan_client_transmit_bytes_total",
+                "IP Traffic statistic transmit_bytes per LAN client",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .map(|(ip, traffic)| {
+                        PromSample::new(
+                            vec![PromLabel::new("ip", ip.to_string())],
+                            traffic.tx_bytes as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for IpTrafficClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_traffic().await?;
+        Ok(IpTrafficClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "iptraffic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "
+
+iptmon={'192.168.1.10':{rx:0x2d3c1a,tx:0x1f4a2},'192.168.1.23':{rx:0x0,tx:0x0},'192.168.1.105':{rx:0x5f5e1000,tx:0x3b9aca0}};
+";
+        assert_eq!(
+            IpTrafficClient::parse_body(body.to_string()),
+            btreemap! {
+                "192.168.1.10".to_string() => ClientTraffic { rx_bytes: 0x2d3c1a, tx_bytes: 0x1f4a2 },
+                "192.168.1.23".to_string() => ClientTraffic { rx_bytes: 0, tx_bytes: 0 },
+                "192.168.1.105".to_string() => ClientTraffic { rx_bytes: 0x5f5e1000, tx_bytes: 0x3b9aca0 },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "iptmon={'192.168.1.10':{rx:0x2d3c1a,tx:0x1f4a2},'192.168.1.23':{rx:0x10000000000000000,tx:0x0}};";
+        assert_eq!(
+            IpTrafficClient::parse_body(body.to_string()),
+            btreemap! {
+                "192.168.1.10".to_string() => ClientTraffic { rx_bytes: 0x2d3c1a, tx_bytes: 0x1f4a2 },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            IpTrafficClient::raw_to_prom(btreemap! {
+                "192.168.1.10".to_string() => ClientTraffic { rx_bytes: 2964506, tx_bytes: 128162 },
+                "192.168.1.23".to_string() => ClientTraffic { rx_bytes: 0, tx_bytes: 0 },
+            }),
+            vec![
+                PromMetric::new(
+                    "lan_client_receive_bytes_total",
+                    "IP Traffic statistic receive_bytes per LAN client",
+                    PromMetricType::Counter,
+                    vec![
+                        PromSample::new(
+                            vec![PromLabel::new("ip", "192.168.1.10".to_string())],
+                            2964506f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("ip", "192.168.1.23".to_string())],
+                            0f64,
+                            None
+                        ),
+                    ],
+                ),
+                PromMetric::new(
+                    "lan_client_transmit_bytes_total",
+                    "IP Traffic statistic transmit_bytes per LAN client",
+                    PromMetricType::Counter,
+                    vec![
+                        PromSample::new(
+                            vec![PromLabel::new("ip", "192.168.1.10".to_string())],
+                            128162f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("ip", "192.168.1.23".to_string())],
+                            0f64,
+                            None
+                        ),
+                    ],
+                ),
+            ]
+        )
+    }
+}
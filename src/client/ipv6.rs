@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::jsx::{parse_assignment, unquote};
+use crate::client::{
+    capture_str, parse_capture, parse_warning, DeviceFilter, Scraper, TomatoClientInternal,
+};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct Ipv6Client {
+    client: TomatoClientInternal,
+    devices: DeviceFilter,
+}
+
+#[derive(Debug, PartialEq)]
+struct Ipv6Addresses {
+    pub wan_addr: String,
+    pub lan_addr: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Ipv6Traffic {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+impl Ipv6Client {
+    pub fn new(client: TomatoClientInternal, devices: DeviceFilter) -> Ipv6Client {
+        Ipv6Client { client, devices }
+    }
+
+    async fn get_ipv6(
+        &self,
+    ) -> Result<(Ipv6Addresses, BTreeMap<String, Ipv6Traffic>), ExporterError> {
+        let status = self
+            .client
+            .make_request("status-data.jsx".to_string(), None)
+            .await?;
+        let traffic = self
+            .client
+            .run_command(
+                "for f in /proc/net/dev_snmp6/*; do \
+                    [ -f \"$f\" ] || continue; \
+                    echo \"==> ${f##*/} <==\"; \
+                    grep -E '^Ip6(In|Out)Octets' $f; \
+                done"
+                    .to_string(),
+            )
+            .await?;
+        let mut traffic = Ipv6Client::parse_traffic(traffic);
+        traffic.retain(|device, _| self.devices.matches(device));
+        Ok((Ipv6Client::parse_addresses(status), traffic))
+    }
+
+    // the status page separates multiple addresses with <br>; only the first one is kept
+    fn parse_addresses(body: String) -> Ipv6Addresses {
+        let address = |name: &str| {
+            parse_assignment(body.as_str(), name)
+                .map(|value| {
+                    unquote(value.as_str())
+                        .split("<br>")
+                        .next()
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string()
+                })
+                .unwrap_or_default()
+        };
+        Ipv6Addresses {
+            wan_addr: address("stats.ip6_wan"),
+            lan_addr: address("stats.ip6_lan"),
+        }
+    }
+
+    // routers with ipv6 disabled have no dev_snmp6 directory, which yields no traffic counters
+    fn parse_traffic(body: String) -> BTreeMap<String, Ipv6Traffic> {
+        let header_re = Regex::new(r"(?m)^==> (?P<device>\S+) <==$").unwrap();
+        let octets_re =
+            Regex::new(r"(?m)^Ip6(?P<direction>In|Out)Octets\s+(?P<value>[0-9]+)$").unwrap();
+
+        let headers: Vec<_> = header_re.captures_iter(body.as_str()).collect();
+        headers
+            .iter()
+            .enumerate()
+            .map(|(i, capture)| {
+                let start = capture.get(0).map_or(0, |m| m.end());
+                let end = headers
+                    .get(i + 1)
+                    .and_then(|next| next.get(0))
+                    .map_or(body.len(), |next| next.start());
+                let mut traffic = Ipv6Traffic {
+                    rx_bytes: 0,
+                    tx_bytes: 0,
+                };
+                for octets in octets_re.captures_iter(&body[start..end]) {
+                    let Ok(value) = parse_capture::<u64>(&octets, "value") else {
+                        parse_warning(octets.get(0).map_or("", |m| m.as_str()));
+                        continue;
+                    };
+                    match capture_str(&octets, "direction") {
+                        Ok("In") => traffic.rx_bytes = value,
+                        _ => traffic.tx_bytes = value,
+                    }
+                }
+                (
+                    capture_str(capture, "device")
+                        .unwrap_or_default()
+                        .to_string(),
+                    traffic,
+                )
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(
+        addresses: Ipv6Addresses,
+        traffic: BTreeMap<String, Ipv6Traffic>,
+    ) -> Vec<PromMetric> {
+        vec![
+            PromMetric::info(
+                "ipv6_info",
+                "Labeled IPv6 address information",
+                vec![vec![
+                    PromLabel::new("wan_addr", addresses.wan_addr),
+                    PromLabel::new("lan_addr", addresses.lan_addr),
+                ]],
+            ),
+            PromMetric::new(
+                "ipv6_receive_bytes_total",
+                "IPv6 bytes received per network device",
+                PromMetricType::Counter,
+                traffic
+                    .iter()
+                    .map(|(device, traffic)| {
+                        PromSample::new(
+                            vec![PromLabel::new("device", device.to_string())],
+                            traffic.rx_bytes as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "ipv6_transmit_bytes_total",
+                "IPv6 bytes transmitted per network device",
+                PromMetricType::Counter,
+                traffic
+                    .iter()
+                    .map(|(device, traffic)| {
+                        PromSample::new(
+                            vec![PromLabel::new("device", device.to_string())],
+                            traffic.tx_bytes as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for Ipv6Client {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let (addresses, traffic) = self.get_ipv6().await?;
+        Ok(Ipv6Client::raw_to_prom(addresses, traffic))
+    }
+
+    fn get_name(&self) -> String {
+        "ipv6".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_addresses() {
+        let body = "
+stats = { };
+
+do {
+	stats.wanup = '1' == '1';
+	stats.ip6_wan = '2001:db8::45<br>fe80::1';
+	stats.ip6_lan = '2001:db8:1::1';
+} while (0);
+";
+        assert_eq!(
+            Ipv6Client::parse_addresses(body.to_string()),
+            Ipv6Addresses {
+                wan_addr: "2001:db8::45".to_string(),
+                lan_addr: "2001:db8:1::1".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_addresses_disabled() {
+        assert_eq!(
+            Ipv6Client::parse_addresses("stats = { };".to_string()),
+            Ipv6Addresses {
+                wan_addr: "".to_string(),
+                lan_addr: "".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_traffic() {
+        let body = "==> br0 <==
+Ip6InOctets                     	123456
+Ip6OutOctets                    	654321
+==> vlan2 <==
+Ip6InOctets                     	9876543210
+Ip6OutOctets                    	1234567890
+";
+        assert_eq!(
+            Ipv6Client::parse_traffic(body.to_string()),
+            btreemap! {
+                "br0".to_string() => Ipv6Traffic { rx_bytes: 123456, tx_bytes: 654321 },
+                "vlan2".to_string() => Ipv6Traffic { rx_bytes: 9876543210, tx_bytes: 1234567890 },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            Ipv6Client::raw_to_prom(
+                Ipv6Addresses {
+                    wan_addr: "2001:db8::45".to_string(),
+                    lan_addr: "2001:db8:1::1".to_string(),
+                },
+                btreemap! {
+                    "vlan2".to_string() => Ipv6Traffic { rx_bytes: 9876543210, tx_bytes: 1234567890 },
+                }
+            ),
+            vec![
+                PromMetric::new(
+                    "ipv6_info",
+                    "Labeled IPv6 address information",
+                    PromMetricType::Info,
+                    vec![PromSample::new(
+                        vec![
+                            PromLabel::new("wan_addr", "2001:db8::45".to_string()),
+                            PromLabel::new("lan_addr", "2001:db8:1::1".to_string()),
+                        ],
+                        1f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "ipv6_receive_bytes_total",
+                    "IPv6 bytes received per network device",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "vlan2".to_string())],
+                        9876543210f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "ipv6_transmit_bytes_total",
+                    "IPv6 bytes transmitted per network device",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "vlan2".to_string())],
+                        1234567890f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
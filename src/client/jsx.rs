@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::capture_str;
+
+pub fn parse_nvram(body: &str) -> BTreeMap<String, String> {
+    let block_re = Regex::new(r"(?s)nvram\s*=\s*\{(?P<entries>.*?)\};").unwrap();
+    let entry_re = Regex::new(r"'(?P<key>[^']+)'\s*:\s*'(?P<value>(?:[^'\\]|\\.)*)'").unwrap();
+    block_re
+        .captures(body)
+        .map(|block| {
+            entry_re
+                .captures_iter(capture_str(&block, "entries").unwrap_or_default())
+                .filter_map(|entry| {
+                    Some((
+                        capture_str(&entry, "key").ok()?.to_string(),
+                        capture_str(&entry, "value").ok()?.to_string(),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn parse_assignment(body: &str, name: &str) -> Option<String> {
+    let assign_re =
+        Regex::new(format!(r"(?m)^\s*{}\s*=\s*(?P<value>[^;\n]*);", regex::escape(name)).as_str())
+            .unwrap();
+    assign_re
+        .captures(body)
+        .and_then(|capture| capture_str(&capture, "value").ok())
+        .map(|value| value.trim().to_string())
+}
+
+pub fn unquote(value: &str) -> String {
+    value
+        .trim()
+        .trim_start_matches(['\'', '"'])
+        .trim_end_matches(['\'', '"'])
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BODY: &str = "
+nvram = {
+	'wan_proto': 'dhcp',
+	'wan_ipaddr': '203.0.113.45',
+	'wan_gateway_get': '203.0.113.1',
+	'router_name': 'karabor'};
+
+stats = { };
+
+do {
+	stats.wanip = nvram.wan_ipaddr;
+	stats.wanup = '1' == '1';
+	stats.wanuptime = '2 days, 03:04:05';
+} while (0);
+";
+
+    #[test]
+    fn test_parse_nvram() {
+        assert_eq!(
+            parse_nvram(BODY),
+            btreemap! {
+                "wan_proto".to_string() => "dhcp".to_string(),
+                "wan_ipaddr".to_string() => "203.0.113.45".to_string(),
+                "wan_gateway_get".to_string() => "203.0.113.1".to_string(),
+                "router_name".to_string() => "karabor".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        assert_eq!(
+            parse_assignment(BODY, "stats.wanuptime").map(|val| unquote(val.as_str())),
+            Some("2 days, 03:04:05".to_string())
+        );
+        assert_eq!(parse_assignment(BODY, "stats.missing"), None);
+    }
+}
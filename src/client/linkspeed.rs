@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct LinkSpeedClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct LinkSettings {
+    pub speed_mbps: u64,
+    pub full_duplex: bool,
+}
+
+impl LinkSpeedClient {
+    pub fn new(client: TomatoClientInternal) -> LinkSpeedClient {
+        LinkSpeedClient { client }
+    }
+
+    async fn get_link_speeds(&self) -> Result<BTreeMap<String, LinkSettings>, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "for d in /sys/class/net/*; do \
+                    echo \"${d##*/} $(cat $d/speed 2>/dev/null) $(cat $d/duplex 2>/dev/null)\"; \
+                done; \
+                robocfg show 2>/dev/null"
+                    .to_string(),
+            )
+            .await?;
+        Ok(LinkSpeedClient::parse_body(body))
+    }
+
+    // broadcom switch ports are only visible to robocfg and are reported as portN; ports without a
+    // link and interfaces that don't report a speed are skipped
+    fn parse_body(body: String) -> BTreeMap<String, LinkSettings> {
+        let sysfs_re =
+            Regex::new(r"(?m)^(?P<device>\S+) (?P<speed>[0-9]+) (?P<duplex>full|half)$").unwrap();
+        let robocfg_re =
+            Regex::new(r"(?m)^Port (?P<port>[0-9]+):\s+(?P<speed>[0-9]+)(?P<duplex>FD|HD)\s")
+                .unwrap();
+
+        let mut links: BTreeMap<String, LinkSettings> = BTreeMap::new();
+        for capture in sysfs_re.captures_iter(body.as_str()) {
+            match (
+                capture_str(&capture, "device"),
+                LinkSpeedClient::parse_link(&capture, "full"),
+            ) {
+                (Ok(device), Ok(link)) => {
+                    links.insert(device.to_string(), link);
+                }
+                _ => parse_warning(capture.get(0).map_or("", |m| m.as_str())),
+            }
+        }
+        for capture in robocfg_re.captures_iter(body.as_str()) {
+            match (
+                capture_str(&capture, "port"),
+                LinkSpeedClient::parse_link(&capture, "FD"),
+            ) {
+                (Ok(port), Ok(link)) => {
+                    links.insert(format!("port{}", port), link);
+                }
+                _ => parse_warning(capture.get(0).map_or("", |m| m.as_str())),
+            }
+        }
+        links
+    }
+
+    fn parse_link(capture: &Captures, full_duplex: &str) -> Result<LinkSettings, ExporterError> {
+        Ok(LinkSettings {
+            speed_mbps: parse_capture(capture, "speed")?,
+            full_duplex: capture_str(capture, "duplex")? == full_duplex,
+        })
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, LinkSettings>) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "node_network_speed_bytes",
+                "Network device property: speed_bytes",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .iter()
+                    .map(|(device, link)| {
+                        PromSample::new(
+                            vec![PromLabel::new("device", device.to_string())],
+                            (link.speed_mbps * 1000 * 1000 / 8) as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "node_network_full_duplex",
+                "Whether the network device negotiated full duplex",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .iter()
+                    .map(|(device, link)| {
+                        PromSample::new(
+                            vec![PromLabel::new("device", device.to_string())],
+                            if link.full_duplex { 1f64 } else { 0f64 },
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for LinkSpeedClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_link_speeds().await?;
+        Ok(LinkSpeedClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "linkspeed".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "br0
+eth0 1000 full
+eth1
+lo
+vlan1
+Switch: enabled
+Port 0:   DOWN enabled stp: none vlan: 2 jumbo: off mac: 00:00:00:00:00:00
+Port 1: 1000FD enabled stp: none vlan: 1 jumbo: off mac: 00:11:22:33:44:55
+Port 2:  100HD enabled stp: none vlan: 1 jumbo: off mac: 00:11:22:33:44:66
+Port 8: 1000FD enabled stp: none vlan: 2 jumbo: off mac: 00:11:22:33:44:77";
+        assert_eq!(
+            LinkSpeedClient::parse_body(body.to_string()),
+            btreemap! {
+                "eth0".to_string() => LinkSettings { speed_mbps: 1000, full_duplex: true },
+                "port1".to_string() => LinkSettings { speed_mbps: 1000, full_duplex: true },
+                "port2".to_string() => LinkSettings { speed_mbps: 100, full_duplex: false },
+                "port8".to_string() => LinkSettings { speed_mbps: 1000, full_duplex: true },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "eth0 1000 full
+eth1 99999999999999999999 full
+Port 1: 99999999999999999999FD enabled stp: none vlan: 1 jumbo: off mac: 00:11:22:33:44:55
+Port 2:  100HD enabled stp: none vlan: 1 jumbo: off mac: 00:11:22:33:44:66";
+        assert_eq!(
+            LinkSpeedClient::parse_body(body.to_string()),
+            btreemap! {
+                "eth0".to_string() => LinkSettings { speed_mbps: 1000, full_duplex: true },
+                "port2".to_string() => LinkSettings { speed_mbps: 100, full_duplex: false },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            LinkSpeedClient::raw_to_prom(btreemap! {
+                "port2".to_string() => LinkSettings { speed_mbps: 100, full_duplex: false },
+            }),
+            vec![
+                PromMetric::new(
+                    "node_network_speed_bytes",
+                    "Network device property: speed_bytes",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "port2".to_string())],
+                        12500000f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "node_network_full_duplex",
+                    "Whether the network device negotiated full duplex",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "port2".to_string())],
+                        0f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
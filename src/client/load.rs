@@ -1,7 +1,8 @@
-use regex::{Captures, Regex};
+use regex::Regex;
 
-use crate::client::{Scraper, TomatoClientInternal};
-use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+use crate::client::{parse_capture, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::PromMetric;
 
 #[derive(Clone)]
 pub struct LoadClient {
@@ -21,96 +22,58 @@ impl LoadClient {
         LoadClient { client }
     }
 
-    async fn get_time(&self) -> Result<LoadInfo, reqwest::Error> {
+    async fn get_time(&self) -> Result<LoadInfo, ExporterError> {
         let body = self
             .client
             .run_command("cat /proc/loadavg".to_string())
             .await?;
-        Ok(LoadClient::parse_body(body))
+        LoadClient::parse_body(body)
     }
 
-    fn parse_cap_f32(capture: &Captures, field: &str) -> f32 {
-        capture
-            .name(field)
-            .unwrap()
-            .as_str()
-            .parse::<f32>()
-            .unwrap()
-    }
-
-    fn parse_cap_u32(capture: &Captures, field: &str) -> u32 {
-        capture
-            .name(field)
-            .unwrap()
-            .as_str()
-            .parse::<u32>()
-            .unwrap()
-    }
-
-    fn parse_body(body: String) -> LoadInfo {
+    fn parse_body(body: String) -> Result<LoadInfo, ExporterError> {
         let body_parser_re =
             Regex::new(r"(?P<load_1m>[0-9]+.[0-9]+) (?P<load_5m>[0-9]+.[0-9]+) (?P<load_15m>[0-9]+.[0-9]+) (?P<running>[0-9]+)/(?P<total_procs>[0-9]+) (?P<last_pid>[0-9]+)")
                 .unwrap();
-        body_parser_re
+        let capture = body_parser_re
             .captures(body.as_str().trim())
-            .map(|capture| LoadInfo {
-                load_1m: LoadClient::parse_cap_f32(&capture, "load_1m"),
-                load_5m: LoadClient::parse_cap_f32(&capture, "load_5m"),
-                load_15m: LoadClient::parse_cap_f32(&capture, "load_15m"),
-                total_procs: LoadClient::parse_cap_u32(&capture, "total_procs"),
-            })
-            .expect("Unable to parse load")
+            .ok_or_else(|| ExporterError::Parse("unable to parse load".to_string()))?;
+        Ok(LoadInfo {
+            load_1m: parse_capture(&capture, "load_1m")?,
+            load_5m: parse_capture(&capture, "load_5m")?,
+            load_15m: parse_capture(&capture, "load_15m")?,
+            total_procs: parse_capture(&capture, "total_procs")?,
+        })
     }
 
     fn raw_to_prom(raw_metrics: LoadInfo) -> Vec<PromMetric> {
         vec![
-            PromMetric::new(
-                "node_load1",
-                "1m load average",
-                PromMetricType::Gauge,
-                vec![PromSample::new(
-                    Vec::new(),
-                    raw_metrics.load_1m as f64,
-                    None,
-                )],
-            ),
-            PromMetric::new(
-                "node_load5",
-                "5m load average",
-                PromMetricType::Gauge,
-                vec![PromSample::new(
-                    Vec::new(),
-                    raw_metrics.load_5m as f64,
-                    None,
-                )],
-            ),
-            PromMetric::new(
-                "node_load15",
-                "15m load average",
-                PromMetricType::Gauge,
-                vec![PromSample::new(
-                    Vec::new(),
-                    raw_metrics.load_15m as f64,
-                    None,
-                )],
-            ),
-            PromMetric::new(
-                "node_processes_pids",
-                "Number of PIDs",
-                PromMetricType::Gauge,
-                vec![PromSample::new(
-                    Vec::new(),
-                    raw_metrics.total_procs as f64,
-                    None,
-                )],
-            ),
+            PromMetric::build("node_load1")
+                .help("1m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_1m as f64))
+                .finish(),
+            PromMetric::build("node_load5")
+                .help("5m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_5m as f64))
+                .finish(),
+            PromMetric::build("node_load15")
+                .help("15m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_15m as f64))
+                .finish(),
+            PromMetric::build("node_processes_pids")
+                .help("Number of PIDs")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.total_procs as f64))
+                .finish(),
         ]
     }
 }
 
 #[async_trait]
 impl Scraper for LoadClient {
-    async fn get_metrics(&self) -> Result<Vec<PromMetric>, reqwest::Error> {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
         let raw_metrics = self.get_time().await?;
         Ok(LoadClient::raw_to_prom(raw_metrics))
     }
@@ -121,14 +84,16 @@ impl Scraper for LoadClient {
 }
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_cast)]
 mod test {
     use super::*;
+    use crate::prometheus::{PromMetricType, PromSample};
 
     #[test]
     fn test_parse_body() {
         let body = "0.01 0.02 0.03 2/38 23618";
         assert_eq!(
-            LoadClient::parse_body(body.to_string()),
+            LoadClient::parse_body(body.to_string()).unwrap(),
             LoadInfo {
                 load_1m: 0.01f32,
                 load_5m: 0.02f32,
@@ -138,6 +103,11 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_parse_body_malformed() {
+        assert!(LoadClient::parse_body("cat: can't open '/proc/loadavg'".to_string()).is_err())
+    }
+
     #[test]
     fn test_raw_to_prom() {
         assert_eq!(
@@ -2,7 +2,8 @@ use std::collections::BTreeMap;
 
 use regex::Regex;
 
-use crate::client::{Scraper, TomatoClientInternal};
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
 use crate::prometheus::{PromMetric, PromMetricType, PromSample};
 
 #[derive(Clone)]
@@ -15,7 +16,7 @@ impl MemClient {
         MemClient { client }
     }
 
-    async fn get_mem(&self) -> Result<BTreeMap<String, u64>, reqwest::Error> {
+    async fn get_mem(&self) -> Result<BTreeMap<String, u64>, ExporterError> {
         let body = self
             .client
             .run_command("cat /proc/meminfo".to_string())
@@ -27,28 +28,24 @@ impl MemClient {
         let mem_re = Regex::new(r"(?P<name>[^:\n]+):\s+(?P<val_kB>[0-9]+) kB").unwrap();
         mem_re
             .captures_iter(body.as_str().trim())
-            .map(|capture| {
-                (
-                    capture
-                        .name("name")
-                        .unwrap()
-                        .as_str()
-                        .to_string()
-                        .replace('(', "_")
-                        .replace(')', ""),
-                    capture
-                        .name("val_kB")
-                        .unwrap()
-                        .as_str()
-                        .parse::<u64>()
-                        .unwrap()
-                        * 1024,
-                )
+            .filter_map(|capture| {
+                let field = capture_str(&capture, "name").and_then(|name| {
+                    parse_capture::<u64>(&capture, "val_kB").map(|val_kb| (name, val_kb))
+                });
+                match field {
+                    Ok((name, val_kb)) if val_kb.checked_mul(1024).is_some() => {
+                        Some((name.replace('(', "_").replace(')', ""), val_kb * 1024))
+                    }
+                    _ => {
+                        parse_warning(capture.get(0).map_or("", |m| m.as_str()));
+                        None
+                    }
+                }
             })
             .collect()
     }
 
-    fn raw_to_prom(raw_metrics: BTreeMap<String, u64>) -> Vec<PromMetric> {
+    pub(crate) fn raw_to_prom(raw_metrics: BTreeMap<String, u64>) -> Vec<PromMetric> {
         raw_metrics
             .into_iter()
             .map(|(name, val_bytes)| {
@@ -65,7 +62,7 @@ impl MemClient {
 
 #[async_trait]
 impl Scraper for MemClient {
-    async fn get_metrics(&self) -> Result<Vec<PromMetric>, reqwest::Error> {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
         let raw_metrics = self.get_mem().await?;
         Ok(MemClient::raw_to_prom(raw_metrics))
     }
@@ -146,6 +143,18 @@ VmallocChunk:  1008828 kB";
         )
     }
 
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "MemTotal:       255700 kB
+MemFree:        99999999999999999999 kB";
+        assert_eq!(
+            MemClient::parse_body(body.to_string()),
+            btreemap! {
+                "MemTotal".to_string() => 255700 * 1024,
+            }
+        )
+    }
+
     #[test]
     fn test_raw_to_prom() {
         assert_eq!(
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::merlin::{app_get, parse_hook};
+use crate::client::{Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct MerlinCpuClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct CpuUsage {
+    pub total: u64,
+    pub busy: u64,
+}
+
+impl MerlinCpuClient {
+    pub fn new(client: TomatoClientInternal) -> MerlinCpuClient {
+        MerlinCpuClient { client }
+    }
+
+    async fn get_cpus(&self) -> Result<BTreeMap<u8, CpuUsage>, ExporterError> {
+        let body = app_get(&self.client, "cpu_usage").await?;
+        MerlinCpuClient::parse_body(body)
+    }
+
+    // cpuN_total is every jiffy the cpu has counted since boot and cpuN_usage the ones it wasn't
+    // idle for, with the cpus numbered from 1
+    fn parse_body(body: String) -> Result<BTreeMap<u8, CpuUsage>, ExporterError> {
+        let total_re = Regex::new(r"^cpu(?P<cpu>[0-9]+)_total$").unwrap();
+        let vars = parse_hook(body.as_str(), "cpu_usage")?;
+        Ok(vars
+            .iter()
+            .filter_map(|(name, total)| {
+                let cpu = total_re.captures(name)?["cpu"].to_string();
+                let busy = vars.get(format!("cpu{}_usage", cpu).as_str())?;
+                Some((
+                    cpu.parse::<u8>().ok()?.checked_sub(1)?,
+                    CpuUsage {
+                        total: total.parse::<u64>().ok()?,
+                        busy: busy.parse::<u64>().ok()?,
+                    },
+                ))
+            })
+            .collect())
+    }
+
+    // the busy time isn't broken down any further, so it is reported under a mode of its own
+    fn raw_to_prom(cpus: BTreeMap<u8, CpuUsage>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "node_cpu_seconds_total",
+            "Seconds the cpus spent in each mode",
+            PromMetricType::Counter,
+            cpus.into_iter()
+                .flat_map(|(i, cpu)| {
+                    vec![
+                        PromSample::new(
+                            vec![
+                                PromLabel::new("cpu", i.to_string()),
+                                PromLabel::new("mode", "busy".to_string()),
+                            ],
+                            cpu.busy as f64 / 100f64,
+                            None,
+                        ),
+                        PromSample::new(
+                            vec![
+                                PromLabel::new("cpu", i.to_string()),
+                                PromLabel::new("mode", "idle".to_string()),
+                            ],
+                            cpu.total.saturating_sub(cpu.busy) as f64 / 100f64,
+                            None,
+                        ),
+                    ]
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for MerlinCpuClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_cpus().await?;
+        Ok(MerlinCpuClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "cpu".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            MerlinCpuClient::parse_body(
+                r#"{"cpu_usage":{"cpu1_total":"3836100","cpu1_usage":"102400","cpu2_total":"3836050","cpu2_usage":"98000"}}"#
+                    .to_string()
+            )
+            .unwrap(),
+            btreemap! {
+                0 => CpuUsage { total: 3836100, busy: 102400 },
+                1 => CpuUsage { total: 3836050, busy: 98000 },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            MerlinCpuClient::raw_to_prom(btreemap! {
+                0 => CpuUsage { total: 3836100, busy: 102400 },
+            }),
+            vec![PromMetric::new(
+                "node_cpu_seconds_total",
+                "Seconds the cpus spent in each mode",
+                PromMetricType::Counter,
+                vec![
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("cpu", "0".to_string()),
+                            PromLabel::new("mode", "busy".to_string()),
+                        ],
+                        1024f64,
+                        None
+                    ),
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("cpu", "0".to_string()),
+                            PromLabel::new("mode", "idle".to_string()),
+                        ],
+                        37337f64,
+                        None
+                    ),
+                ],
+            )]
+        )
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use crate::client::mem::MemClient;
+use crate::client::merlin::{app_get, parse_hook};
+use crate::client::{Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::PromMetric;
+
+#[derive(Clone)]
+pub struct MerlinMemClient {
+    client: TomatoClientInternal,
+}
+
+impl MerlinMemClient {
+    pub fn new(client: TomatoClientInternal) -> MerlinMemClient {
+        MerlinMemClient { client }
+    }
+
+    async fn get_mem(&self) -> Result<BTreeMap<String, u64>, ExporterError> {
+        let body = app_get(&self.client, "memory_usage").await?;
+        MerlinMemClient::parse_body(body)
+    }
+
+    // reported in kB, and named after the /proc/meminfo fields they are read from so that the
+    // metrics match the ones reported for Tomato
+    fn parse_body(body: String) -> Result<BTreeMap<String, u64>, ExporterError> {
+        let vars = parse_hook(body.as_str(), "memory_usage")?;
+        Ok([("mem_total", "MemTotal"), ("mem_free", "MemFree")]
+            .into_iter()
+            .filter_map(|(var, name)| {
+                Some((name.to_string(), vars.get(var)?.parse::<u64>().ok()? * 1024))
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Scraper for MerlinMemClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_mem().await?;
+        Ok(MemClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            MerlinMemClient::parse_body(
+                r#"{"memory_usage":{"mem_total":"262144","mem_free":"88064","mem_used":"174080"}}"#
+                    .to_string()
+            )
+            .unwrap(),
+            btreemap! {
+                "MemTotal".to_string() => 262144 * 1024,
+                "MemFree".to_string() => 88064 * 1024,
+            }
+        )
+    }
+}
@@ -0,0 +1,70 @@
+mod cpu;
+mod mem;
+mod network;
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::client::TomatoClientInternal;
+use crate::error::ExporterError;
+
+pub use crate::client::merlin::cpu::MerlinCpuClient;
+pub use crate::client::merlin::mem::MerlinMemClient;
+pub use crate::client::merlin::network::MerlinNetworkClient;
+
+// Merlin's web UI polls appGet.cgi, which runs the hook it is given and returns a JSON object with
+// the hook's result under its name
+pub async fn app_get(client: &TomatoClientInternal, hook: &str) -> Result<String, ExporterError> {
+    client
+        .get_page(format!("appGet.cgi?hook={}(appobj)", hook).as_str())
+        .await
+}
+
+// every value in a hook's result is a string, whatever it holds
+pub fn parse_hook(body: &str, hook: &str) -> Result<BTreeMap<String, String>, ExporterError> {
+    let json: Value = serde_json::from_str(body).map_err(|err| {
+        ExporterError::Parse(format!("unable to parse appGet.cgi response: {}", err))
+    })?;
+    Ok(json
+        .get(hook)
+        .and_then(Value::as_object)
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_hook() {
+        assert_eq!(
+            parse_hook(
+                r#"{"memory_usage":{"mem_total":"262144","mem_free":"88064","mem_used":"174080"}}"#,
+                "memory_usage"
+            )
+            .unwrap(),
+            btreemap! {
+                "mem_total".to_string() => "262144".to_string(),
+                "mem_free".to_string() => "88064".to_string(),
+                "mem_used".to_string() => "174080".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_hook_missing() {
+        assert_eq!(parse_hook("{}", "memory_usage").unwrap(), BTreeMap::new())
+    }
+
+    #[test]
+    fn test_parse_hook_not_json() {
+        assert!(parse_hook("<html><body>Login</body></html>", "memory_usage").is_err())
+    }
+}
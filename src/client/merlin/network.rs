@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use crate::client::merlin::{app_get, parse_hook};
+use crate::client::{Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct MerlinNetworkClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct Traffic {
+    pub receive_bytes: u64,
+    pub transmit_bytes: u64,
+}
+
+impl MerlinNetworkClient {
+    pub fn new(client: TomatoClientInternal) -> MerlinNetworkClient {
+        MerlinNetworkClient { client }
+    }
+
+    async fn get_traffic(&self) -> Result<BTreeMap<String, Traffic>, ExporterError> {
+        let body = app_get(&self.client, "netdev").await?;
+        MerlinNetworkClient::parse_body(body)
+    }
+
+    // the counters are named after what the traffic monitor calls each port group (INTERNET, WIRED,
+    // BRIDGE, WIRELESS0, ...) rather than after devices, and are written in hex
+    fn parse_body(body: String) -> Result<BTreeMap<String, Traffic>, ExporterError> {
+        let vars = parse_hook(body.as_str(), "netdev")?;
+        let counter = |name: String| {
+            vars.get(name.as_str())
+                .and_then(|value| u64::from_str_radix(value.trim_start_matches("0x"), 16).ok())
+        };
+        Ok(vars
+            .keys()
+            .filter_map(|name| name.strip_suffix("_rx"))
+            .filter_map(|device| {
+                Some((
+                    device.to_string(),
+                    Traffic {
+                        receive_bytes: counter(format!("{}_rx", device))?,
+                        transmit_bytes: counter(format!("{}_tx", device))?,
+                    },
+                ))
+            })
+            .collect())
+    }
+
+    fn samples(
+        traffic: &BTreeMap<String, Traffic>,
+        value: impl Fn(&Traffic) -> u64,
+    ) -> Vec<PromSample> {
+        traffic
+            .iter()
+            .map(|(device, traffic)| {
+                PromSample::new(
+                    vec![PromLabel::new("device", device.clone())],
+                    value(traffic) as f64,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, Traffic>) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "node_network_receive_bytes_total",
+                "Network device statistic receive_bytes",
+                PromMetricType::Counter,
+                MerlinNetworkClient::samples(&raw_metrics, |traffic| traffic.receive_bytes),
+            ),
+            PromMetric::new(
+                "node_network_transmit_bytes_total",
+                "Network device statistic transmit_bytes",
+                PromMetricType::Counter,
+                MerlinNetworkClient::samples(&raw_metrics, |traffic| traffic.transmit_bytes),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for MerlinNetworkClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_traffic().await?;
+        Ok(MerlinNetworkClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "network".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            MerlinNetworkClient::parse_body(
+                r#"{"netdev":{"INTERNET_rx":"0x7c5bd5e5","INTERNET_tx":"0x1bd4a2b0","WIRED_rx":"0x0","WIRED_tx":"0x10"}}"#
+                    .to_string()
+            )
+            .unwrap(),
+            btreemap! {
+                "INTERNET".to_string() => Traffic {
+                    receive_bytes: 0x7c5bd5e5,
+                    transmit_bytes: 0x1bd4a2b0,
+                },
+                "WIRED".to_string() => Traffic {
+                    receive_bytes: 0,
+                    transmit_bytes: 16,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            MerlinNetworkClient::raw_to_prom(btreemap! {
+                "INTERNET".to_string() => Traffic {
+                    receive_bytes: 2086393317,
+                    transmit_bytes: 466920112,
+                },
+            }),
+            vec![
+                PromMetric::new(
+                    "node_network_receive_bytes_total",
+                    "Network device statistic receive_bytes",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "INTERNET".to_string())],
+                        2086393317f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "node_network_transmit_bytes_total",
+                    "Network device statistic transmit_bytes",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "INTERNET".to_string())],
+                        466920112f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
@@ -1,33 +1,223 @@
+mod adblock;
+mod auth;
+mod breaker;
+mod capture;
 mod cpu;
+mod cputemp;
+mod ddwrt;
+mod diskstats;
+mod dnsmasq;
+mod entropy;
+mod filesystem;
+mod firewall;
+mod hwmon;
+mod iptraffic;
+mod ipv6;
+mod jsx;
+mod linkspeed;
 mod load;
 mod mem;
+mod merlin;
+mod mounts;
 mod network;
+mod ntp;
+mod nvram;
+mod openvpn;
+mod openwrt;
+mod portforward;
+mod process;
+mod radio;
+mod routerinfo;
+mod rstats;
+mod snmp;
+mod sockstat;
+mod survey;
+mod tcpstat;
+mod telnet;
 mod time;
 mod uname;
+mod upnp;
+mod vmstat;
+mod vpnclient;
+mod wan;
+mod wds;
+mod wificlients;
+mod wireguard;
+mod wlcounters;
 
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Formatter;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use ::time::OffsetDateTime;
 use dyn_clone::DynClone;
 use futures::future::join_all;
-use reqwest::{Client, ClientBuilder};
-use tracing::{info, trace, trace_span, warn};
+use regex::{Captures, Regex};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client, ClientBuilder, Proxy, Response, StatusCode};
+use tracing::{debug, info, trace, trace_span, warn};
 use url::form_urlencoded;
 
+use crate::client::adblock::AdblockClient;
+use crate::client::auth::Authenticator;
+use crate::client::breaker::CircuitBreaker;
+use crate::client::capture::Capture;
 use crate::client::cpu::CpuClient;
+use crate::client::cputemp::CpuTempClient;
+use crate::client::ddwrt::{DdwrtLoadClient, DdwrtMemClient, DdwrtWifiClientsClient};
+use crate::client::diskstats::DiskstatsClient;
+use crate::client::dnsmasq::DnsmasqClient;
+use crate::client::entropy::EntropyClient;
+use crate::client::filesystem::FilesystemClient;
+use crate::client::firewall::FirewallClient;
+use crate::client::hwmon::HwmonClient;
+use crate::client::iptraffic::IpTrafficClient;
+use crate::client::ipv6::Ipv6Client;
+use crate::client::linkspeed::LinkSpeedClient;
 use crate::client::load::LoadClient;
 use crate::client::mem::MemClient;
+use crate::client::merlin::{MerlinCpuClient, MerlinMemClient, MerlinNetworkClient};
+use crate::client::mounts::MountsClient;
 use crate::client::network::NetworkClient;
+use crate::client::ntp::NtpClient;
+use crate::client::openvpn::OpenVpnClient;
+use crate::client::openwrt::{
+    OpenWrtLoadClient, OpenWrtMemClient, OpenWrtNetworkClient, OpenWrtWifiClientsClient, Ubus,
+};
+use crate::client::portforward::PortForwardClient;
+use crate::client::process::ProcessClient;
+use crate::client::radio::RadioClient;
+use crate::client::routerinfo::RouterInfoClient;
+use crate::client::rstats::RstatsClient;
+use crate::client::snmp::SnmpClient;
+use crate::client::sockstat::SockstatClient;
+use crate::client::survey::SurveyClient;
+use crate::client::tcpstat::TcpstatClient;
+use crate::client::telnet::TelnetClient;
 use crate::client::time::TimeClient;
 use crate::client::uname::UnameClient;
-use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromResponse, PromSample};
+use crate::client::upnp::UpnpClient;
+use crate::client::vmstat::VmstatClient;
+use crate::client::vpnclient::VpnClientClient;
+use crate::client::wan::WanClient;
+use crate::client::wds::WdsClient;
+use crate::client::wificlients::WifiClientsClient;
+use crate::client::wireguard::WireguardClient;
+use crate::client::wlcounters::WlCountersClient;
+use crate::config::{
+    Charset, Config, Firmware, NetworkConfig, NonFiniteValues, RelabelAction, Transport,
+};
+use crate::error::ExporterError;
+use crate::prometheus::{
+    PromHistogram, PromLabel, PromMetric, PromMetricType, PromResponse, PromSample,
+};
+
+// upper bounds of the buckets in tomato_scrape_collector_duration_seconds, from a collector that is
+// served straight from memory up to one that runs into a generous timeout
+const DURATION_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1f64, 2.5, 5f64, 10f64, 30f64,
+];
+
+tokio::task_local! {
+    // set while get_raw_responses is running, to keep every response the router sends back
+    static RAW_RESPONSES: RefCell<Vec<RawResponse>>;
+    static RAW_COLLECTOR: String;
+    // lines the running collector skipped because they couldn't be parsed
+    static PARSE_WARNINGS: Cell<u64>;
+    // every response the running collector got, kept when parse failures are being captured
+    static CAPTURED: RefCell<Option<Vec<RawResponse>>>;
+}
+
+pub struct RawResponse {
+    pub collector: String,
+    pub request: String,
+    pub body: Result<String, String>,
+}
+
+// a stray byte in a page, such as a Latin-1 degree sign in a UTF-8 one, is replaced rather than
+// failing the scrape, since the parsers rarely look at the text around it
+fn decode(body: &[u8], charset: Charset) -> String {
+    match charset {
+        Charset::Utf8 => String::from_utf8_lossy(body).into_owned(),
+        // every byte is the code point of the same value
+        Charset::Latin1 => body.iter().map(|byte| *byte as char).collect(),
+    }
+}
+
+// none of the pages collectors read are HTML documents, so one arriving instead is the router
+// bouncing the request, most often with a page that redirects to the login form once the session is
+// no longer valid.  it's turned into an error here rather than left for the parsers to choke on
+fn check_page(endpoint: &str, content_type: Option<&str>, body: &str) -> Result<(), ExporterError> {
+    if content_type.is_some_and(|content_type| !content_type.starts_with("text/html")) {
+        return Ok(());
+    }
+    if is_redirect(content_type, body) {
+        return Err(ExporterError::UnexpectedPage(format!(
+            "{} returned a redirect, the session may no longer be valid",
+            endpoint
+        )));
+    }
+    let page = body.trim_start().to_ascii_lowercase();
+    if page.starts_with("<!doctype html") || page.starts_with("<html") {
+        return Err(ExporterError::UnexpectedPage(format!(
+            "{} returned an HTML page",
+            endpoint
+        )));
+    }
+    Ok(())
+}
+
+fn is_redirect(content_type: Option<&str>, body: &str) -> bool {
+    if content_type.is_some_and(|content_type| !content_type.starts_with("text/html")) {
+        return false;
+    }
+    let page = body.trim_start().to_ascii_lowercase();
+    page.contains("http-equiv=\"refresh\"")
+        || page.contains("http-equiv=refresh")
+        || (page.starts_with("<script") && page.contains("location."))
+}
+
+// a collector that reads many lines, such as one per interface, skips any it can't parse rather than
+// failing outright, and counts them in node_scrape_collector_parse_warnings_total
+fn parse_warning(line: &str) {
+    debug!("Skipping malformed line: {:?}", line);
+    let _ = PARSE_WARNINGS.try_with(|warnings| warnings.set(warnings.get() + 1));
+}
+
+// a named group of a regex capture parsed into a number, for the parsers that pick fields out of a
+// command's output
+fn parse_capture<T: std::str::FromStr>(
+    capture: &Captures,
+    field: &str,
+) -> Result<T, ExporterError> {
+    let value = capture.name(field).map_or("", |m| m.as_str());
+    value
+        .parse::<T>()
+        .map_err(|_| ExporterError::Parse(format!("{} is not a number: {:?}", field, value)))
+}
+
+// a named group of a regex capture, for the parsers that pick text out of a command's output
+fn capture_str<'h>(capture: &Captures<'h>, field: &str) -> Result<&'h str, ExporterError> {
+    capture
+        .name(field)
+        .map(|m| m.as_str())
+        .ok_or_else(|| ExporterError::Parse(format!("{} is missing", field)))
+}
 
 #[async_trait]
-trait Scraper: DynClone + Send {
-    async fn get_metrics(&self) -> Result<Vec<PromMetric>, reqwest::Error>;
+trait Scraper: DynClone + Send + Sync {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError>;
 
     fn get_name(&self) -> String;
+
+    // collectors that never talk to the router are left out when deciding whether a scrape failed
+    fn queries_router(&self) -> bool {
+        true
+    }
 }
 
 impl std::fmt::Debug for dyn Scraper {
@@ -40,67 +230,327 @@ dyn_clone::clone_trait_object!(Scraper);
 
 struct ScraperResult {
     pub name: String,
+    pub queries_router: bool,
     pub duration: f64,
-    pub result: Result<Vec<PromMetric>, reqwest::Error>,
+    pub result: Result<Vec<PromMetric>, ExporterError>,
+    pub parse_warnings: u64,
+}
+
+#[derive(Clone)]
+pub struct Scrape {
+    pub response: PromResponse,
+    // every collector that queries the router failed, such as when it can't be reached
+    pub failed: bool,
 }
 
 #[derive(Clone)]
 pub struct TomatoClient {
     data_clients: Vec<Box<dyn Scraper>>,
+    timeouts: HashMap<String, Duration>,
+    default_timeout: Duration,
+    non_finite_values: BTreeMap<String, NonFiniteValues>,
+    scrape_timeout: Option<Duration>,
+    namespace: String,
+    relabel: Vec<(Regex, RelabelAction)>,
+    const_labels: BTreeMap<String, String>,
+    drop_zero_counters: bool,
+    breaker: Option<CircuitBreaker>,
+    auth: Authenticator,
+    // running totals for node_scrape_collector_parse_warnings_total, by collector
+    parse_warnings: Arc<Mutex<BTreeMap<String, u64>>>,
+    // every duration observed so far for tomato_scrape_collector_duration_seconds, by collector
+    durations: Arc<Mutex<BTreeMap<String, PromHistogram>>>,
+    capture: Option<Capture>,
 }
 
 impl TomatoClient {
-    pub fn new(
-        ip_address: String,
-        admin_username: String,
-        admin_password: String,
-        http_id: String,
-    ) -> TomatoClient {
-        let client = TomatoClientInternal::new(ip_address, admin_username, admin_password, http_id);
-        TomatoClient {
-            data_clients: vec![
-                Box::new(CpuClient::new(client.clone())),
-                Box::new(LoadClient::new(client.clone())),
-                Box::new(MemClient::new(client.clone())),
-                Box::new(NetworkClient::new(client.clone())),
-                Box::new(TimeClient::new(client.clone())),
-                Box::new(UnameClient::new(client)),
-            ],
+    pub fn new(conf: &Config) -> Result<TomatoClient, ExporterError> {
+        // built once and cloned into every scraper, which shares the underlying connection pool so
+        // that scrapes reuse connections to the router instead of each opening new ones
+        let client = TomatoClientInternal::from_conf(conf)?;
+        let breaker = client.breaker.clone();
+        let auth = client.auth.clone();
+        let scrapers = TomatoClient::all_scrapers(client, conf);
+        let known_names: Vec<String> = scrapers
+            .iter()
+            .map(|(scraper, _)| scraper.get_name())
+            .collect();
+        for name in conf.collectors.keys() {
+            if !known_names.contains(name) {
+                warn!("Ignoring unknown collector {} in config", name);
+            }
+        }
+        let data_clients: Vec<Box<dyn Scraper>> = scrapers
+            .into_iter()
+            .filter(|(scraper, default)| {
+                conf.collector_enabled(scraper.get_name().as_str(), *default)
+            })
+            .map(|(scraper, _)| scraper)
+            .collect();
+        let client = TomatoClient {
+            data_clients,
+            timeouts: conf
+                .collector_timeouts
+                .iter()
+                .map(|(name, timeout)| (name.clone(), Duration::from_secs_f64(*timeout)))
+                .collect(),
+            default_timeout: Duration::from_secs_f64(conf.collector_timeout),
+            non_finite_values: conf.non_finite_values.clone(),
+            scrape_timeout: conf.scrape_timeout.map(Duration::from_secs_f64),
+            namespace: conf.namespace.clone(),
+            // the patterns have already been checked when the config was validated
+            relabel: conf
+                .relabel
+                .iter()
+                .map(|rule| {
+                    (
+                        Regex::new(rule.metric.as_str()).unwrap(),
+                        rule.action.clone(),
+                    )
+                })
+                .collect(),
+            const_labels: conf.const_labels.clone(),
+            drop_zero_counters: conf.drop_zero_counters,
+            breaker,
+            auth,
+            parse_warnings: Arc::new(Mutex::new(BTreeMap::new())),
+            durations: Arc::new(Mutex::new(BTreeMap::new())),
+            capture: conf.capture_parse_failures.as_ref().map(|capture| {
+                Capture::new(
+                    capture,
+                    vec![
+                        conf.admin_username.clone(),
+                        conf.admin_password.clone(),
+                        conf.http_id.clone(),
+                    ],
+                )
+            }),
+        };
+        info!(
+            "Enabled collectors: {}",
+            client.collector_names().join(", ")
+        );
+        Ok(client)
+    }
+
+    pub fn collector_names(&self) -> Vec<String> {
+        self.data_clients
+            .iter()
+            .map(|scraper| scraper.get_name())
+            .collect()
+    }
+
+    // every collector along with whether it runs when the collectors section doesn't mention it
+    fn all_scrapers(client: TomatoClientInternal, conf: &Config) -> Vec<(Box<dyn Scraper>, bool)> {
+        match conf.firmware {
+            Firmware::Ddwrt => return TomatoClient::ddwrt_scrapers(client),
+            Firmware::Merlin => return TomatoClient::merlin_scrapers(client),
+            Firmware::OpenWrt => return TomatoClient::openwrt_scrapers(client, conf),
+            Firmware::Tomato | Firmware::FreshTomato => {}
         }
+        let devices = DeviceFilter::new(&conf.network);
+        let freshtomato = conf.firmware == Firmware::FreshTomato;
+        vec![
+            (Box::new(AdblockClient::new(client.clone())), freshtomato),
+            (Box::new(CpuClient::new(client.clone())), true),
+            (Box::new(CpuTempClient::new(client.clone())), freshtomato),
+            (Box::new(DiskstatsClient::new(client.clone())), true),
+            (Box::new(DnsmasqClient::new(client.clone())), true),
+            (Box::new(EntropyClient::new(client.clone())), true),
+            (Box::new(FilesystemClient::new(client.clone())), true),
+            (
+                Box::new(FirewallClient::new(
+                    client.clone(),
+                    conf.firewall_chains.clone(),
+                )),
+                !conf.firewall_chains.is_empty(),
+            ),
+            (Box::new(HwmonClient::new(client.clone())), true),
+            (Box::new(IpTrafficClient::new(client.clone())), true),
+            (
+                Box::new(Ipv6Client::new(client.clone(), devices.clone())),
+                true,
+            ),
+            (Box::new(LinkSpeedClient::new(client.clone())), true),
+            (Box::new(LoadClient::new(client.clone())), true),
+            (Box::new(MemClient::new(client.clone())), true),
+            (Box::new(MountsClient::new(client.clone())), true),
+            (
+                Box::new(NetworkClient::new(client.clone(), devices.clone())),
+                true,
+            ),
+            (Box::new(NtpClient::new(client.clone())), true),
+            (Box::new(OpenVpnClient::new(client.clone())), true),
+            (Box::new(PortForwardClient::new(client.clone())), true),
+            (Box::new(ProcessClient::new()), true),
+            (Box::new(RadioClient::new(client.clone())), true),
+            (Box::new(RouterInfoClient::new(client.clone())), true),
+            (Box::new(RstatsClient::new(client.clone())), true),
+            (Box::new(SnmpClient::new(client.clone())), true),
+            (Box::new(SockstatClient::new(client.clone())), true),
+            (
+                Box::new(SurveyClient::new(client.clone())),
+                conf.wifi_survey,
+            ),
+            (Box::new(TcpstatClient::new(client.clone())), true),
+            (Box::new(TimeClient::new(client.clone())), true),
+            (Box::new(UnameClient::new(client.clone())), true),
+            (
+                Box::new(UpnpClient::new(client.clone(), conf.upnp_mapping_info)),
+                true,
+            ),
+            (Box::new(VmstatClient::new(client.clone())), true),
+            (Box::new(VpnClientClient::new(client.clone())), true),
+            (Box::new(WanClient::new(client.clone())), true),
+            (Box::new(WdsClient::new(client.clone())), true),
+            (Box::new(WifiClientsClient::new(client.clone())), true),
+            (Box::new(WireguardClient::new(client.clone())), freshtomato),
+            (Box::new(WlCountersClient::new(client)), true),
+        ]
     }
 
-    pub async fn get_metrics(&self) -> Result<PromResponse, reqwest::Error> {
-        let results = join_all(
-            self.data_clients
-                .iter()
-                .map(|scraper| TomatoClient::run_scraper(scraper.as_ref())),
-        )
-        .await
-        .into_iter();
+    // DD-WRT has no shell.cgi, so only what its status pages show can be collected
+    fn ddwrt_scrapers(client: TomatoClientInternal) -> Vec<(Box<dyn Scraper>, bool)> {
+        vec![
+            (Box::new(DdwrtLoadClient::new(client.clone())), true),
+            (Box::new(DdwrtMemClient::new(client.clone())), true),
+            (Box::new(ProcessClient::new()), true),
+            (Box::new(DdwrtWifiClientsClient::new(client)), true),
+        ]
+    }
+
+    // Merlin's shell can't be reached from the web UI either, so only what appGet.cgi reports can be
+    // collected
+    fn merlin_scrapers(client: TomatoClientInternal) -> Vec<(Box<dyn Scraper>, bool)> {
+        vec![
+            (Box::new(MerlinCpuClient::new(client.clone())), true),
+            (Box::new(MerlinMemClient::new(client.clone())), true),
+            (Box::new(MerlinNetworkClient::new(client)), true),
+            (Box::new(ProcessClient::new()), true),
+        ]
+    }
+
+    // everything on OpenWrt is read through ubus, with one session shared by every collector
+    fn openwrt_scrapers(
+        client: TomatoClientInternal,
+        conf: &Config,
+    ) -> Vec<(Box<dyn Scraper>, bool)> {
+        let ubus = Ubus::new(
+            client,
+            conf.admin_username.clone(),
+            conf.admin_password.clone(),
+        );
+        vec![
+            (Box::new(OpenWrtLoadClient::new(ubus.clone())), true),
+            (Box::new(OpenWrtMemClient::new(ubus.clone())), true),
+            (
+                Box::new(OpenWrtNetworkClient::new(
+                    ubus.clone(),
+                    DeviceFilter::new(&conf.network),
+                )),
+                true,
+            ),
+            (Box::new(ProcessClient::new()), true),
+            (Box::new(OpenWrtWifiClientsClient::new(ubus)), true),
+        ]
+    }
+
+    pub fn collector_defaults(conf: &Config) -> Result<Vec<(String, bool)>, ExporterError> {
+        let mut defaults: Vec<(String, bool)> =
+            TomatoClient::all_scrapers(TomatoClientInternal::from_conf(conf)?, conf)
+                .into_iter()
+                .map(|(scraper, default)| (scraper.get_name(), default))
+                .collect();
+        defaults.sort();
+        Ok(defaults)
+    }
+
+    // only the named collectors are run, or every enabled one when no names are given
+    pub async fn get_metrics(&self, collectors: &[String]) -> Result<Scrape, ExporterError> {
+        let scrapers = self
+            .data_clients
+            .iter()
+            .filter(|scraper| collectors.is_empty() || collectors.contains(&scraper.get_name()));
+        let router_down = self.breaker.as_ref().is_some_and(CircuitBreaker::is_open);
+        let results = join_all(scrapers.map(|scraper| {
+            TomatoClient::run_scraper(
+                scraper.as_ref(),
+                self.timeout(scraper.as_ref()),
+                router_down,
+                self.capture.as_ref(),
+            )
+        }))
+        .await;
+        let router_results: Vec<&ScraperResult> = results
+            .iter()
+            .filter(|result| result.queries_router)
+            .collect();
+        let failed = !router_results.is_empty()
+            && router_results.iter().all(|result| result.result.is_err());
 
         let mut scraper_durations: Vec<PromSample> = Vec::new();
         let mut scraper_successes: Vec<PromSample> = Vec::new();
+        let mut scraper_errors: Vec<PromSample> = Vec::new();
+        let mut scraper_parse_warnings: Vec<PromSample> = Vec::new();
+        let mut parse_warnings = self.parse_warnings.lock().unwrap();
+        let mut durations = self.durations.lock().unwrap();
         let mut metrics: Vec<PromMetric> = results
+            .into_iter()
             .filter_map(|result| {
                 scraper_durations.push(PromSample::new(
                     vec![PromLabel::new("collector", result.name.clone())],
                     result.duration,
                     None,
                 ));
+                durations
+                    .entry(result.name.clone())
+                    .or_insert_with(|| {
+                        PromHistogram::new(
+                            vec![PromLabel::new("collector", result.name.clone())],
+                            &DURATION_BUCKETS,
+                        )
+                    })
+                    .observe(result.duration);
                 scraper_successes.push(PromSample::new(
                     vec![PromLabel::new("collector", result.name.clone())],
                     if result.result.is_ok() { 1f64 } else { 0f64 },
                     None,
                 ));
+                let total = parse_warnings.entry(result.name.clone()).or_default();
+                *total += result.parse_warnings;
+                scraper_parse_warnings.push(PromSample::new(
+                    vec![PromLabel::new("collector", result.name.clone())],
+                    *total as f64,
+                    None,
+                ));
 
                 let name = result.name.clone();
+                let drop_non_finite =
+                    self.non_finite_values.get(&name).copied() == Some(NonFiniteValues::Drop);
                 result
                     .result
                     .map_err(|err| {
-                        warn!("Scraper {} failed: {}", name, err);
+                        warn!(kind = err.kind(), "Scraper {} failed: {}", name, err);
+                        scraper_errors.push(PromSample::new(
+                            vec![
+                                PromLabel::new("collector", name.clone()),
+                                PromLabel::new("kind", err.kind().to_string()),
+                            ],
+                            1f64,
+                            None,
+                        ));
                         err
                     })
                     .ok()
+                    .map(|metrics| {
+                        if !drop_non_finite {
+                            return metrics;
+                        }
+                        metrics
+                            .into_iter()
+                            .map(PromMetric::without_non_finite)
+                            .collect()
+                    })
             })
             .flatten()
             .collect();
@@ -110,69 +560,265 @@ impl TomatoClient {
             PromMetricType::Gauge,
             scraper_durations,
         ));
+        metrics.push(PromMetric::histogram(
+            "tomato_scrape_collector_duration_seconds",
+            "How long collector scrapes have taken",
+            durations.values().cloned().collect(),
+        ));
         metrics.push(PromMetric::new(
             "node_scrape_collector_success",
             "Whether a collector succeeded",
             PromMetricType::Gauge,
             scraper_successes,
         ));
+        metrics.push(PromMetric::new(
+            "node_scrape_collector_error",
+            "The kind of error a collector failed with",
+            PromMetricType::Gauge,
+            scraper_errors,
+        ));
+        metrics.push(PromMetric::new(
+            "node_scrape_collector_parse_warnings_total",
+            "Lines of the router's response a collector skipped because it couldn't parse them",
+            PromMetricType::Counter,
+            scraper_parse_warnings,
+        ));
+        metrics.push(PromMetric::new(
+            "tomato_authentication_ok",
+            "Whether the router accepted the admin credentials the last time it was asked",
+            PromMetricType::Gauge,
+            self.auth
+                .accepted()
+                .map(|accepted| {
+                    PromSample::new(Vec::new(), if accepted { 1f64 } else { 0f64 }, None)
+                })
+                .into_iter()
+                .collect(),
+        ));
+
+        let mut response = PromResponse::new(metrics);
+        if self.drop_zero_counters {
+            response = response.without_zero_counters();
+        }
+        Ok(Scrape {
+            response: response
+                .with_namespace(self.namespace.as_str())
+                .relabel(&self.relabel)
+                .with_const_labels(&self.const_labels)
+                .merge_duplicates(),
+            failed,
+        })
+    }
+
+    fn timeout(&self, scraper: &dyn Scraper) -> Duration {
+        let timeout = self
+            .timeouts
+            .get(&scraper.get_name())
+            .copied()
+            .unwrap_or(self.default_timeout);
+        // every collector starts at the same time, so capping each one at the scrape timeout
+        // returns whatever has finished by then, with the rest marked as failed
+        self.scrape_timeout
+            .map_or(timeout, |scrape_timeout| timeout.min(scrape_timeout))
+    }
 
-        Ok(PromResponse::new(metrics))
+    // runs the collectors like a regular scrape, but returns what the router sent back instead of
+    // the parsed metrics.  a debug scrape is left out of the circuit breaker, the parse warning
+    // totals and the duration histogram, which are about the scrapes Prometheus makes
+    pub async fn get_raw_responses(&self, collectors: &[String]) -> Vec<RawResponse> {
+        let scrapers = self
+            .data_clients
+            .iter()
+            .filter(|scraper| collectors.is_empty() || collectors.contains(&scraper.get_name()));
+        let router_down = self.breaker.as_ref().is_some_and(CircuitBreaker::is_open);
+        RAW_RESPONSES
+            .scope(RefCell::new(Vec::new()), async {
+                join_all(scrapers.map(|scraper| {
+                    TomatoClient::run_scraper(
+                        scraper.as_ref(),
+                        self.timeout(scraper.as_ref()),
+                        router_down,
+                        None,
+                    )
+                }))
+                .await;
+                RAW_RESPONSES.with(RefCell::take)
+            })
+            .await
     }
 
-    async fn run_scraper(scraper: &dyn Scraper) -> ScraperResult {
+    async fn run_scraper(
+        scraper: &dyn Scraper,
+        timeout: Duration,
+        router_down: bool,
+        capture: Option<&Capture>,
+    ) -> ScraperResult {
         let span = trace_span!("Run scraper");
         let _guard = span.enter();
 
         let start_time = OffsetDateTime::now_utc();
-        let result = scraper.get_metrics().await;
+        let (result, parse_warnings) = if router_down && scraper.queries_router() {
+            (Err(ExporterError::CircuitOpen), 0)
+        } else {
+            let captured = capture.map(|_| Vec::new());
+            let scrape = async {
+                let result = match tokio::time::timeout(
+                    timeout,
+                    RAW_COLLECTOR.scope(scraper.get_name(), scraper.get_metrics()),
+                )
+                .await
+                {
+                    Ok(result) => result,
+                    Err(_) => Err(ExporterError::Timeout(timeout)),
+                };
+                if let (Some(capture), Err(err)) = (capture, &result) {
+                    if matches!(
+                        err,
+                        ExporterError::Parse(_) | ExporterError::UnexpectedPage(_)
+                    ) {
+                        let captured = CAPTURED.with(|captured| captured.take());
+                        capture.save(
+                            scraper.get_name().as_str(),
+                            err,
+                            captured.unwrap_or_default().as_slice(),
+                        );
+                    }
+                }
+                (result, PARSE_WARNINGS.with(Cell::get))
+            };
+            PARSE_WARNINGS
+                .scope(Cell::new(0), CAPTURED.scope(RefCell::new(captured), scrape))
+                .await
+        };
         let end_time = OffsetDateTime::now_utc();
         let duration = (end_time - start_time).as_seconds_f64();
         trace!(scraper = scraper.get_name(), duration);
         ScraperResult {
             name: scraper.get_name(),
+            queries_router: scraper.queries_router(),
             duration,
             result,
+            parse_warnings,
         }
     }
 }
 
+// network devices are reported when they match accept_devices, if set, and don't match
+// ignored_devices, if set
+#[derive(Clone, Default)]
+struct DeviceFilter {
+    ignored: Option<Regex>,
+    accepted: Option<Regex>,
+}
+
+impl DeviceFilter {
+    // the patterns have already been checked when the config was validated
+    fn new(conf: &NetworkConfig) -> DeviceFilter {
+        let compile =
+            |pattern: &Option<String>| pattern.as_ref().map(|pattern| Regex::new(pattern).unwrap());
+        DeviceFilter {
+            ignored: compile(&conf.ignored_devices),
+            accepted: compile(&conf.accept_devices),
+        }
+    }
+
+    fn matches(&self, device: &str) -> bool {
+        self.accepted.as_ref().is_none_or(|re| re.is_match(device))
+            && !self.ignored.as_ref().is_some_and(|re| re.is_match(device))
+    }
+}
+
 #[derive(Clone)]
 pub struct TomatoClientInternal {
     hostname: String,
-    admin_username: String,
-    admin_password: String,
+    auth: Authenticator,
+    breaker: Option<CircuitBreaker>,
+    telnet: Option<TelnetClient>,
     http_id: String,
+    charset: Charset,
     client: Client,
 }
 
 impl TomatoClientInternal {
     pub fn new(
-        ip_address: String,
-        admin_username: String,
-        admin_password: String,
+        base_url: String,
+        auth: Authenticator,
+        breaker: Option<CircuitBreaker>,
+        telnet: Option<TelnetClient>,
         http_id: String,
+        charset: Charset,
+        client: Client,
     ) -> TomatoClientInternal {
-        info!("Creating TomatoUSB client for {}", ip_address);
+        info!("Creating TomatoUSB client for {}", base_url);
         TomatoClientInternal {
-            hostname: format!("http://{}", ip_address),
-            admin_username,
-            admin_password,
+            hostname: base_url,
+            auth,
+            breaker,
+            telnet,
             http_id,
-            client: ClientBuilder::new()
-                .build()
-                .expect("Unable to construct HTTP client"),
+            charset,
+            client,
         }
     }
 
+    fn from_conf(conf: &Config) -> Result<TomatoClientInternal, ExporterError> {
+        let scheme = if conf.router_https { "https" } else { "http" };
+        Ok(TomatoClientInternal::new(
+            format!("{}://{}", scheme, conf.router_ip),
+            Authenticator::new(
+                conf.admin_username.clone(),
+                conf.admin_password.clone(),
+                &conf.router_auth,
+                TomatoClientInternal::client_builder(conf)?
+                    .redirect(Policy::none())
+                    .build()?,
+            ),
+            conf.circuit_breaker.as_ref().map(CircuitBreaker::new),
+            match conf.transport {
+                Transport::Http => None,
+                Transport::Telnet => Some(TelnetClient::new(
+                    format!("{}:{}", conf.router_ip, conf.telnet_port),
+                    conf.admin_username.clone(),
+                    conf.admin_password.clone(),
+                    Duration::from_secs_f64(conf.router_timeout),
+                )),
+            },
+            conf.http_id.clone(),
+            conf.router_charset,
+            TomatoClientInternal::client_builder(conf)?.build()?,
+        ))
+    }
+
+    fn client_builder(conf: &Config) -> Result<ClientBuilder, ExporterError> {
+        let mut builder =
+            ClientBuilder::new().timeout(Duration::from_secs_f64(conf.router_timeout));
+        if conf.router_insecure_skip_verify {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        // the proxy has already been checked when the config was validated, but the CA file can have
+        // gone missing since
+        if let Some(proxy) = &conf.router_proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str()).unwrap());
+        }
+        if let Some(ca_file) = &conf.router_ca_file {
+            let pem = fs::read(ca_file).map_err(|err| {
+                ExporterError::Config(format!("unable to read {}: {}", ca_file, err))
+            })?;
+            for cert in Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        Ok(builder)
+    }
+
     pub async fn make_request(
         &self,
         endpoint: String,
         args: Option<HashMap<String, String>>,
-    ) -> Result<String, reqwest::Error> {
+    ) -> Result<String, ExporterError> {
         let arg_map = args.unwrap_or_default();
         let body = arg_map
-            .into_iter()
+            .iter()
             .fold(
                 form_urlencoded::Serializer::new(String::new())
                     .append_pair("_http_id", self.http_id.as_str()),
@@ -180,20 +826,117 @@ impl TomatoClientInternal {
             )
             .finish();
 
-        let response = self
-            .client
-            .post(format!("{}/{}", &self.hostname.clone(), endpoint).as_str())
-            .basic_auth(
-                self.admin_username.clone(),
-                Some(self.admin_password.clone()),
-            )
-            .body(body)
+        let result = self.fetch(endpoint.as_str(), Some(body)).await;
+        let mut args: Vec<String> = arg_map
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        args.sort();
+        self.record([vec![endpoint.clone()], args].concat().join(" "), &result);
+        result
+    }
+
+    // feeds the outcome of every request to the router into the circuit breaker or, during a debug
+    // scrape, the raw responses
+    fn record(&self, request: String, result: &Result<String, ExporterError>) {
+        let debug_scrape = RAW_RESPONSES.try_with(|_| ()).is_ok();
+        if let Some(breaker) = self.breaker.as_ref().filter(|_| !debug_scrape) {
+            match result {
+                Ok(_) => breaker.record_success(),
+                Err(_) => breaker.record_failure(),
+            }
+        }
+        let response = || RawResponse {
+            collector: RAW_COLLECTOR.try_with(String::clone).unwrap_or_default(),
+            request: request.clone(),
+            body: match result {
+                Ok(body) => Ok(body.clone()),
+                Err(err) => Err(err.to_string()),
+            },
+        };
+        let _ = RAW_RESPONSES.try_with(|raw| raw.borrow_mut().push(response()));
+        let _ = CAPTURED.try_with(|captured| {
+            if let Some(captured) = captured.borrow_mut().as_mut() {
+                captured.push(response());
+            }
+        });
+    }
+
+    // a plain GET of a status page, for firmware that serves its data that way rather than through
+    // form posts
+    pub async fn get_page(&self, endpoint: &str) -> Result<String, ExporterError> {
+        let result = self.fetch(endpoint, None).await;
+        self.record(endpoint.to_string(), &result);
+        result
+    }
+
+    // a raw POST body, for firmware with a JSON API.  request describes the call in the raw responses,
+    // since the body may carry credentials
+    pub async fn post_page(
+        &self,
+        endpoint: &str,
+        body: String,
+        request: String,
+    ) -> Result<String, ExporterError> {
+        let result = self.fetch(endpoint, Some(body)).await;
+        self.record(request, &result);
+        result
+    }
+
+    async fn fetch(&self, endpoint: &str, body: Option<String>) -> Result<String, ExporterError> {
+        let (mut status, mut content_type, mut page) = self.read(endpoint, body.clone()).await?;
+        // a session that has expired is logged in again and the request retried once
+        if self
+            .auth
+            .expire(status, content_type.as_deref(), page.as_str())
+        {
+            (status, content_type, page) = self.read(endpoint, body).await?;
+        }
+        self.auth.check(endpoint, status, page.as_str())?;
+        check_page(endpoint, content_type.as_deref(), page.as_str())?;
+        Ok(page)
+    }
+
+    // the status, content type and decoded body of a single request
+    async fn read(
+        &self,
+        endpoint: &str,
+        body: Option<String>,
+    ) -> Result<(StatusCode, Option<String>, String), ExporterError> {
+        let response = self.send(endpoint, body).await?;
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = decode(response.bytes().await?.as_ref(), self.charset);
+        Ok((status, content_type, body))
+    }
+
+    async fn send(&self, endpoint: &str, body: Option<String>) -> Result<Response, ExporterError> {
+        let url = format!("{}/{}", self.hostname, endpoint);
+        let request = match body {
+            Some(body) => self.client.post(url.as_str()).body(body),
+            None => self.client.get(url.as_str()),
+        };
+        self.auth
+            .authorize(self.hostname.as_str(), request)
+            .await?
             .send()
-            .await?;
-        response.text().await
+            .await
+            .map_err(ExporterError::from)
     }
 
-    async fn run_command(&self, command: String) -> Result<String, reqwest::Error> {
+    async fn run_command(&self, command: String) -> Result<String, ExporterError> {
+        if let Some(telnet) = &self.telnet {
+            let result = telnet
+                .run_command(command.as_str())
+                .await
+                .map_err(ExporterError::from);
+            self.record(format!("telnet {}", command), &result);
+            return result;
+        }
         self.make_request(
             "shell.cgi".to_string(),
             Some(hashmap! {
@@ -206,3 +949,67 @@ impl TomatoClientInternal {
         .await
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_device_filter() {
+        let all = DeviceFilter::default();
+        assert!(all.matches("imq0"));
+
+        let filter = DeviceFilter::new(&NetworkConfig {
+            ignored_devices: Some("^imq[0-9]+$".to_string()),
+            accept_devices: Some("^(eth|vlan|imq)".to_string()),
+        });
+        assert!(filter.matches("eth0"));
+        assert!(filter.matches("vlan2"));
+        assert!(!filter.matches("imq1"));
+        assert!(!filter.matches("br0"));
+    }
+
+    #[test]
+    fn test_decode() {
+        let body = b"cpu_temp = '52\xb0C';";
+        assert_eq!(decode(body, Charset::Utf8), "cpu_temp = '52\u{fffd}C';");
+        assert_eq!(decode(body, Charset::Latin1), "cpu_temp = '52\u{b0}C';");
+        assert_eq!(decode("52°C".as_bytes(), Charset::Utf8), "52°C");
+    }
+
+    #[test]
+    fn test_check_page() {
+        assert!(check_page(
+            "status-data.jsx",
+            Some("application/x-javascript"),
+            "\nnvram = {'wan_proto': 'dhcp'};"
+        )
+        .is_ok());
+        assert!(check_page(
+            "Status_Router.live.asp",
+            Some("text/html"),
+            "{lan_mac::00:11:22:33:44:55}"
+        )
+        .is_ok());
+        assert!(matches!(
+            check_page(
+                "status-data.jsx",
+                Some("text/html"),
+                "<html><head><meta http-equiv=\"refresh\" content=\"0; url=/\"></head></html>"
+            ),
+            Err(ExporterError::UnexpectedPage(_))
+        ));
+        assert!(matches!(
+            check_page("update.cgi", None, "<script>location.href='/';</script>"),
+            Err(ExporterError::UnexpectedPage(_))
+        ));
+        assert!(matches!(
+            check_page(
+                "update.cgi",
+                Some("text/html; charset=utf-8"),
+                "<!DOCTYPE html>\n<html><body>Bad request</body></html>"
+            ),
+            Err(ExporterError::UnexpectedPage(_))
+        ));
+    }
+}
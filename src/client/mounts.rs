@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::{capture_str, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct MountsClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct MountStatus {
+    pub fstype: String,
+    pub up: bool,
+}
+
+impl MountsClient {
+    pub fn new(client: TomatoClientInternal) -> MountsClient {
+        MountsClient { client }
+    }
+
+    async fn get_mounts(&self) -> Result<BTreeMap<String, MountStatus>, ExporterError> {
+        // only the enabled flag of the cifs nvram entries is read, since they also carry credentials
+        let body = self
+            .client
+            .run_command(
+                "for k in cifs1 cifs2; do echo \"$k=$(nvram get $k | cut -c1)\"; done; cat /proc/mounts"
+                    .to_string(),
+            )
+            .await?;
+        Ok(MountsClient::parse_body(body))
+    }
+
+    fn parse_body(body: String) -> BTreeMap<String, MountStatus> {
+        let configured_re = Regex::new(r"(?m)^(?P<name>cifs[0-9])=1$").unwrap();
+        let mount_re =
+            Regex::new(r"(?m)^\S+ (?P<mountpoint>\S+) (?P<fstype>cifs|smbfs|nfs|nfs4) ").unwrap();
+
+        let mut mounts: BTreeMap<String, MountStatus> = configured_re
+            .captures_iter(body.as_str())
+            .filter_map(|capture| {
+                Some((
+                    format!("/{}", capture_str(&capture, "name").ok()?),
+                    MountStatus {
+                        fstype: "cifs".to_string(),
+                        up: false,
+                    },
+                ))
+            })
+            .collect();
+        for capture in mount_re.captures_iter(body.as_str()) {
+            if let (Ok(mountpoint), Ok(fstype)) = (
+                capture_str(&capture, "mountpoint"),
+                capture_str(&capture, "fstype"),
+            ) {
+                mounts.insert(
+                    mountpoint.to_string(),
+                    MountStatus {
+                        fstype: fstype.to_string(),
+                        up: true,
+                    },
+                );
+            }
+        }
+        mounts
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, MountStatus>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "mount_up",
+            "Whether the network filesystem is mounted",
+            PromMetricType::Gauge,
+            raw_metrics
+                .into_iter()
+                .map(|(mountpoint, status)| {
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("mountpoint", mountpoint),
+                            PromLabel::new("fstype", status.fstype),
+                        ],
+                        if status.up { 1f64 } else { 0f64 },
+                        None,
+                    )
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for MountsClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_mounts().await?;
+        Ok(MountsClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "mounts".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "cifs1=1
+cifs2=1
+rootfs / rootfs rw 0 0
+/dev/root / squashfs ro 0 0
+tmpfs /tmp tmpfs rw 0 0
+//nas/backup /cifs1 cifs rw,mand,relatime,unc=\\\\nas\\backup,username=guest 0 0
+nas:/export/media /mnt/media nfs rw,relatime,vers=3 0 0";
+        assert_eq!(
+            MountsClient::parse_body(body.to_string()),
+            btreemap! {
+                "/cifs1".to_string() => MountStatus { fstype: "cifs".to_string(), up: true },
+                "/cifs2".to_string() => MountStatus { fstype: "cifs".to_string(), up: false },
+                "/mnt/media".to_string() => MountStatus { fstype: "nfs".to_string(), up: true },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            MountsClient::raw_to_prom(btreemap! {
+                "/cifs1".to_string() => MountStatus { fstype: "cifs".to_string(), up: true },
+                "/cifs2".to_string() => MountStatus { fstype: "cifs".to_string(), up: false },
+            }),
+            vec![PromMetric::new(
+                "mount_up",
+                "Whether the network filesystem is mounted",
+                PromMetricType::Gauge,
+                vec![
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("mountpoint", "/cifs1".to_string()),
+                            PromLabel::new("fstype", "cifs".to_string()),
+                        ],
+                        1f64,
+                        None
+                    ),
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("mountpoint", "/cifs2".to_string()),
+                            PromLabel::new("fstype", "cifs".to_string()),
+                        ],
+                        0f64,
+                        None
+                    ),
+                ]
+            )]
+        )
+    }
+}
@@ -1,17 +1,17 @@
 use std::collections::BTreeMap;
 
-use regex::{Captures, Regex};
-
-use crate::client::{Scraper, TomatoClientInternal};
+use crate::client::{parse_warning, DeviceFilter, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
 use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
 
 #[derive(Clone)]
 pub struct NetworkClient {
     client: TomatoClientInternal,
+    devices: DeviceFilter,
 }
 
 #[derive(Debug, PartialEq)]
-struct NetworkInterface {
+pub(super) struct NetworkInterface {
     pub name: String,
     pub rx_bytes: u64,
     pub rx_packets: u64,
@@ -31,111 +31,118 @@ struct NetworkInterface {
     pub tx_compressed: u64,
 }
 
+type NetworkStat = (&'static str, fn(&NetworkInterface) -> u64);
+
+const NETWORK_STATS: [NetworkStat; 16] = [
+    ("receive_bytes", |iface| iface.rx_bytes),
+    ("transmit_bytes", |iface| iface.tx_bytes),
+    ("receive_packets", |iface| iface.rx_packets),
+    ("transmit_packets", |iface| iface.tx_packets),
+    ("receive_errs", |iface| iface.rx_errs),
+    ("transmit_errs", |iface| iface.tx_errs),
+    ("receive_drop", |iface| iface.rx_drop),
+    ("transmit_drop", |iface| iface.tx_drop),
+    ("receive_fifo", |iface| iface.rx_fifo),
+    ("transmit_fifo", |iface| iface.tx_fifo),
+    ("receive_frame", |iface| iface.rx_frame),
+    ("receive_compressed", |iface| iface.rx_compressed),
+    ("receive_multicast", |iface| iface.rx_multicast),
+    ("transmit_colls", |iface| iface.tx_colls),
+    ("transmit_carrier", |iface| iface.tx_carrier),
+    ("transmit_compressed", |iface| iface.tx_compressed),
+];
+
 impl NetworkClient {
-    pub fn new(client: TomatoClientInternal) -> NetworkClient {
-        NetworkClient { client }
+    pub fn new(client: TomatoClientInternal, devices: DeviceFilter) -> NetworkClient {
+        NetworkClient { client, devices }
     }
 
-    async fn get_network(&self) -> Result<BTreeMap<String, NetworkInterface>, reqwest::Error> {
+    async fn get_network(&self) -> Result<BTreeMap<String, NetworkInterface>, ExporterError> {
         let body = self
             .client
             .run_command("cat /proc/net/dev".to_string())
             .await?;
-        Ok(NetworkClient::parse_body(body))
+        let mut interfaces = NetworkClient::parse_body(body);
+        interfaces.retain(|name, _| self.devices.matches(name));
+        Ok(interfaces)
     }
 
-    fn parse_cap_u64(capture: &Captures, field: &str) -> u64 {
-        capture
-            .name(field)
-            .unwrap()
-            .as_str()
-            .parse::<u64>()
-            .unwrap()
-    }
-
-    fn parse_body(body: String) -> BTreeMap<String, NetworkInterface> {
-        let if_re = Regex::new(r" *(?P<name>[a-z0-9]+): *(?P<rx_bytes>[0-9]+) +(?P<rx_packets>[0-9]+) +(?P<rx_errs>[0-9]+) +(?P<rx_drop>[0-9]+) +(?P<rx_fifo>[0-9]+) +(?P<rx_frame>[0-9]+) +(?P<rx_compressed>[0-9]+) +(?P<rx_multicast>[0-9]+) +(?P<tx_bytes>[0-9]+) +(?P<tx_packets>[0-9]+) +(?P<tx_errs>[0-9]+) +(?P<tx_drop>[0-9]+) +(?P<tx_fifo>[0-9]+) +(?P<tx_colls>[0-9]+) +(?P<tx_carrier>[0-9]+) +(?P<tx_compressed>[0-9]+)").unwrap();
-        if_re
-            .captures_iter(body.as_str().trim())
-            .map(|capture| {
-                let name = capture.name("name").unwrap().as_str().to_string();
-                (
-                    name.clone(),
-                    NetworkInterface {
-                        name,
-                        rx_bytes: NetworkClient::parse_cap_u64(&capture, "rx_bytes"),
-                        rx_packets: NetworkClient::parse_cap_u64(&capture, "rx_packets"),
-                        rx_errs: NetworkClient::parse_cap_u64(&capture, "rx_errs"),
-                        rx_drop: NetworkClient::parse_cap_u64(&capture, "rx_drop"),
-                        rx_fifo: NetworkClient::parse_cap_u64(&capture, "rx_fifo"),
-                        rx_frame: NetworkClient::parse_cap_u64(&capture, "rx_frame"),
-                        rx_compressed: NetworkClient::parse_cap_u64(&capture, "rx_compressed"),
-                        rx_multicast: NetworkClient::parse_cap_u64(&capture, "rx_multicast"),
-                        tx_bytes: NetworkClient::parse_cap_u64(&capture, "tx_bytes"),
-                        tx_packets: NetworkClient::parse_cap_u64(&capture, "tx_packets"),
-                        tx_errs: NetworkClient::parse_cap_u64(&capture, "tx_errs"),
-                        tx_drop: NetworkClient::parse_cap_u64(&capture, "tx_drop"),
-                        tx_fifo: NetworkClient::parse_cap_u64(&capture, "tx_fifo"),
-                        tx_colls: NetworkClient::parse_cap_u64(&capture, "tx_colls"),
-                        tx_carrier: NetworkClient::parse_cap_u64(&capture, "tx_carrier"),
-                        tx_compressed: NetworkClient::parse_cap_u64(&capture, "tx_compressed"),
-                    },
-                )
+    // every line after the two header lines is an interface name, a colon and 16 counters.  names
+    // can hold dots and dashes, as in wl0.1, and a line that can't be parsed is skipped rather than
+    // losing every other interface with it
+    pub(super) fn parse_body(body: String) -> BTreeMap<String, NetworkInterface> {
+        body.lines()
+            .filter(|line| line.contains(':'))
+            .filter_map(|line| {
+                let interface = NetworkClient::parse_line(line);
+                if interface.is_none() {
+                    parse_warning(line);
+                }
+                interface
             })
+            .map(|interface| (interface.name.clone(), interface))
             .collect()
     }
 
+    fn parse_line(line: &str) -> Option<NetworkInterface> {
+        let (name, counters) = line.split_once(':')?;
+        let name = name.trim();
+        let counters = counters
+            .split_whitespace()
+            .map(|counter| counter.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()?;
+        if name.is_empty() || name.contains(char::is_whitespace) || counters.len() != 16 {
+            return None;
+        }
+        let mut counters = counters.into_iter();
+        Some(NetworkInterface {
+            name: name.to_string(),
+            rx_bytes: counters.next()?,
+            rx_packets: counters.next()?,
+            rx_errs: counters.next()?,
+            rx_drop: counters.next()?,
+            rx_fifo: counters.next()?,
+            rx_frame: counters.next()?,
+            rx_compressed: counters.next()?,
+            rx_multicast: counters.next()?,
+            tx_bytes: counters.next()?,
+            tx_packets: counters.next()?,
+            tx_errs: counters.next()?,
+            tx_drop: counters.next()?,
+            tx_fifo: counters.next()?,
+            tx_colls: counters.next()?,
+            tx_carrier: counters.next()?,
+            tx_compressed: counters.next()?,
+        })
+    }
+
     fn raw_to_prom(raw_metrics: BTreeMap<String, NetworkInterface>) -> Vec<PromMetric> {
-        vec![
-            PromMetric::new(
-                "node_network_receive_bytes_total",
-                "Network device statistic receive_bytes",
-                PromMetricType::Counter,
-                raw_metrics
-                    .iter()
-                    .filter_map(|(key, value)| {
-                        let iface = value.to_owned();
-                        if iface.rx_bytes > 0 {
-                            Some(vec![PromSample::new(
-                                vec![PromLabel::new("device", key.to_string())],
-                                value.to_owned().rx_bytes as f64,
-                                None,
-                            )])
-                        } else {
-                            None
-                        }
-                    })
-                    .flatten()
-                    .collect(),
-            ),
-            PromMetric::new(
-                "node_network_transmit_bytes_total",
-                "Network device statistic transmit_bytes",
-                PromMetricType::Counter,
-                raw_metrics
-                    .iter()
-                    .filter_map(|(key, value)| {
-                        let iface = value.to_owned();
-                        if iface.tx_bytes > 0 {
-                            Some(vec![PromSample::new(
+        NETWORK_STATS
+            .iter()
+            .map(|(stat, value)| {
+                PromMetric::new(
+                    format!("node_network_{}_total", stat).as_str(),
+                    format!("Network device statistic {}", stat).as_str(),
+                    PromMetricType::Counter,
+                    raw_metrics
+                        .iter()
+                        .map(|(key, iface)| {
+                            PromSample::new(
                                 vec![PromLabel::new("device", key.to_string())],
-                                value.to_owned().tx_bytes as f64,
+                                value(iface) as f64,
                                 None,
-                            )])
-                        } else {
-                            None
-                        }
-                    })
-                    .flatten()
-                    .collect(),
-            ),
-        ]
+                            )
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
     }
 }
 
 #[async_trait]
 impl Scraper for NetworkClient {
-    async fn get_metrics(&self) -> Result<Vec<PromMetric>, reqwest::Error> {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
         let raw_metrics = self.get_network().await?;
         Ok(NetworkClient::raw_to_prom(raw_metrics))
     }
@@ -147,9 +154,13 @@ impl Scraper for NetworkClient {
 
 #[cfg(test)]
 mod test {
+    use std::cell::Cell;
+
     use super::*;
+    use crate::client::PARSE_WARNINGS;
 
     impl NetworkInterface {
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
             name: String,
             rx_bytes: u64,
@@ -220,6 +231,28 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop  fifo colls carrier compressed
+ wl0.1:  123456    1000    0    0    0     0          0         0    65432     800    0    0    0     0       0          0
+  eth0:99999999999999999999 4125685    9    0    9     9          0         0 264555112  996099    0    0    0     0       0          0
+  eth1:68892432  621865    0    0";
+        let (interfaces, warnings) = PARSE_WARNINGS.sync_scope(Cell::new(0), || {
+            (
+                NetworkClient::parse_body(body.to_string()),
+                PARSE_WARNINGS.with(Cell::get),
+            )
+        });
+        assert_eq!(warnings, 2);
+        assert_eq!(
+            interfaces,
+            btreemap! {
+                "wl0.1".to_string() => NetworkInterface::new("wl0.1".to_string(), 123456, 1000, 0, 0, 0, 0, 0, 0, 65432, 800, 0, 0, 0, 0, 0, 0),
+            }
+        )
+    }
+
     #[test]
     fn test_raw_to_prom() {
         assert_eq!(
@@ -233,7 +266,7 @@ mod test {
                 "br0".to_string() => NetworkInterface::new("br0".to_string(), 141360332, 899095, 0, 0, 0, 0, 0, 12878, 1303031977, 4051507, 0, 0, 0, 0, 0, 0),
                 "imq0".to_string() => NetworkInterface::new("imq0".to_string(), 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0),
                 "imq1".to_string() => NetworkInterface::new("imq1".to_string(), 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0),
-            }),
+            })[..2],
             vec![
                 PromMetric::new(
                     "node_network_receive_bytes_total",
@@ -260,6 +293,16 @@ mod test {
                             52613707f64,
                             None
                         ),
+                        PromSample::new(
+                            vec![PromLabel::new("device", "imq0".to_string())],
+                            0f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("device", "imq1".to_string())],
+                            0f64,
+                            None
+                        ),
                         PromSample::new(
                             vec![PromLabel::new("device", "lo".to_string())],
                             20551f64,
@@ -302,6 +345,16 @@ mod test {
                             200476396f64,
                             None
                         ),
+                        PromSample::new(
+                            vec![PromLabel::new("device", "imq0".to_string())],
+                            0f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("device", "imq1".to_string())],
+                            0f64,
+                            None
+                        ),
                         PromSample::new(
                             vec![PromLabel::new("device", "lo".to_string())],
                             20551f64,
@@ -322,4 +375,47 @@ mod test {
             ]
         )
     }
+
+    #[test]
+    fn test_raw_to_prom_all_stats() {
+        let metrics = NetworkClient::raw_to_prom(btreemap! {
+            "vlan2".to_string() => NetworkInterface::new("vlan2".to_string(), 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16),
+        });
+        let sample = |value: f64| {
+            vec![PromSample::new(
+                vec![PromLabel::new("device", "vlan2".to_string())],
+                value,
+                None,
+            )]
+        };
+        assert_eq!(
+            metrics,
+            vec![
+                ("receive_bytes", 1f64),
+                ("transmit_bytes", 9f64),
+                ("receive_packets", 2f64),
+                ("transmit_packets", 10f64),
+                ("receive_errs", 3f64),
+                ("transmit_errs", 11f64),
+                ("receive_drop", 4f64),
+                ("transmit_drop", 12f64),
+                ("receive_fifo", 5f64),
+                ("transmit_fifo", 13f64),
+                ("receive_frame", 6f64),
+                ("receive_compressed", 7f64),
+                ("receive_multicast", 8f64),
+                ("transmit_colls", 14f64),
+                ("transmit_carrier", 15f64),
+                ("transmit_compressed", 16f64),
+            ]
+            .into_iter()
+            .map(|(stat, value)| PromMetric::new(
+                format!("node_network_{}_total", stat).as_str(),
+                format!("Network device statistic {}", stat).as_str(),
+                PromMetricType::Counter,
+                sample(value),
+            ))
+            .collect::<Vec<PromMetric>>()
+        )
+    }
 }
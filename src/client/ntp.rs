@@ -0,0 +1,130 @@
+use crate::client::{nvram, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct NtpClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct NtpStatus {
+    pub synced: bool,
+    pub last_sync_timestamp: Option<u64>,
+}
+
+impl NtpClient {
+    pub fn new(client: TomatoClientInternal) -> NtpClient {
+        NtpClient { client }
+    }
+
+    async fn get_ntp(&self) -> Result<NtpStatus, ExporterError> {
+        // syslog timestamps carry no year, so busybox date parses them as being from the current year
+        let body = self
+            .client
+            .run_command(format!(
+                "{}; \
+                l=$(grep -iE 'ntpd.*(setting time|synchroni)' /var/log/messages | tail -n 1 | cut -c1-15); \
+                echo \"last_sync=$([ -n \"$l\" ] && date -D '%b %e %H:%M:%S' -d \"$l\" +%s)\"",
+                nvram::get_command(&["ntp_ready"])
+            ))
+            .await?;
+        Ok(NtpClient::parse_body(body))
+    }
+
+    fn parse_body(body: String) -> NtpStatus {
+        let vars = nvram::parse(body.as_str());
+        NtpStatus {
+            synced: vars.get("ntp_ready").map(|v| v.as_str()) == Some("1"),
+            last_sync_timestamp: vars.get("last_sync").and_then(|v| v.parse::<u64>().ok()),
+        }
+    }
+
+    fn raw_to_prom(raw_metrics: NtpStatus) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "ntp_synced",
+                "Whether the router clock has been synchronized over NTP",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    Vec::new(),
+                    if raw_metrics.synced { 1f64 } else { 0f64 },
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "ntp_last_sync_timestamp_seconds",
+                "Time of the last logged NTP synchronization in unix epoch seconds",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .last_sync_timestamp
+                    .map_or_else(Vec::new, |timestamp| {
+                        vec![PromSample::new(Vec::new(), timestamp as f64, None)]
+                    }),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for NtpClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_ntp().await?;
+        Ok(NtpClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "ntp".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            NtpClient::parse_body("ntp_ready=1\nlast_sync=1697460000\n".to_string()),
+            NtpStatus {
+                synced: true,
+                last_sync_timestamp: Some(1697460000),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_never_synced() {
+        assert_eq!(
+            NtpClient::parse_body("ntp_ready=0\nlast_sync=\n".to_string()),
+            NtpStatus {
+                synced: false,
+                last_sync_timestamp: None,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            NtpClient::raw_to_prom(NtpStatus {
+                synced: true,
+                last_sync_timestamp: Some(1697460000),
+            }),
+            vec![
+                PromMetric::new(
+                    "ntp_synced",
+                    "Whether the router clock has been synchronized over NTP",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 1f64, None)],
+                ),
+                PromMetric::new(
+                    "ntp_last_sync_timestamp_seconds",
+                    "Time of the last logged NTP synchronization in unix epoch seconds",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 1697460000f64, None)],
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::capture_str;
+
+pub fn get_command(keys: &[&str]) -> String {
+    format!(
+        "for k in {}; do echo \"$k=$(nvram get $k)\"; done",
+        keys.join(" ")
+    )
+}
+
+pub fn parse(body: &str) -> BTreeMap<String, String> {
+    let var_re = Regex::new(r"(?m)^(?P<key>[a-zA-Z0-9_.]+)=(?P<value>.*)$").unwrap();
+    var_re
+        .captures_iter(body)
+        .filter_map(|capture| {
+            Some((
+                capture_str(&capture, "key").ok()?.to_string(),
+                capture_str(&capture, "value").ok()?.trim().to_string(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_command() {
+        assert_eq!(
+            get_command(&["t_model_name", "os_version"]),
+            "for k in t_model_name os_version; do echo \"$k=$(nvram get $k)\"; done"
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(
+                "t_model_name=Asus RT-AC68U\nos_version=2023.5 K26ARM USB AIO-64K\ncfe_version=\n"
+            ),
+            btreemap! {
+                "t_model_name".to_string() => "Asus RT-AC68U".to_string(),
+                "os_version".to_string() => "2023.5 K26ARM USB AIO-64K".to_string(),
+                "cfe_version".to_string() => "".to_string(),
+            }
+        )
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::{capture_str, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct OpenVpnClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct OpenVpnInstance {
+    pub up: bool,
+    pub connections: Vec<OpenVpnConnection>,
+    pub read_bytes: Option<u64>,
+    pub write_bytes: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+struct OpenVpnConnection {
+    pub common_name: String,
+    pub real_address: String,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
+
+impl OpenVpnClient {
+    pub fn new(client: TomatoClientInternal) -> OpenVpnClient {
+        OpenVpnClient { client }
+    }
+
+    async fn get_openvpn(&self) -> Result<BTreeMap<String, OpenVpnInstance>, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "for d in /etc/openvpn/server* /etc/openvpn/client*; do \
+                    [ -d \"$d\" ] || continue; \
+                    n=${d##*/}; \
+                    pidof vpn$n >/dev/null && s=1 || s=0; \
+                    echo \"==> $n $s <==\"; \
+                    cat $d/status 2>/dev/null; \
+                done"
+                    .to_string(),
+            )
+            .await?;
+        Ok(OpenVpnClient::parse_body(body))
+    }
+
+    fn parse_body(body: String) -> BTreeMap<String, OpenVpnInstance> {
+        let header_re = Regex::new(r"(?m)^==> (?P<name>[a-z0-9]+) (?P<up>[01]) <==$").unwrap();
+        let headers: Vec<_> = header_re.captures_iter(body.as_str()).collect();
+        headers
+            .iter()
+            .enumerate()
+            .map(|(i, capture)| {
+                let start = capture.get(0).map_or(0, |m| m.end());
+                let end = headers
+                    .get(i + 1)
+                    .and_then(|next| next.get(0))
+                    .map_or(body.len(), |next| next.start());
+                let status = &body[start..end];
+                (
+                    capture_str(capture, "name").unwrap_or_default().to_string(),
+                    OpenVpnInstance {
+                        up: capture_str(capture, "up").is_ok_and(|up| up == "1"),
+                        connections: OpenVpnClient::parse_connections(status),
+                        read_bytes: OpenVpnClient::parse_statistic(status, "TCP/UDP read bytes"),
+                        write_bytes: OpenVpnClient::parse_statistic(status, "TCP/UDP write bytes"),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    // handles both status-version 1 (section headings) and 2/3 (HEADER/CLIENT_LIST rows)
+    fn parse_connections(status: &str) -> Vec<OpenVpnConnection> {
+        let mut columns: Vec<String> = Vec::new();
+        let mut in_v1_client_list = false;
+        let mut connections = Vec::new();
+        for line in status.lines().map(|line| line.trim()) {
+            let fields: Vec<&str> = line.split([',', '\t']).collect();
+            let row = match fields[0] {
+                "HEADER" if fields.get(1) == Some(&"CLIENT_LIST") => {
+                    columns = fields[2..].iter().map(|f| f.to_string()).collect();
+                    continue;
+                }
+                "CLIENT_LIST" => &fields[1..],
+                "Common Name" => {
+                    columns = fields.iter().map(|f| f.to_string()).collect();
+                    in_v1_client_list = true;
+                    continue;
+                }
+                "ROUTING TABLE" | "GLOBAL STATS" | "END" => {
+                    in_v1_client_list = false;
+                    continue;
+                }
+                _ if in_v1_client_list => &fields[..],
+                _ => continue,
+            };
+            let column = |name: &str| {
+                columns
+                    .iter()
+                    .position(|col| col == name)
+                    .and_then(|i| row.get(i))
+                    .map(|val| val.to_string())
+                    .unwrap_or_default()
+            };
+            match (
+                column("Bytes Received").parse::<u64>(),
+                column("Bytes Sent").parse::<u64>(),
+            ) {
+                (Ok(bytes_received), Ok(bytes_sent)) => connections.push(OpenVpnConnection {
+                    common_name: column("Common Name"),
+                    real_address: column("Real Address"),
+                    bytes_received,
+                    bytes_sent,
+                }),
+                _ => parse_warning(line),
+            }
+        }
+        connections
+    }
+
+    fn parse_statistic(status: &str, name: &str) -> Option<u64> {
+        status.lines().find_map(|line| {
+            line.trim()
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix(','))
+                .and_then(|val| val.parse::<u64>().inspect_err(|_| parse_warning(line)).ok())
+        })
+    }
+
+    // each server or client instance, such as server1 or client2, is labelled server since Prometheus
+    // sets the instance label to the scrape target
+    fn raw_to_prom(raw_metrics: BTreeMap<String, OpenVpnInstance>) -> Vec<PromMetric> {
+        let connection_labels = |name: &String, conn: &OpenVpnConnection| {
+            vec![
+                PromLabel::new("server", name.to_string()),
+                PromLabel::new("common_name", conn.common_name.clone()),
+                PromLabel::new("real_address", conn.real_address.clone()),
+            ]
+        };
+        vec![
+            PromMetric::new(
+                "openvpn_up",
+                "Whether the OpenVPN instance is running",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .iter()
+                    .map(|(name, instance)| {
+                        PromSample::new(
+                            vec![PromLabel::new("server", name.to_string())],
+                            if instance.up { 1f64 } else { 0f64 },
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "openvpn_server_connected_clients",
+                "Number of clients connected to the OpenVPN server",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .iter()
+                    .filter(|(name, _)| name.starts_with("server"))
+                    .map(|(name, instance)| {
+                        PromSample::new(
+                            vec![PromLabel::new("server", name.to_string())],
+                            instance.connections.len() as f64,
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "openvpn_server_client_received_bytes_total",
+                "Bytes received by the OpenVPN server from a connected client",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .flat_map(|(name, instance)| {
+                        instance.connections.iter().map(move |conn| {
+                            PromSample::new(
+                                connection_labels(name, conn),
+                                conn.bytes_received as f64,
+                                None,
+                            )
+                        })
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "openvpn_server_client_sent_bytes_total",
+                "Bytes sent by the OpenVPN server to a connected client",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .flat_map(|(name, instance)| {
+                        instance.connections.iter().map(move |conn| {
+                            PromSample::new(
+                                connection_labels(name, conn),
+                                conn.bytes_sent as f64,
+                                None,
+                            )
+                        })
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "openvpn_client_received_bytes_total",
+                "Bytes read from the link by the OpenVPN client",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .filter_map(|(name, instance)| {
+                        instance.read_bytes.map(|read_bytes| {
+                            PromSample::new(
+                                vec![PromLabel::new("server", name.to_string())],
+                                read_bytes as f64,
+                                None,
+                            )
+                        })
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "openvpn_client_sent_bytes_total",
+                "Bytes written to the link by the OpenVPN client",
+                PromMetricType::Counter,
+                raw_metrics
+                    .iter()
+                    .filter_map(|(name, instance)| {
+                        instance.write_bytes.map(|write_bytes| {
+                            PromSample::new(
+                                vec![PromLabel::new("server", name.to_string())],
+                                write_bytes as f64,
+                                None,
+                            )
+                        })
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for OpenVpnClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_openvpn().await?;
+        Ok(OpenVpnClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "openvpn".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "==> server1 1 <==
+TITLE,OpenVPN 2.5.8 arm-unknown-linux-gnu [SSL (OpenSSL)] [LZO] [LZ4] [EPOLL] [MH/PKTINFO] [AEAD]
+TIME,Sat Jun  5 15:55:45 2021,1622901345
+HEADER,CLIENT_LIST,Common Name,Real Address,Virtual Address,Virtual IPv6 Address,Bytes Received,Bytes Sent,Connected Since,Connected Since (time_t),Username,Client ID,Peer ID
+CLIENT_LIST,laptop,203.0.113.5:51234,10.8.0.6,,123456,654321,Sat Jun  5 14:00:00 2021,1622894400,UNDEF,0,0
+CLIENT_LIST,phone,198.51.100.7:1194,10.8.0.10,,2048,4096,Sat Jun  5 15:00:00 2021,1622898000,UNDEF,1,1
+HEADER,ROUTING_TABLE,Virtual Address,Common Name,Real Address,Last Ref,Last Ref (time_t)
+ROUTING_TABLE,10.8.0.6,laptop,203.0.113.5:51234,Sat Jun  5 15:55:40 2021,1622901340
+GLOBAL_STATS,Max bcast/mcast queue length,0
+END
+==> server2 0 <==
+==> client1 1 <==
+OpenVPN STATISTICS
+Updated,Sat Jun  5 15:55:45 2021
+TUN/TAP read bytes,1234
+TUN/TAP write bytes,5678
+TCP/UDP read bytes,9012
+TCP/UDP write bytes,3456
+Auth read bytes,5678
+END";
+        assert_eq!(
+            OpenVpnClient::parse_body(body.to_string()),
+            btreemap! {
+                "server1".to_string() => OpenVpnInstance {
+                    up: true,
+                    connections: vec![
+                        OpenVpnConnection {
+                            common_name: "laptop".to_string(),
+                            real_address: "203.0.113.5:51234".to_string(),
+                            bytes_received: 123456,
+                            bytes_sent: 654321,
+                        },
+                        OpenVpnConnection {
+                            common_name: "phone".to_string(),
+                            real_address: "198.51.100.7:1194".to_string(),
+                            bytes_received: 2048,
+                            bytes_sent: 4096,
+                        },
+                    ],
+                    read_bytes: None,
+                    write_bytes: None,
+                },
+                "server2".to_string() => OpenVpnInstance {
+                    up: false,
+                    connections: vec![],
+                    read_bytes: None,
+                    write_bytes: None,
+                },
+                "client1".to_string() => OpenVpnInstance {
+                    up: true,
+                    connections: vec![],
+                    read_bytes: Some(9012),
+                    write_bytes: Some(3456),
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_status_version_1() {
+        let body = "==> server1 1 <==
+OpenVPN CLIENT LIST
+Updated,Sat Jun  5 15:55:45 2021
+Common Name,Real Address,Bytes Received,Bytes Sent,Connected Since
+laptop,203.0.113.5:51234,123456,654321,Sat Jun  5 14:00:00 2021
+ROUTING TABLE
+Virtual Address,Common Name,Real Address,Last Ref
+10.8.0.6,laptop,203.0.113.5:51234,Sat Jun  5 15:55:40 2021
+GLOBAL STATS
+Max bcast/mcast queue length,0
+END";
+        assert_eq!(
+            OpenVpnClient::parse_body(body.to_string()),
+            btreemap! {
+                "server1".to_string() => OpenVpnInstance {
+                    up: true,
+                    connections: vec![OpenVpnConnection {
+                        common_name: "laptop".to_string(),
+                        real_address: "203.0.113.5:51234".to_string(),
+                        bytes_received: 123456,
+                        bytes_sent: 654321,
+                    }],
+                    read_bytes: None,
+                    write_bytes: None,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "==> server1 1 <==
+HEADER,CLIENT_LIST,Common Name,Real Address,Virtual Address,Virtual IPv6 Address,Bytes Received,Bytes Sent
+CLIENT_LIST,laptop,203.0.113.5:51234,10.8.0.6,,123456,654321
+CLIENT_LIST,phone,198.51.100.7:1194,10.8.0.10,,99999999999999999999999,4096
+CLIENT_LIST,tablet
+END
+==> client1 1 <==
+TCP/UDP read bytes,garbage
+TCP/UDP write bytes,3456
+END";
+        assert_eq!(
+            OpenVpnClient::parse_body(body.to_string()),
+            btreemap! {
+                "server1".to_string() => OpenVpnInstance {
+                    up: true,
+                    connections: vec![OpenVpnConnection {
+                        common_name: "laptop".to_string(),
+                        real_address: "203.0.113.5:51234".to_string(),
+                        bytes_received: 123456,
+                        bytes_sent: 654321,
+                    }],
+                    read_bytes: None,
+                    write_bytes: None,
+                },
+                "client1".to_string() => OpenVpnInstance {
+                    up: true,
+                    connections: vec![],
+                    read_bytes: None,
+                    write_bytes: Some(3456),
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            OpenVpnClient::raw_to_prom(btreemap! {
+                "client1".to_string() => OpenVpnInstance {
+                    up: true,
+                    connections: vec![],
+                    read_bytes: Some(9012),
+                    write_bytes: Some(3456),
+                },
+                "server1".to_string() => OpenVpnInstance {
+                    up: true,
+                    connections: vec![OpenVpnConnection {
+                        common_name: "laptop".to_string(),
+                        real_address: "203.0.113.5:51234".to_string(),
+                        bytes_received: 123456,
+                        bytes_sent: 654321,
+                    }],
+                    read_bytes: None,
+                    write_bytes: None,
+                },
+            }),
+            vec![
+                PromMetric::new(
+                    "openvpn_up",
+                    "Whether the OpenVPN instance is running",
+                    PromMetricType::Gauge,
+                    vec![
+                        PromSample::new(
+                            vec![PromLabel::new("server", "client1".to_string())],
+                            1f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("server", "server1".to_string())],
+                            1f64,
+                            None
+                        ),
+                    ],
+                ),
+                PromMetric::new(
+                    "openvpn_server_connected_clients",
+                    "Number of clients connected to the OpenVPN server",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("server", "server1".to_string())],
+                        1f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "openvpn_server_client_received_bytes_total",
+                    "Bytes received by the OpenVPN server from a connected client",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![
+                            PromLabel::new("server", "server1".to_string()),
+                            PromLabel::new("common_name", "laptop".to_string()),
+                            PromLabel::new("real_address", "203.0.113.5:51234".to_string()),
+                        ],
+                        123456f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "openvpn_server_client_sent_bytes_total",
+                    "Bytes sent by the OpenVPN server to a connected client",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![
+                            PromLabel::new("server", "server1".to_string()),
+                            PromLabel::new("common_name", "laptop".to_string()),
+                            PromLabel::new("real_address", "203.0.113.5:51234".to_string()),
+                        ],
+                        654321f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "openvpn_client_received_bytes_total",
+                    "Bytes read from the link by the OpenVPN client",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("server", "client1".to_string())],
+                        9012f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "openvpn_client_sent_bytes_total",
+                    "Bytes written to the link by the OpenVPN client",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("server", "client1".to_string())],
+                        3456f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
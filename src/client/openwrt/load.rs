@@ -0,0 +1,130 @@
+use serde_json::{json, Value};
+
+use crate::client::openwrt::Ubus;
+use crate::client::Scraper;
+use crate::error::ExporterError;
+use crate::prometheus::PromMetric;
+
+#[derive(Clone)]
+pub struct OpenWrtLoadClient {
+    ubus: Ubus,
+}
+
+#[derive(Debug, PartialEq)]
+struct LoadAverage {
+    pub load_1m: f64,
+    pub load_5m: f64,
+    pub load_15m: f64,
+}
+
+impl OpenWrtLoadClient {
+    pub fn new(ubus: Ubus) -> OpenWrtLoadClient {
+        OpenWrtLoadClient { ubus }
+    }
+
+    async fn get_load(&self) -> Result<LoadAverage, ExporterError> {
+        let info = self.ubus.call("system", "info", json!({})).await?;
+        OpenWrtLoadClient::parse_body(info)
+    }
+
+    // the load averages are the kernel's fixed point values, scaled by 65536
+    fn parse_body(info: Value) -> Result<LoadAverage, ExporterError> {
+        let load = |i: usize| {
+            info["load"][i]
+                .as_u64()
+                .map(|load| load as f64 / 65536f64)
+                .ok_or_else(|| ExporterError::Parse("unable to parse load".to_string()))
+        };
+        Ok(LoadAverage {
+            load_1m: load(0)?,
+            load_5m: load(1)?,
+            load_15m: load(2)?,
+        })
+    }
+
+    fn raw_to_prom(raw_metrics: LoadAverage) -> Vec<PromMetric> {
+        vec![
+            PromMetric::build("node_load1")
+                .help("1m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_1m))
+                .finish(),
+            PromMetric::build("node_load5")
+                .help("5m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_5m))
+                .finish(),
+            PromMetric::build("node_load15")
+                .help("15m load average")
+                .gauge()
+                .sample(|s| s.value(raw_metrics.load_15m))
+                .finish(),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for OpenWrtLoadClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_load().await?;
+        Ok(OpenWrtLoadClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "load".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prometheus::{PromMetricType, PromSample};
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            OpenWrtLoadClient::parse_body(json!({
+                "localtime": 1697105472,
+                "uptime": 273905,
+                "load": [8192, 3276, 655],
+            }))
+            .unwrap(),
+            LoadAverage {
+                load_1m: 0.125f64,
+                load_5m: 3276f64 / 65536f64,
+                load_15m: 655f64 / 65536f64,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            OpenWrtLoadClient::raw_to_prom(LoadAverage {
+                load_1m: 0.125f64,
+                load_5m: 0.05f64,
+                load_15m: 0.01f64,
+            }),
+            vec![
+                PromMetric::new(
+                    "node_load1",
+                    "1m load average",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 0.125f64, None)],
+                ),
+                PromMetric::new(
+                    "node_load5",
+                    "5m load average",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 0.05f64, None)],
+                ),
+                PromMetric::new(
+                    "node_load15",
+                    "15m load average",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 0.01f64, None)],
+                ),
+            ]
+        )
+    }
+}
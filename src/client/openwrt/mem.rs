@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::client::mem::MemClient;
+use crate::client::openwrt::Ubus;
+use crate::client::Scraper;
+use crate::error::ExporterError;
+use crate::prometheus::PromMetric;
+
+#[derive(Clone)]
+pub struct OpenWrtMemClient {
+    ubus: Ubus,
+}
+
+// where each /proc/meminfo field is found in the result of system info, so that the metrics match
+// the ones reported for Tomato
+const MEMINFO_FIELDS: [(&str, &str, &str); 8] = [
+    ("memory", "total", "MemTotal"),
+    ("memory", "free", "MemFree"),
+    ("memory", "available", "MemAvailable"),
+    ("memory", "shared", "Shmem"),
+    ("memory", "buffered", "Buffers"),
+    ("memory", "cached", "Cached"),
+    ("swap", "total", "SwapTotal"),
+    ("swap", "free", "SwapFree"),
+];
+
+impl OpenWrtMemClient {
+    pub fn new(ubus: Ubus) -> OpenWrtMemClient {
+        OpenWrtMemClient { ubus }
+    }
+
+    async fn get_mem(&self) -> Result<BTreeMap<String, u64>, ExporterError> {
+        let info = self.ubus.call("system", "info", json!({})).await?;
+        Ok(OpenWrtMemClient::parse_body(info))
+    }
+
+    // already in bytes.  older releases leave out the fields they don't know about
+    fn parse_body(info: Value) -> BTreeMap<String, u64> {
+        MEMINFO_FIELDS
+            .iter()
+            .filter_map(|(section, field, name)| {
+                Some((name.to_string(), info[section][field].as_u64()?))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Scraper for OpenWrtMemClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_mem().await?;
+        Ok(MemClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            OpenWrtMemClient::parse_body(json!({
+                "uptime": 273905,
+                "memory": {
+                    "total": 126705664,
+                    "free": 60567552,
+                    "shared": 118784,
+                    "buffered": 4358144,
+                    "cached": 22679552,
+                },
+                "swap": {"total": 0, "free": 0},
+            })),
+            btreemap! {
+                "MemTotal".to_string() => 126705664,
+                "MemFree".to_string() => 60567552,
+                "Shmem".to_string() => 118784,
+                "Buffers".to_string() => 4358144,
+                "Cached".to_string() => 22679552,
+                "SwapTotal".to_string() => 0,
+                "SwapFree".to_string() => 0,
+            }
+        )
+    }
+}
@@ -0,0 +1,180 @@
+mod load;
+mod mem;
+mod network;
+mod wificlients;
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::client::TomatoClientInternal;
+use crate::error::ExporterError;
+
+pub use crate::client::openwrt::load::OpenWrtLoadClient;
+pub use crate::client::openwrt::mem::OpenWrtMemClient;
+pub use crate::client::openwrt::network::OpenWrtNetworkClient;
+pub use crate::client::openwrt::wificlients::OpenWrtWifiClientsClient;
+
+// the session ubus hands out to anyone, which is only allowed to log in
+const ANONYMOUS_SESSION: &str = "00000000000000000000000000000000";
+const UBUS_STATUS_PERMISSION_DENIED: i64 = 6;
+const JSONRPC_ACCESS_DENIED: i64 = -32002;
+
+// calls ubus objects through the /ubus JSON-RPC endpoint of OpenWrt's web server.  the session id is
+// passed along with every call rather than as a cookie, and is shared by every scraper until the
+// router stops accepting it
+#[derive(Clone)]
+pub struct Ubus {
+    client: TomatoClientInternal,
+    username: String,
+    password: String,
+    session: Arc<Mutex<Option<String>>>,
+}
+
+impl Ubus {
+    pub fn new(client: TomatoClientInternal, username: String, password: String) -> Ubus {
+        Ubus {
+            client,
+            username,
+            password,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub async fn call(
+        &self,
+        object: &str,
+        method: &str,
+        args: Value,
+    ) -> Result<Value, ExporterError> {
+        let session = self.session.lock().unwrap().clone();
+        if let Some(session) = session {
+            if let Some(result) = self.send(session.as_str(), object, method, &args).await? {
+                return Ok(result);
+            }
+            debug!("ubus session expired, logging in again");
+        }
+        let session = self.login().await?;
+        self.send(session.as_str(), object, method, &args)
+            .await?
+            .ok_or_else(|| ExporterError::Auth(format!("{} {}: permission denied", object, method)))
+    }
+
+    async fn login(&self) -> Result<String, ExporterError> {
+        let result = self
+            .send(
+                ANONYMOUS_SESSION,
+                "session",
+                "login",
+                &json!({"username": self.username, "password": self.password}),
+            )
+            .await?
+            .ok_or_else(|| ExporterError::Auth("ubus login was rejected".to_string()))?;
+        let session = result
+            .get("ubus_rpc_session")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ExporterError::Rpc("ubus login returned no session".to_string()))?
+            .to_string();
+        *self.session.lock().unwrap() = Some(session.clone());
+        Ok(session)
+    }
+
+    // None when the session isn't allowed to make the call, which is also how ubus reports a session
+    // that has expired
+    async fn send(
+        &self,
+        session: &str,
+        object: &str,
+        method: &str,
+        args: &Value,
+    ) -> Result<Option<Value>, ExporterError> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "call",
+            "params": [session, object, method, args],
+        });
+        let reply = self
+            .client
+            .post_page(
+                "ubus",
+                body.to_string(),
+                format!("ubus {} {}", object, method),
+            )
+            .await?;
+        Ubus::parse_reply(reply.as_str())
+            .map_err(|err| ExporterError::Rpc(format!("{} {}: {}", object, method, err)))
+    }
+
+    // a successful call returns its status code followed by its result, which is left out by calls
+    // that have nothing to return
+    fn parse_reply(reply: &str) -> Result<Option<Value>, String> {
+        let reply: Value = serde_json::from_str(reply).map_err(|err| err.to_string())?;
+        if let Some(error) = reply.get("error") {
+            return match error.get("code").and_then(Value::as_i64) {
+                Some(JSONRPC_ACCESS_DENIED) => Ok(None),
+                _ => Err(error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+                    .to_string()),
+            };
+        }
+        let result = reply
+            .get("result")
+            .and_then(Value::as_array)
+            .ok_or_else(|| "response has no result".to_string())?;
+        match result.first().and_then(Value::as_i64) {
+            Some(0) => Ok(Some(result.get(1).cloned().unwrap_or(Value::Null))),
+            Some(UBUS_STATUS_PERMISSION_DENIED) => Ok(None),
+            Some(status) => Err(format!("returned status {}", status)),
+            None => Err("response has no status".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(
+            Ubus::parse_reply(r#"{"jsonrpc":"2.0","id":1,"result":[0,{"load":[2240,1856,1600]}]}"#),
+            Ok(Some(json!({"load": [2240, 1856, 1600]})))
+        );
+        assert_eq!(
+            Ubus::parse_reply(r#"{"jsonrpc":"2.0","id":1,"result":[0]}"#),
+            Ok(Some(Value::Null))
+        );
+    }
+
+    #[test]
+    fn test_parse_reply_denied() {
+        assert_eq!(
+            Ubus::parse_reply(r#"{"jsonrpc":"2.0","id":1,"result":[6]}"#),
+            Ok(None)
+        );
+        assert_eq!(
+            Ubus::parse_reply(
+                r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32002,"message":"Access denied"}}"#
+            ),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_parse_reply_error() {
+        assert_eq!(
+            Ubus::parse_reply(r#"{"jsonrpc":"2.0","id":1,"result":[4]}"#),
+            Err("returned status 4".to_string())
+        );
+        assert_eq!(
+            Ubus::parse_reply(
+                r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"Object not found"}}"#
+            ),
+            Err("Object not found".to_string())
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::client::openwrt::Ubus;
+use crate::client::{DeviceFilter, Scraper};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct OpenWrtNetworkClient {
+    ubus: Ubus,
+    devices: DeviceFilter,
+}
+
+// the name each statistic is reported under by node_exporter, followed by the one netifd uses
+const NETWORK_STATS: [(&str, &str); 10] = [
+    ("receive_bytes", "rx_bytes"),
+    ("receive_packets", "rx_packets"),
+    ("receive_errs", "rx_errors"),
+    ("receive_drop", "rx_dropped"),
+    ("receive_multicast", "multicast"),
+    ("transmit_bytes", "tx_bytes"),
+    ("transmit_packets", "tx_packets"),
+    ("transmit_errs", "tx_errors"),
+    ("transmit_drop", "tx_dropped"),
+    ("transmit_colls", "collisions"),
+];
+
+impl OpenWrtNetworkClient {
+    pub fn new(ubus: Ubus, devices: DeviceFilter) -> OpenWrtNetworkClient {
+        OpenWrtNetworkClient { ubus, devices }
+    }
+
+    async fn get_devices(&self) -> Result<BTreeMap<String, BTreeMap<String, u64>>, ExporterError> {
+        let status = self
+            .ubus
+            .call("network.device", "status", json!({}))
+            .await?;
+        let mut devices = OpenWrtNetworkClient::parse_body(status);
+        devices.retain(|device, _| self.devices.matches(device));
+        Ok(devices)
+    }
+
+    // netifd reports every device it knows about, each with the counters from its statistics
+    fn parse_body(status: Value) -> BTreeMap<String, BTreeMap<String, u64>> {
+        status
+            .as_object()
+            .map(|devices| {
+                devices
+                    .iter()
+                    .map(|(device, status)| {
+                        (
+                            device.clone(),
+                            NETWORK_STATS
+                                .iter()
+                                .filter_map(|(stat, field)| {
+                                    Some((stat.to_string(), status["statistics"][field].as_u64()?))
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, BTreeMap<String, u64>>) -> Vec<PromMetric> {
+        NETWORK_STATS
+            .iter()
+            .map(|(stat, _)| {
+                PromMetric::new(
+                    format!("node_network_{}_total", stat).as_str(),
+                    format!("Network device statistic {}", stat).as_str(),
+                    PromMetricType::Counter,
+                    raw_metrics
+                        .iter()
+                        .filter_map(|(device, stats)| {
+                            Some(PromSample::new(
+                                vec![PromLabel::new("device", device.clone())],
+                                *stats.get(*stat)? as f64,
+                                None,
+                            ))
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Scraper for OpenWrtNetworkClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_devices().await?;
+        Ok(OpenWrtNetworkClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "network".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            OpenWrtNetworkClient::parse_body(json!({
+                "br-lan": {
+                    "up": true,
+                    "statistics": {
+                        "collisions": 0,
+                        "multicast": 1203,
+                        "rx_bytes": 141360332,
+                        "rx_dropped": 12,
+                        "rx_errors": 0,
+                        "rx_packets": 899095,
+                        "tx_bytes": 1303031977,
+                        "tx_dropped": 0,
+                        "tx_errors": 0,
+                        "tx_packets": 4051507,
+                    },
+                },
+                "eth1": {"up": false},
+            })),
+            btreemap! {
+                "br-lan".to_string() => btreemap! {
+                    "receive_bytes".to_string() => 141360332,
+                    "receive_packets".to_string() => 899095,
+                    "receive_errs".to_string() => 0,
+                    "receive_drop".to_string() => 12,
+                    "receive_multicast".to_string() => 1203,
+                    "transmit_bytes".to_string() => 1303031977,
+                    "transmit_packets".to_string() => 4051507,
+                    "transmit_errs".to_string() => 0,
+                    "transmit_drop".to_string() => 0,
+                    "transmit_colls".to_string() => 0,
+                },
+                "eth1".to_string() => BTreeMap::new(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            OpenWrtNetworkClient::raw_to_prom(btreemap! {
+                "br-lan".to_string() => btreemap! {
+                    "receive_bytes".to_string() => 141360332,
+                },
+            })[..2],
+            vec![
+                PromMetric::new(
+                    "node_network_receive_bytes_total",
+                    "Network device statistic receive_bytes",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("device", "br-lan".to_string())],
+                        141360332f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "node_network_receive_packets_total",
+                    "Network device statistic receive_packets",
+                    PromMetricType::Counter,
+                    Vec::new(),
+                ),
+            ]
+        )
+    }
+}
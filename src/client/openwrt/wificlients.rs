@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::client::openwrt::Ubus;
+use crate::client::Scraper;
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct OpenWrtWifiClientsClient {
+    ubus: Ubus,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct WirelessInterface {
+    pub iface: String,
+    pub ssid: String,
+}
+
+impl OpenWrtWifiClientsClient {
+    pub fn new(ubus: Ubus) -> OpenWrtWifiClientsClient {
+        OpenWrtWifiClientsClient { ubus }
+    }
+
+    // iwinfo only describes one interface per call, so every interface takes two more calls
+    async fn get_clients(&self) -> Result<BTreeMap<WirelessInterface, u32>, ExporterError> {
+        let devices = self.ubus.call("iwinfo", "devices", json!({})).await?;
+        let mut clients: BTreeMap<WirelessInterface, u32> = BTreeMap::new();
+        for device in OpenWrtWifiClientsClient::parse_devices(devices) {
+            let args = json!({ "device": device });
+            let info = self.ubus.call("iwinfo", "info", args.clone()).await?;
+            let assoclist = self.ubus.call("iwinfo", "assoclist", args).await?;
+            clients.insert(
+                WirelessInterface {
+                    iface: device,
+                    ssid: info["ssid"].as_str().unwrap_or_default().to_string(),
+                },
+                OpenWrtWifiClientsClient::parse_assoclist(assoclist),
+            );
+        }
+        Ok(clients)
+    }
+
+    fn parse_devices(devices: Value) -> Vec<String> {
+        devices["devices"]
+            .as_array()
+            .map(|devices| {
+                devices
+                    .iter()
+                    .filter_map(|device| Some(device.as_str()?.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn parse_assoclist(assoclist: Value) -> u32 {
+        assoclist["results"]
+            .as_array()
+            .map_or(0, |results| results.len() as u32)
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<WirelessInterface, u32>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "wifi_clients",
+            "Number of clients associated with a wireless interface",
+            PromMetricType::Gauge,
+            raw_metrics
+                .into_iter()
+                .map(|(iface, clients)| {
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("iface", iface.iface),
+                            PromLabel::new("ssid", iface.ssid),
+                        ],
+                        clients as f64,
+                        None,
+                    )
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for OpenWrtWifiClientsClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_clients().await?;
+        Ok(OpenWrtWifiClientsClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "wificlients".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_devices() {
+        assert_eq!(
+            OpenWrtWifiClientsClient::parse_devices(json!({"devices": ["phy0-ap0", "phy1-ap0"]})),
+            vec!["phy0-ap0".to_string(), "phy1-ap0".to_string()]
+        )
+    }
+
+    #[test]
+    fn test_parse_assoclist() {
+        assert_eq!(
+            OpenWrtWifiClientsClient::parse_assoclist(json!({
+                "results": [
+                    {"mac": "AA:BB:CC:DD:EE:01", "signal": -45, "inactive": 20},
+                    {"mac": "AA:BB:CC:DD:EE:02", "signal": -61, "inactive": 1040},
+                ],
+            })),
+            2
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            OpenWrtWifiClientsClient::raw_to_prom(btreemap! {
+                WirelessInterface {
+                    iface: "phy0-ap0".to_string(),
+                    ssid: "OpenWrt".to_string(),
+                } => 2,
+            }),
+            vec![PromMetric::new(
+                "wifi_clients",
+                "Number of clients associated with a wireless interface",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    vec![
+                        PromLabel::new("iface", "phy0-ap0".to_string()),
+                        PromLabel::new("ssid", "OpenWrt".to_string()),
+                    ],
+                    2f64,
+                    None
+                )],
+            )]
+        )
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct PortForwardClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct PortForward {
+    pub proto: String,
+    pub ext_port: String,
+    pub int_addr: String,
+}
+
+impl PortForwardClient {
+    pub fn new(client: TomatoClientInternal) -> PortForwardClient {
+        PortForwardClient { client }
+    }
+
+    async fn get_port_forwards(&self) -> Result<BTreeMap<PortForward, u64>, ExporterError> {
+        let body = self
+            .client
+            .run_command("iptables -t nat -L WANPREROUTING -v -n -x".to_string())
+            .await?;
+        Ok(PortForwardClient::parse_body(body))
+    }
+
+    // one DNAT rule is installed per forward, also used for miniupnpd's chain; port ranges and lists
+    // are kept as-is
+    pub(super) fn parse_body(body: String) -> BTreeMap<PortForward, u64> {
+        let rule_re = Regex::new(
+            r"(?m)^\s*(?P<packets>[0-9]+)\s+[0-9]+ DNAT\s+(?P<proto>\S+) .*?(?:dpts?:|multiport dports )(?P<ext_port>\S+).* to:(?P<int_addr>\S+)",
+        )
+        .unwrap();
+        let mut forwards: BTreeMap<PortForward, u64> = BTreeMap::new();
+        for capture in rule_re.captures_iter(body.as_str()) {
+            match PortForwardClient::parse_rule(&capture) {
+                Ok((forward, packets)) => {
+                    let total = forwards.entry(forward).or_insert(0);
+                    *total = total.saturating_add(packets);
+                }
+                Err(_) => parse_warning(capture.get(0).map_or("", |m| m.as_str())),
+            }
+        }
+        forwards
+    }
+
+    fn parse_rule(capture: &Captures) -> Result<(PortForward, u64), ExporterError> {
+        Ok((
+            PortForward {
+                proto: capture_str(capture, "proto")?.to_string(),
+                ext_port: capture_str(capture, "ext_port")?.to_string(),
+                int_addr: capture_str(capture, "int_addr")?.to_string(),
+            },
+            parse_capture(capture, "packets")?,
+        ))
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<PortForward, u64>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "port_forward_packets_total",
+            "Packets matched by a port forwarding rule",
+            PromMetricType::Counter,
+            raw_metrics
+                .into_iter()
+                .map(|(forward, packets)| {
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("proto", forward.proto),
+                            PromLabel::new("ext_port", forward.ext_port),
+                            PromLabel::new("int_addr", forward.int_addr),
+                        ],
+                        packets as f64,
+                        None,
+                    )
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for PortForwardClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_port_forwards().await?;
+        Ok(PortForwardClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "portforward".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "Chain WANPREROUTING (1 references)
+    pkts      bytes target     prot opt in     out     source               destination
+     812    48720 DNAT       icmp --  *      *       0.0.0.0/0            0.0.0.0/0           to:192.168.1.1
+      10      600 DNAT       tcp  --  *      *       0.0.0.0/0            0.0.0.0/0           tcp dpt:8080 to:192.168.1.10:80
+       3      180 DNAT       udp  --  *      *       0.0.0.0/0            0.0.0.0/0           udp dpts:5000:5010 to:192.168.1.20
+       7      420 DNAT       tcp  --  *      *       0.0.0.0/0            0.0.0.0/0           multiport dports 80,443 to:192.168.1.30";
+        assert_eq!(
+            PortForwardClient::parse_body(body.to_string()),
+            btreemap! {
+                PortForward {
+                    proto: "tcp".to_string(),
+                    ext_port: "8080".to_string(),
+                    int_addr: "192.168.1.10:80".to_string(),
+                } => 10,
+                PortForward {
+                    proto: "tcp".to_string(),
+                    ext_port: "80,443".to_string(),
+                    int_addr: "192.168.1.30".to_string(),
+                } => 7,
+                PortForward {
+                    proto: "udp".to_string(),
+                    ext_port: "5000:5010".to_string(),
+                    int_addr: "192.168.1.20".to_string(),
+                } => 3,
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "      10      600 DNAT       tcp  --  *      *       0.0.0.0/0            0.0.0.0/0           tcp dpt:8080 to:192.168.1.10:80
+99999999999999999999      180 DNAT       udp  --  *      *       0.0.0.0/0            0.0.0.0/0           udp dpts:5000:5010 to:192.168.1.20";
+        assert_eq!(
+            PortForwardClient::parse_body(body.to_string()),
+            btreemap! {
+                PortForward {
+                    proto: "tcp".to_string(),
+                    ext_port: "8080".to_string(),
+                    int_addr: "192.168.1.10:80".to_string(),
+                } => 10,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            PortForwardClient::raw_to_prom(btreemap! {
+                PortForward {
+                    proto: "tcp".to_string(),
+                    ext_port: "8080".to_string(),
+                    int_addr: "192.168.1.10:80".to_string(),
+                } => 10,
+            }),
+            vec![PromMetric::new(
+                "port_forward_packets_total",
+                "Packets matched by a port forwarding rule",
+                PromMetricType::Counter,
+                vec![PromSample::new(
+                    vec![
+                        PromLabel::new("proto", "tcp".to_string()),
+                        PromLabel::new("ext_port", "8080".to_string()),
+                        PromLabel::new("int_addr", "192.168.1.10:80".to_string()),
+                    ],
+                    10f64,
+                    None
+                )]
+            )]
+        )
+    }
+}
@@ -0,0 +1,306 @@
+use std::fs;
+
+use regex::Regex;
+
+use crate::client::{parse_capture, Scraper};
+use crate::error::ExporterError;
+use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+
+// the kernel reports times in /proc in units of USER_HZ, which is taken to be 100 rather than read
+// from sysconf(_SC_CLK_TCK).  it's 100 on every architecture routers and the hosts scraping them
+// run, only alpha and ia64 differ
+const USER_HZ: f64 = 100f64;
+
+// reports on the exporter's own process rather than the router
+#[derive(Clone, Default)]
+pub struct ProcessClient {}
+
+#[derive(Debug, PartialEq)]
+struct ProcessStats {
+    pub cpu_seconds: f64,
+    pub start_time_seconds: f64,
+    pub resident_memory_bytes: u64,
+    pub virtual_memory_bytes: u64,
+    pub open_fds: u64,
+    pub max_fds: Option<u64>,
+}
+
+impl ProcessClient {
+    pub fn new() -> ProcessClient {
+        ProcessClient {}
+    }
+
+    // /proc is only available on Linux, elsewhere nothing is reported
+    fn get_process(&self) -> Result<Option<ProcessStats>, ExporterError> {
+        let read = || -> Option<(String, String, String, String, u64)> {
+            Some((
+                fs::read_to_string("/proc/self/stat").ok()?,
+                fs::read_to_string("/proc/stat").ok()?,
+                fs::read_to_string("/proc/self/status").ok()?,
+                fs::read_to_string("/proc/self/limits").ok()?,
+                fs::read_dir("/proc/self/fd").ok()?.count() as u64,
+            ))
+        };
+        read()
+            .map(|(stat, boot_stat, status, limits, open_fds)| {
+                ProcessClient::parse_body(stat, boot_stat, status, limits, open_fds)
+            })
+            .transpose()
+    }
+
+    fn parse_kb(status: &str, field: &str) -> Result<u64, ExporterError> {
+        let Some(capture) = Regex::new(format!(r"(?m)^{}:\s+(?P<kb>[0-9]+) kB$", field).as_str())
+            .unwrap()
+            .captures(status)
+        else {
+            return Ok(0);
+        };
+        parse_capture::<u64>(&capture, "kb")?
+            .checked_mul(1024)
+            .ok_or_else(|| ExporterError::Parse(format!("{} is out of range", field)))
+    }
+
+    // the command name in stat is wrapped in parentheses and may itself contain spaces or
+    // parentheses, so, as procps does, fields are counted from the last closing parenthesis, which
+    // is followed by the third field
+    fn parse_body(
+        stat: String,
+        boot_stat: String,
+        status: String,
+        limits: String,
+        open_fds: u64,
+    ) -> Result<ProcessStats, ExporterError> {
+        let fields: Vec<&str> = stat
+            .rsplit_once(')')
+            .ok_or_else(|| ExporterError::Parse("unable to find the command name".to_string()))?
+            .1
+            .split_whitespace()
+            .collect();
+        let field = |number: usize| -> Result<f64, ExporterError> {
+            fields
+                .get(number - 3)
+                .and_then(|field| field.parse::<u64>().ok())
+                .map(|field| field as f64)
+                .ok_or_else(|| {
+                    ExporterError::Parse(format!("unable to parse stat field {}", number))
+                })
+        };
+        let boot_time = Regex::new(r"(?m)^btime (?P<btime>[0-9]+)$")
+            .unwrap()
+            .captures(boot_stat.as_str())
+            .ok_or_else(|| ExporterError::Parse("unable to parse boot time".to_string()))?;
+        let max_fds_re = Regex::new(r"(?m)^Max open files\s+(?P<soft>[0-9]+)").unwrap();
+
+        Ok(ProcessStats {
+            cpu_seconds: (field(14)? + field(15)?) / USER_HZ,
+            start_time_seconds: parse_capture::<u64>(&boot_time, "btime")? as f64
+                + field(22)? / USER_HZ,
+            resident_memory_bytes: ProcessClient::parse_kb(status.as_str(), "VmRSS")?,
+            virtual_memory_bytes: ProcessClient::parse_kb(status.as_str(), "VmSize")?,
+            open_fds,
+            max_fds: max_fds_re
+                .captures(limits.as_str())
+                .map(|capture| parse_capture(&capture, "soft"))
+                .transpose()?,
+        })
+    }
+
+    fn raw_to_prom(raw_metrics: Option<ProcessStats>) -> Vec<PromMetric> {
+        let sample = |value: Option<f64>| {
+            value.map_or_else(Vec::new, |value| {
+                vec![PromSample::new(Vec::new(), value, None)]
+            })
+        };
+        vec![
+            PromMetric::new(
+                "process_cpu_seconds_total",
+                "Total user and system CPU time spent in seconds",
+                PromMetricType::Counter,
+                sample(raw_metrics.as_ref().map(|stats| stats.cpu_seconds)),
+            ),
+            PromMetric::new(
+                "process_start_time_seconds",
+                "Start time of the process since unix epoch in seconds",
+                PromMetricType::Gauge,
+                sample(raw_metrics.as_ref().map(|stats| stats.start_time_seconds)),
+            ),
+            PromMetric::new(
+                "process_resident_memory_bytes",
+                "Resident memory size in bytes",
+                PromMetricType::Gauge,
+                sample(
+                    raw_metrics
+                        .as_ref()
+                        .map(|stats| stats.resident_memory_bytes as f64),
+                ),
+            ),
+            PromMetric::new(
+                "process_virtual_memory_bytes",
+                "Virtual memory size in bytes",
+                PromMetricType::Gauge,
+                sample(
+                    raw_metrics
+                        .as_ref()
+                        .map(|stats| stats.virtual_memory_bytes as f64),
+                ),
+            ),
+            PromMetric::new(
+                "process_open_fds",
+                "Number of open file descriptors",
+                PromMetricType::Gauge,
+                sample(raw_metrics.as_ref().map(|stats| stats.open_fds as f64)),
+            ),
+            PromMetric::new(
+                "process_max_fds",
+                "Maximum number of open file descriptors",
+                PromMetricType::Gauge,
+                sample(
+                    raw_metrics
+                        .as_ref()
+                        .and_then(|stats| stats.max_fds)
+                        .map(|max_fds| max_fds as f64),
+                ),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for ProcessClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        Ok(ProcessClient::raw_to_prom(self.get_process()?))
+    }
+
+    fn get_name(&self) -> String {
+        "process".to_string()
+    }
+
+    fn queries_router(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let stat = "8327 (tomato (exporter)) S 8216 8216 8216 0 -1 4194304 82 0 0 0 1234 567 0 0 20 0 1 0 336782 2703360 285 18446744073709551615 94534610481152 94534610501033 140723354160688 0 0 0 0 0 0 0 0 0 17 0 0 0 0 0 0";
+        let boot_stat = "cpu  2255 34 2290 22625563 6290 127 456 0 0 0
+intr 114930548 113199788 3 0 5 263 0 4 [... lots more numbers ...]
+ctxt 1990473
+btime 1792160971
+processes 2915
+";
+        let status = "Name:\ttomato-exporter
+VmPeak:\t   12340 kB
+VmSize:\t   12288 kB
+VmRSS:\t    5120 kB
+Threads:\t5
+";
+        let limits = "Limit                     Soft Limit           Hard Limit           Units
+Max processes             63704                63704                processes
+Max open files            1024                 524288               files
+";
+        assert_eq!(
+            ProcessClient::parse_body(
+                stat.to_string(),
+                boot_stat.to_string(),
+                status.to_string(),
+                limits.to_string(),
+                9
+            )
+            .unwrap(),
+            ProcessStats {
+                cpu_seconds: 18.01,
+                start_time_seconds: 1792164338.82,
+                resident_memory_bytes: 5242880,
+                virtual_memory_bytes: 12582912,
+                open_fds: 9,
+                max_fds: Some(1024),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let parse = |stat: &str| {
+            ProcessClient::parse_body(
+                stat.to_string(),
+                "btime 1792160971\n".to_string(),
+                String::new(),
+                String::new(),
+                9,
+            )
+        };
+        assert!(matches!(parse("8327 tomato"), Err(ExporterError::Parse(_))));
+        assert!(matches!(
+            parse("8327 (tomato) S 8216 8216"),
+            Err(ExporterError::Parse(_))
+        ));
+        assert!(matches!(
+            ProcessClient::parse_body(
+                "8327 (tomato) S 8216 8216 8216 0 -1 4194304 82 0 0 0 1234 567 0 0 20 0 1 0 336782"
+                    .to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+                9
+            ),
+            Err(ExporterError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            ProcessClient::raw_to_prom(Some(ProcessStats {
+                cpu_seconds: 18.01,
+                start_time_seconds: 1792164338.82,
+                resident_memory_bytes: 5242880,
+                virtual_memory_bytes: 12582912,
+                open_fds: 9,
+                max_fds: None,
+            })),
+            vec![
+                PromMetric::new(
+                    "process_cpu_seconds_total",
+                    "Total user and system CPU time spent in seconds",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(Vec::new(), 18.01f64, None)],
+                ),
+                PromMetric::new(
+                    "process_start_time_seconds",
+                    "Start time of the process since unix epoch in seconds",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 1792164338.82f64, None)],
+                ),
+                PromMetric::new(
+                    "process_resident_memory_bytes",
+                    "Resident memory size in bytes",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 5242880f64, None)],
+                ),
+                PromMetric::new(
+                    "process_virtual_memory_bytes",
+                    "Virtual memory size in bytes",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 12582912f64, None)],
+                ),
+                PromMetric::new(
+                    "process_open_fds",
+                    "Number of open file descriptors",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 9f64, None)],
+                ),
+                PromMetric::new(
+                    "process_max_fds",
+                    "Maximum number of open file descriptors",
+                    PromMetricType::Gauge,
+                    Vec::new(),
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::survey::SurveyClient;
+use crate::client::{capture_str, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric};
+
+#[derive(Clone)]
+pub struct RadioClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct RadioInfo {
+    pub channel: u16,
+    pub chanspec: String,
+    pub country: String,
+}
+
+impl RadioClient {
+    pub fn new(client: TomatoClientInternal) -> RadioClient {
+        RadioClient { client }
+    }
+
+    async fn get_radios(&self) -> Result<BTreeMap<String, RadioInfo>, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "for i in $(nvram get wl_ifnames); do \
+                    echo \"==> $i <==\"; \
+                    wl -i $i channel; \
+                    wl -i $i chanspec; \
+                    wl -i $i country; \
+                done"
+                    .to_string(),
+            )
+            .await?;
+        Ok(RadioClient::parse_body(body))
+    }
+
+    fn parse_body(body: String) -> BTreeMap<String, RadioInfo> {
+        let header_re = Regex::new(r"(?m)^==> (?P<iface>\S+) <==$").unwrap();
+        let channel_re = Regex::new(r"(?m)^current mac channel\s+(?P<channel>[0-9]+)").unwrap();
+        let chanspec_re = Regex::new(r"(?m)^(?P<chanspec>[0-9]\S*) \(0x[0-9a-fA-F]+\)").unwrap();
+        let country_re = Regex::new(r"(?m)^(?P<country>[A-Z#][A-Z0-9#]+) \(").unwrap();
+
+        let headers: Vec<_> = header_re.captures_iter(body.as_str()).collect();
+        headers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, capture)| {
+                let start = capture.get(0).map_or(0, |m| m.end());
+                let end = headers
+                    .get(i + 1)
+                    .and_then(|next| next.get(0))
+                    .map_or(body.len(), |next| next.start());
+                let block = &body[start..end];
+                let field = |re: &Regex, name: &str| {
+                    re.captures(block)
+                        .and_then(|c| capture_str(&c, name).ok().map(str::to_string))
+                };
+                let iface = capture_str(capture, "iface").ok()?.to_string();
+                // interfaces whose radio is disabled don't report a channel and are skipped
+                let channel = field(&channel_re, "channel")?;
+                let Ok(channel) = channel.parse::<u16>() else {
+                    parse_warning(format!("{} channel {}", iface, channel).as_str());
+                    return None;
+                };
+                Some((
+                    iface,
+                    RadioInfo {
+                        channel,
+                        chanspec: field(&chanspec_re, "chanspec").unwrap_or_default(),
+                        country: field(&country_re, "country").unwrap_or_default(),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, RadioInfo>) -> Vec<PromMetric> {
+        vec![PromMetric::info(
+            "wifi_radio_info",
+            "Labeled wireless radio channel and regulatory information",
+            raw_metrics
+                .into_iter()
+                .map(|(iface, radio)| {
+                    vec![
+                        PromLabel::new("iface", iface),
+                        PromLabel::new("channel", radio.channel.to_string()),
+                        PromLabel::new("chanspec", radio.chanspec),
+                        PromLabel::new("country", radio.country),
+                        PromLabel::new("band", SurveyClient::band(radio.channel)),
+                    ]
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for RadioClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_radios().await?;
+        Ok(RadioClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "radio".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prometheus::{PromMetricType, PromSample};
+
+    #[test]
+    fn test_parse_body() {
+        let body = "==> eth1 <==
+current mac channel	6
+target channel	6
+scan channel	0
+6 (0x1006)
+US (US/0) UNITED STATES
+==> eth2 <==
+current mac channel	36
+target channel	36
+scan channel	0
+36/80 (0xe02a)
+US (US/0) UNITED STATES
+==> eth3 <==
+wl driver adapter not found
+";
+        assert_eq!(
+            RadioClient::parse_body(body.to_string()),
+            btreemap! {
+                "eth1".to_string() => RadioInfo {
+                    channel: 6,
+                    chanspec: "6".to_string(),
+                    country: "US".to_string(),
+                },
+                "eth2".to_string() => RadioInfo {
+                    channel: 36,
+                    chanspec: "36/80".to_string(),
+                    country: "US".to_string(),
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "==> eth1 <==
+current mac channel	6
+6 (0x1006)
+US (US/0) UNITED STATES
+==> eth2 <==
+current mac channel	999999
+36/80 (0xe02a)
+";
+        assert_eq!(
+            RadioClient::parse_body(body.to_string()),
+            btreemap! {
+                "eth1".to_string() => RadioInfo {
+                    channel: 6,
+                    chanspec: "6".to_string(),
+                    country: "US".to_string(),
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            RadioClient::raw_to_prom(btreemap! {
+                "eth2".to_string() => RadioInfo {
+                    channel: 36,
+                    chanspec: "36/80".to_string(),
+                    country: "US".to_string(),
+                },
+            }),
+            vec![PromMetric::new(
+                "wifi_radio_info",
+                "Labeled wireless radio channel and regulatory information",
+                PromMetricType::Info,
+                vec![PromSample::new(
+                    vec![
+                        PromLabel::new("iface", "eth2".to_string()),
+                        PromLabel::new("channel", "36".to_string()),
+                        PromLabel::new("chanspec", "36/80".to_string()),
+                        PromLabel::new("country", "US".to_string()),
+                        PromLabel::new("band", "5GHz".to_string()),
+                    ],
+                    1f64,
+                    None
+                )]
+            )]
+        )
+    }
+}
@@ -0,0 +1,157 @@
+use regex::Regex;
+
+use crate::client::{capture_str, nvram, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric};
+
+#[derive(Clone)]
+pub struct RouterInfoClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct RouterInfo {
+    model: String,
+    firmware: String,
+    cfe_version: String,
+    system_type: String,
+}
+
+impl RouterInfoClient {
+    pub fn new(client: TomatoClientInternal) -> RouterInfoClient {
+        RouterInfoClient { client }
+    }
+
+    async fn get_router_info(&self) -> Result<RouterInfo, ExporterError> {
+        let body = self
+            .client
+            .run_command(format!(
+                "{}; grep -m1 -E '^(system type|Hardware)' /proc/cpuinfo",
+                nvram::get_command(&["t_model_name", "os_version", "cfe_version"])
+            ))
+            .await?;
+        Ok(RouterInfoClient::parse_body(body))
+    }
+
+    fn parse_body(body: String) -> RouterInfo {
+        let vars = nvram::parse(body.as_str());
+        let var = |key: &str| vars.get(key).cloned().unwrap_or_default();
+        let system_type_re =
+            Regex::new(r"(?m)^(?:system type|Hardware)\s*: (?P<system_type>.*)$").unwrap();
+        RouterInfo {
+            model: var("t_model_name"),
+            firmware: var("os_version"),
+            cfe_version: var("cfe_version"),
+            system_type: system_type_re
+                .captures(body.as_str())
+                .and_then(|capture| {
+                    capture_str(&capture, "system_type")
+                        .ok()
+                        .map(|system_type| system_type.trim().to_string())
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn raw_to_prom(info: RouterInfo) -> Vec<PromMetric> {
+        vec![PromMetric::info(
+            "tomato_router_info",
+            "Labeled router hardware and firmware information",
+            vec![vec![
+                PromLabel::new("model", info.model),
+                PromLabel::new("firmware", info.firmware),
+                PromLabel::new("cfe_version", info.cfe_version),
+                PromLabel::new("system_type", info.system_type),
+            ]],
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for RouterInfoClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_router_info().await?;
+        Ok(RouterInfoClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "routerinfo".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prometheus::{PromMetricType, PromSample};
+
+    #[test]
+    fn test_parse_body() {
+        assert_eq!(
+            RouterInfoClient::parse_body(
+                "t_model_name=Asus RT-N66U
+os_version=1.28.0000 MIPSR2-140 K26 USB AIO-64K
+cfe_version=1.0.1.7
+system type		: Broadcom BCM4716 chip rev 1 pkg 10"
+                    .to_string()
+            ),
+            RouterInfo {
+                model: "Asus RT-N66U".to_string(),
+                firmware: "1.28.0000 MIPSR2-140 K26 USB AIO-64K".to_string(),
+                cfe_version: "1.0.1.7".to_string(),
+                system_type: "Broadcom BCM4716 chip rev 1 pkg 10".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_arm() {
+        assert_eq!(
+            RouterInfoClient::parse_body(
+                "t_model_name=Asus RT-AC68U
+os_version=2023.5 K26ARM USB AIO-64K
+cfe_version=
+Hardware	: Northstar Prototype"
+                    .to_string()
+            ),
+            RouterInfo {
+                model: "Asus RT-AC68U".to_string(),
+                firmware: "2023.5 K26ARM USB AIO-64K".to_string(),
+                cfe_version: "".to_string(),
+                system_type: "Northstar Prototype".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            RouterInfoClient::raw_to_prom(RouterInfo {
+                model: "Asus RT-N66U".to_string(),
+                firmware: "1.28.0000 MIPSR2-140 K26 USB AIO-64K".to_string(),
+                cfe_version: "1.0.1.7".to_string(),
+                system_type: "Broadcom BCM4716 chip rev 1 pkg 10".to_string(),
+            }),
+            vec![PromMetric::new(
+                "tomato_router_info",
+                "Labeled router hardware and firmware information",
+                PromMetricType::Info,
+                vec![PromSample::new(
+                    vec![
+                        PromLabel::new("model", "Asus RT-N66U".to_string()),
+                        PromLabel::new(
+                            "firmware",
+                            "1.28.0000 MIPSR2-140 K26 USB AIO-64K".to_string()
+                        ),
+                        PromLabel::new("cfe_version", "1.0.1.7".to_string()),
+                        PromLabel::new(
+                            "system_type",
+                            "Broadcom BCM4716 chip rev 1 pkg 10".to_string()
+                        ),
+                    ],
+                    1f64,
+                    None
+                )]
+            )]
+        )
+    }
+}
@@ -0,0 +1,165 @@
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct RstatsClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct MonthlyTotal {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+impl RstatsClient {
+    pub fn new(client: TomatoClientInternal) -> RstatsClient {
+        RstatsClient { client }
+    }
+
+    async fn get_monthly(&self) -> Result<Option<MonthlyTotal>, ExporterError> {
+        let body = self
+            .client
+            .make_request(
+                "update.cgi".to_string(),
+                Some(hashmap! {
+                    "exec".to_string() => "bandwidth".to_string(),
+                    "arg0".to_string() => "monthly".to_string(),
+                }),
+            )
+            .await?;
+        RstatsClient::parse_body(body)
+    }
+
+    fn parse_cap_hex(capture: &Captures, field: &str) -> Result<u64, ExporterError> {
+        let value = capture_str(capture, field)?;
+        u64::from_str_radix(value, 16)
+            .map_err(|_| ExporterError::Parse(format!("{} is out of range: 0x{}", field, value)))
+    }
+
+    // entries are [date, rx, tx] with the date packed as (year - 1900) << 16 | month << 8, so the
+    // largest date is the current month
+    fn parse_body(body: String) -> Result<Option<MonthlyTotal>, ExporterError> {
+        let entry_re = Regex::new(
+            r"\[\s*0x(?P<date>[0-9a-fA-F]+)\s*,\s*0x(?P<rx>[0-9a-fA-F]+)\s*,\s*0x(?P<tx>[0-9a-fA-F]+)\s*\]",
+        )
+        .unwrap();
+        let entries = entry_re
+            .captures_iter(body.as_str())
+            .map(|capture| {
+                Ok((
+                    RstatsClient::parse_cap_hex(&capture, "date")?,
+                    MonthlyTotal {
+                        rx_bytes: RstatsClient::parse_cap_hex(&capture, "rx")?,
+                        tx_bytes: RstatsClient::parse_cap_hex(&capture, "tx")?,
+                    },
+                ))
+            })
+            .collect::<Result<Vec<(u64, MonthlyTotal)>, ExporterError>>()?;
+        Ok(entries
+            .into_iter()
+            .max_by_key(|(date, _)| *date)
+            .map(|(_, total)| total))
+    }
+
+    fn raw_to_prom(raw_metrics: Option<MonthlyTotal>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "wan_monthly_bytes_total",
+            "Bytes transferred over the WAN during the current month",
+            PromMetricType::Counter,
+            raw_metrics.map_or_else(Vec::new, |total| {
+                vec![
+                    PromSample::new(
+                        vec![PromLabel::new("direction", "receive".to_string())],
+                        total.rx_bytes as f64,
+                        None,
+                    ),
+                    PromSample::new(
+                        vec![PromLabel::new("direction", "transmit".to_string())],
+                        total.tx_bytes as f64,
+                        None,
+                    ),
+                ]
+            }),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for RstatsClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_monthly().await?;
+        Ok(RstatsClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "rstats".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "
+monthly_history = [
+[0x007c0900,0x1f4a3b2c,0x3b9aca00],
+[0x007c0a00,0x00bc614e,0x0001e240],
+[0x007c0800,0x7fffffff,0x7fffffff]];";
+        assert_eq!(
+            RstatsClient::parse_body(body.to_string()).unwrap(),
+            Some(MonthlyTotal {
+                rx_bytes: 12345678,
+                tx_bytes: 123456,
+            })
+        )
+    }
+
+    #[test]
+    fn test_parse_body_no_history() {
+        assert_eq!(
+            RstatsClient::parse_body("\nmonthly_history = [];".to_string()).unwrap(),
+            None
+        )
+    }
+
+    #[test]
+    fn test_parse_body_out_of_range() {
+        assert!(RstatsClient::parse_body(
+            "\nmonthly_history = [\n[0x007c0a00,0x1ffffffffffffffff,0x0001e240]];".to_string()
+        )
+        .is_err())
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            RstatsClient::raw_to_prom(Some(MonthlyTotal {
+                rx_bytes: 12345678,
+                tx_bytes: 123456,
+            })),
+            vec![PromMetric::new(
+                "wan_monthly_bytes_total",
+                "Bytes transferred over the WAN during the current month",
+                PromMetricType::Counter,
+                vec![
+                    PromSample::new(
+                        vec![PromLabel::new("direction", "receive".to_string())],
+                        12345678f64,
+                        None
+                    ),
+                    PromSample::new(
+                        vec![PromLabel::new("direction", "transmit".to_string())],
+                        123456f64,
+                        None
+                    ),
+                ]
+            )]
+        )
+    }
+}
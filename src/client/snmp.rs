@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+
+use crate::client::{parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct SnmpClient {
+    client: TomatoClientInternal,
+}
+
+const PROTOCOLS: [&str; 4] = ["Ip", "Icmp", "Tcp", "Udp"];
+
+impl SnmpClient {
+    pub fn new(client: TomatoClientInternal) -> SnmpClient {
+        SnmpClient { client }
+    }
+
+    async fn get_snmp(&self) -> Result<BTreeMap<String, i64>, ExporterError> {
+        let body = self
+            .client
+            .run_command("cat /proc/net/snmp".to_string())
+            .await?;
+        Ok(SnmpClient::parse_body(body))
+    }
+
+    // every protocol is a header line of field names followed by a line of values
+    fn parse_body(body: String) -> BTreeMap<String, i64> {
+        let lines: Vec<&str> = body.as_str().trim().lines().collect();
+        lines
+            .chunks_exact(2)
+            .filter_map(|pair| {
+                let [names, values] = pair else {
+                    return None;
+                };
+                let (protocol, names) = names.split_once(": ")?;
+                let (_, values) = values.split_once(": ")?;
+                if PROTOCOLS.contains(&protocol) {
+                    Some(
+                        names
+                            .split_whitespace()
+                            .zip(values.split_whitespace())
+                            .filter_map(|(name, value)| {
+                                let field = format!("{}_{}", protocol, name);
+                                match value.parse::<i64>() {
+                                    Ok(value) => Some((field, value)),
+                                    Err(_) => {
+                                        parse_warning(format!("{} {}", field, value).as_str());
+                                        None
+                                    }
+                                }
+                            })
+                            .collect::<Vec<(String, i64)>>(),
+                    )
+                } else {
+                    None
+                }
+            })
+            .flatten()
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, i64>) -> Vec<PromMetric> {
+        raw_metrics
+            .into_iter()
+            .map(|(name, value)| {
+                PromMetric::new(
+                    format!("node_netstat_{}", name).as_str(),
+                    format!("Statistic {}", name.replace('_', "")).as_str(),
+                    PromMetricType::Untyped,
+                    vec![PromSample::new(Vec::new(), value as f64, None)],
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Scraper for SnmpClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_snmp().await?;
+        Ok(SnmpClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "netstat".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "Ip: Forwarding DefaultTTL InReceives InHdrErrors
+Ip: 1 64 1234567 3
+Icmp: InMsgs InErrors OutMsgs
+Icmp: 100 2 98
+IcmpMsg: InType3 OutType3
+IcmpMsg: 50 48
+Tcp: RtoAlgorithm MaxConn ActiveOpens RetransSegs
+Tcp: 1 -1 4567 89
+Udp: InDatagrams NoPorts InErrors OutDatagrams
+Udp: 5000 12 1 4800
+UdpLite: InDatagrams NoPorts
+UdpLite: 0 0";
+        assert_eq!(
+            SnmpClient::parse_body(body.to_string()),
+            btreemap! {
+                "Ip_Forwarding".to_string() => 1,
+                "Ip_DefaultTTL".to_string() => 64,
+                "Ip_InReceives".to_string() => 1234567,
+                "Ip_InHdrErrors".to_string() => 3,
+                "Icmp_InMsgs".to_string() => 100,
+                "Icmp_InErrors".to_string() => 2,
+                "Icmp_OutMsgs".to_string() => 98,
+                "Tcp_RtoAlgorithm".to_string() => 1,
+                "Tcp_MaxConn".to_string() => -1,
+                "Tcp_ActiveOpens".to_string() => 4567,
+                "Tcp_RetransSegs".to_string() => 89,
+                "Udp_InDatagrams".to_string() => 5000,
+                "Udp_NoPorts".to_string() => 12,
+                "Udp_InErrors".to_string() => 1,
+                "Udp_OutDatagrams".to_string() => 4800,
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "Tcp: RtoAlgorithm MaxConn ActiveOpens
+Tcp: 1 -1 99999999999999999999
+Udp: InDatagrams";
+        assert_eq!(
+            SnmpClient::parse_body(body.to_string()),
+            btreemap! {
+                "Tcp_RtoAlgorithm".to_string() => 1,
+                "Tcp_MaxConn".to_string() => -1,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            SnmpClient::raw_to_prom(btreemap! {
+                "Tcp_MaxConn".to_string() => -1,
+                "Tcp_RetransSegs".to_string() => 89,
+            }),
+            vec![
+                PromMetric::new(
+                    "node_netstat_Tcp_MaxConn",
+                    "Statistic TcpMaxConn",
+                    PromMetricType::Untyped,
+                    vec![PromSample::new(Vec::new(), -1f64, None)],
+                ),
+                PromMetric::new(
+                    "node_netstat_Tcp_RetransSegs",
+                    "Statistic TcpRetransSegs",
+                    PromMetricType::Untyped,
+                    vec![PromSample::new(Vec::new(), 89f64, None)],
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct SockstatClient {
+    client: TomatoClientInternal,
+}
+
+impl SockstatClient {
+    pub fn new(client: TomatoClientInternal) -> SockstatClient {
+        SockstatClient { client }
+    }
+
+    async fn get_sockstat(&self) -> Result<BTreeMap<String, u64>, ExporterError> {
+        let body = self
+            .client
+            .run_command("cat /proc/net/sockstat".to_string())
+            .await?;
+        Ok(SockstatClient::parse_body(body))
+    }
+
+    fn parse_body(body: String) -> BTreeMap<String, u64> {
+        let line_re = Regex::new(r"(?m)^(?P<protocol>[A-Za-z0-9]+): (?P<fields>.*)$").unwrap();
+        let field_re = Regex::new(r"(?P<name>[a-z]+) (?P<value>[0-9]+)").unwrap();
+        line_re
+            .captures_iter(body.as_str().trim())
+            .flat_map(|line| {
+                let protocol = capture_str(&line, "protocol").unwrap_or_default();
+                field_re
+                    .captures_iter(capture_str(&line, "fields").unwrap_or_default())
+                    .filter_map(|field| {
+                        let name = capture_str(&field, "name").ok()?;
+                        match parse_capture::<u64>(&field, "value") {
+                            Ok(value) => Some((format!("{}_{}", protocol, name), value)),
+                            Err(_) => {
+                                parse_warning(line.get(0).map_or("", |m| m.as_str()));
+                                None
+                            }
+                        }
+                    })
+                    .collect::<Vec<(String, u64)>>()
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, u64>) -> Vec<PromMetric> {
+        raw_metrics
+            .into_iter()
+            .map(|(name, value)| {
+                PromMetric::new(
+                    format!("node_sockstat_{}", name).as_str(),
+                    format!("Socket statistics field {}", name).as_str(),
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), value as f64, None)],
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Scraper for SockstatClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_sockstat().await?;
+        Ok(SockstatClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "sockstat".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "sockets: used 123
+TCP: inuse 10 orphan 0 tw 5 alloc 12 mem 1
+UDP: inuse 8 mem 2
+UDPLITE: inuse 0
+RAW: inuse 0
+FRAG: inuse 0 memory 0";
+        assert_eq!(
+            SockstatClient::parse_body(body.to_string()),
+            btreemap! {
+                "sockets_used".to_string() => 123,
+                "TCP_inuse".to_string() => 10,
+                "TCP_orphan".to_string() => 0,
+                "TCP_tw".to_string() => 5,
+                "TCP_alloc".to_string() => 12,
+                "TCP_mem".to_string() => 1,
+                "UDP_inuse".to_string() => 8,
+                "UDP_mem".to_string() => 2,
+                "UDPLITE_inuse".to_string() => 0,
+                "RAW_inuse".to_string() => 0,
+                "FRAG_inuse".to_string() => 0,
+                "FRAG_memory".to_string() => 0,
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "sockets: used 99999999999999999999
+UDP: inuse 8 mem 2";
+        assert_eq!(
+            SockstatClient::parse_body(body.to_string()),
+            btreemap! {
+                "UDP_inuse".to_string() => 8,
+                "UDP_mem".to_string() => 2,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            SockstatClient::raw_to_prom(btreemap! {
+                "TCP_inuse".to_string() => 10,
+                "TCP_tw".to_string() => 5,
+            }),
+            vec![
+                PromMetric::new(
+                    "node_sockstat_TCP_inuse",
+                    "Socket statistics field TCP_inuse",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 10f64, None)],
+                ),
+                PromMetric::new(
+                    "node_sockstat_TCP_tw",
+                    "Socket statistics field TCP_tw",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 5f64, None)],
+                ),
+            ]
+        )
+    }
+}
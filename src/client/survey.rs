@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct SurveyClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct SurveyChannel {
+    pub band: String,
+    pub channel: u16,
+}
+
+impl SurveyClient {
+    pub fn new(client: TomatoClientInternal) -> SurveyClient {
+        SurveyClient { client }
+    }
+
+    async fn get_survey(&self) -> Result<BTreeMap<SurveyChannel, u32>, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "for i in $(nvram get wl_ifnames); do wl -i $i scan; done; \
+                sleep 3; \
+                for i in $(nvram get wl_ifnames); do wl -i $i scanresults; done"
+                    .to_string(),
+            )
+            .await?;
+        Ok(SurveyClient::parse_body(body))
+    }
+
+    pub(super) fn band(channel: u16) -> String {
+        if channel <= 14 {
+            "2.4GHz".to_string()
+        } else {
+            "5GHz".to_string()
+        }
+    }
+
+    fn parse_body(body: String) -> BTreeMap<SurveyChannel, u32> {
+        let bssid_re = Regex::new(r"(?m)^BSSID: (?P<bssid>[0-9A-Fa-f:]{17})").unwrap();
+        let channel_re = Regex::new(r"Channel: (?P<channel>[0-9]+)").unwrap();
+
+        // each access point block starts at its SSID line, so split the output on those
+        let mut aps: BTreeMap<String, u16> = BTreeMap::new();
+        for block in body.split("SSID: \"").skip(1) {
+            if let (Some(bssid), Some(channel)) =
+                (bssid_re.captures(block), channel_re.captures(block))
+            {
+                match (
+                    capture_str(&bssid, "bssid"),
+                    parse_capture::<u16>(&channel, "channel"),
+                ) {
+                    (Ok(bssid), Ok(channel)) => {
+                        aps.insert(bssid.to_lowercase(), channel);
+                    }
+                    _ => parse_warning(block.lines().next().unwrap_or_default()),
+                }
+            }
+        }
+
+        let mut channels: BTreeMap<SurveyChannel, u32> = BTreeMap::new();
+        for channel in aps.into_values() {
+            *channels
+                .entry(SurveyChannel {
+                    band: SurveyClient::band(channel),
+                    channel,
+                })
+                .or_insert(0) += 1;
+        }
+        channels
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<SurveyChannel, u32>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "wifi_survey_neighbor_aps",
+            "Number of neighboring access points seen on a channel",
+            PromMetricType::Gauge,
+            raw_metrics
+                .into_iter()
+                .map(|(channel, count)| {
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("channel", channel.channel.to_string()),
+                            PromLabel::new("band", channel.band),
+                        ],
+                        count as f64,
+                        None,
+                    )
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for SurveyClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_survey().await?;
+        Ok(SurveyClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "survey".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "SSID: \"NeighborNet\"
+Mode: Managed	RSSI: -67 dBm	SNR: 0 dB	noise: -91 dBm	Flags: RSSI on-channel 	Channel: 6
+BSSID: 00:11:22:33:44:55	Capability: ESS ShortSlot
+Supported Rates: [ 1(b) 2(b) 5.5(b) 11(b) 6 9 12 18 24 36 48 54 ]
+
+SSID: \"CoffeeShop\"
+Mode: Managed	RSSI: -80 dBm	SNR: 0 dB	noise: -91 dBm	Channel: 6
+BSSID: 00:11:22:33:44:66	Capability: ESS ShortSlot
+
+SSID: \"\"
+Mode: Managed	RSSI: -85 dBm	SNR: 0 dB	noise: -91 dBm	Channel: 11
+BSSID: 00:11:22:33:44:77	Capability: ESS ShortSlot
+
+SSID: \"NeighborNet-5G\"
+Mode: Managed	RSSI: -72 dBm	SNR: 0 dB	noise: -92 dBm	Channel: 36/80
+BSSID: 00:11:22:33:44:88	Capability: ESS
+
+SSID: \"NeighborNet\"
+Mode: Managed	RSSI: -66 dBm	SNR: 0 dB	noise: -91 dBm	Channel: 6
+BSSID: 00:11:22:33:44:55	Capability: ESS ShortSlot
+";
+        assert_eq!(
+            SurveyClient::parse_body(body.to_string()),
+            btreemap! {
+                SurveyChannel { band: "2.4GHz".to_string(), channel: 6 } => 2,
+                SurveyChannel { band: "2.4GHz".to_string(), channel: 11 } => 1,
+                SurveyChannel { band: "5GHz".to_string(), channel: 36 } => 1,
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "SSID: \"NeighborNet\"
+Mode: Managed	RSSI: -67 dBm	SNR: 0 dB	noise: -91 dBm	Channel: 6
+BSSID: 00:11:22:33:44:55	Capability: ESS ShortSlot
+
+SSID: \"Broken\"
+Mode: Managed	RSSI: -80 dBm	SNR: 0 dB	noise: -91 dBm	Channel: 999999
+BSSID: 00:11:22:33:44:66	Capability: ESS ShortSlot
+";
+        assert_eq!(
+            SurveyClient::parse_body(body.to_string()),
+            btreemap! {
+                SurveyChannel { band: "2.4GHz".to_string(), channel: 6 } => 1,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            SurveyClient::raw_to_prom(btreemap! {
+                SurveyChannel { band: "2.4GHz".to_string(), channel: 6 } => 2,
+                SurveyChannel { band: "5GHz".to_string(), channel: 36 } => 1,
+            }),
+            vec![PromMetric::new(
+                "wifi_survey_neighbor_aps",
+                "Number of neighboring access points seen on a channel",
+                PromMetricType::Gauge,
+                vec![
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("channel", "6".to_string()),
+                            PromLabel::new("band", "2.4GHz".to_string()),
+                        ],
+                        2f64,
+                        None
+                    ),
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("channel", "36".to_string()),
+                            PromLabel::new("band", "5GHz".to_string()),
+                        ],
+                        1f64,
+                        None
+                    ),
+                ]
+            )]
+        )
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::{capture_str, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct TcpstatClient {
+    client: TomatoClientInternal,
+}
+
+impl TcpstatClient {
+    pub fn new(client: TomatoClientInternal) -> TcpstatClient {
+        TcpstatClient { client }
+    }
+
+    async fn get_tcpstat(&self) -> Result<BTreeMap<String, u32>, ExporterError> {
+        let body = self
+            .client
+            .run_command("cat /proc/net/tcp /proc/net/tcp6 2>/dev/null".to_string())
+            .await?;
+        Ok(TcpstatClient::parse_body(body))
+    }
+
+    fn state_name(state: u8) -> Option<&'static str> {
+        match state {
+            0x01 => Some("established"),
+            0x02 => Some("syn_sent"),
+            0x03 => Some("syn_recv"),
+            0x04 => Some("fin_wait1"),
+            0x05 => Some("fin_wait2"),
+            0x06 => Some("time_wait"),
+            0x07 => Some("close"),
+            0x08 => Some("close_wait"),
+            0x09 => Some("last_ack"),
+            0x0A => Some("listen"),
+            0x0B => Some("closing"),
+            _ => None,
+        }
+    }
+
+    fn parse_body(body: String) -> BTreeMap<String, u32> {
+        let conn_re = Regex::new(
+            r"(?m)^\s*[0-9]+: [0-9A-Fa-f]+:[0-9A-Fa-f]+ [0-9A-Fa-f]+:[0-9A-Fa-f]+ (?P<state>[0-9A-Fa-f]{2}) ",
+        )
+        .unwrap();
+        let mut states: BTreeMap<String, u32> = BTreeMap::new();
+        conn_re
+            .captures_iter(body.as_str())
+            .filter_map(|capture| {
+                let state = capture_str(&capture, "state").ok()?;
+                TcpstatClient::state_name(u8::from_str_radix(state, 16).ok()?)
+            })
+            .for_each(|state| *states.entry(state.to_string()).or_insert(0) += 1);
+        states
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, u32>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "node_tcp_connection_states",
+            "Number of connection states",
+            PromMetricType::Gauge,
+            raw_metrics
+                .into_iter()
+                .map(|(state, count)| {
+                    PromSample::new(vec![PromLabel::new("state", state)], count as f64, None)
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for TcpstatClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_tcpstat().await?;
+        Ok(TcpstatClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "tcpstat".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0050 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1234 1 c3a1e000 300 0 0 2 -1
+   1: 0101A8C0:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1235 1 c3a1e400 300 0 0 2 -1
+   2: 0101A8C0:0050 0A01A8C0:D431 01 00000000:00000000 02:0008A3F4 00000000     0        0 2345 2 c3a1e800 21 4 28 10 -1
+   3: 0101A8C0:0050 0B01A8C0:D432 06 00000000:00000000 03:00001774 00000000     0        0 0 3 c3a1ec00
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:0035 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 3456 1 c3a1f000 300 0 0 2 -1";
+        assert_eq!(
+            TcpstatClient::parse_body(body.to_string()),
+            btreemap! {
+                "established".to_string() => 1,
+                "listen".to_string() => 3,
+                "time_wait".to_string() => 1,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            TcpstatClient::raw_to_prom(btreemap! {
+                "established".to_string() => 1,
+                "listen".to_string() => 3,
+            }),
+            vec![PromMetric::new(
+                "node_tcp_connection_states",
+                "Number of connection states",
+                PromMetricType::Gauge,
+                vec![
+                    PromSample::new(
+                        vec![PromLabel::new("state", "established".to_string())],
+                        1f64,
+                        None
+                    ),
+                    PromSample::new(
+                        vec![PromLabel::new("state", "listen".to_string())],
+                        3f64,
+                        None
+                    ),
+                ]
+            )]
+        )
+    }
+}
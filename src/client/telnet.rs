@@ -0,0 +1,239 @@
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+// quoted apart on the command line so that the echoed command itself never matches
+const START_MARKER: &str = "::tomato-exporter-start::";
+const END_MARKER: &str = "::tomato-exporter-end::";
+
+// runs shell commands over the router's telnet console, for builds where shell.cgi doesn't work.
+// commands are sent one at a time over a single session that is shared by every scraper and opened
+// again after any failure
+#[derive(Clone)]
+pub struct TelnetClient {
+    address: String,
+    username: String,
+    password: String,
+    timeout: Duration,
+    session: Arc<Mutex<Option<TelnetSession>>>,
+}
+
+struct TelnetSession {
+    stream: TcpStream,
+    // raw bytes that haven't been split into text and telnet commands yet
+    pending: Vec<u8>,
+    text: String,
+}
+
+impl TelnetClient {
+    pub fn new(
+        address: String,
+        username: String,
+        password: String,
+        timeout: Duration,
+    ) -> TelnetClient {
+        TelnetClient {
+            address,
+            username,
+            password,
+            timeout,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub async fn run_command(&self, command: &str) -> Result<String, Error> {
+        let mut session = self.session.lock().await;
+        let result = tokio::time::timeout(self.timeout, async {
+            if session.is_none() {
+                *session = Some(self.login().await?);
+            }
+            session.as_mut().unwrap().run_command(command).await
+        })
+        .await
+        .unwrap_or_else(|_| {
+            Err(Error::new(
+                ErrorKind::TimedOut,
+                format!("timed out after {:?}", self.timeout),
+            ))
+        });
+        if result.is_err() {
+            *session = None;
+        }
+        result
+    }
+
+    async fn login(&self) -> Result<TelnetSession, Error> {
+        debug!("Logging in to telnet console at {}", self.address);
+        let mut session = TelnetSession {
+            stream: TcpStream::connect(self.address.as_str()).await?,
+            pending: Vec::new(),
+            text: String::new(),
+        };
+        session.read_until(|text| text.ends_with("login: ")).await?;
+        session.write_line(self.username.as_str()).await?;
+        session
+            .read_until(|text| text.ends_with("Password: "))
+            .await?;
+        session.write_line(self.password.as_str()).await?;
+        let text = session
+            .read_until(|text| {
+                text.ends_with("# ") || text.ends_with("$ ") || text.ends_with("login: ")
+            })
+            .await?;
+        if text.ends_with("login: ") {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "telnet login was rejected",
+            ));
+        }
+        Ok(session)
+    }
+}
+
+impl TelnetSession {
+    async fn run_command(&mut self, command: &str) -> Result<String, Error> {
+        let (start_head, start_tail) = START_MARKER.split_at(START_MARKER.len() / 2);
+        let (end_head, end_tail) = END_MARKER.split_at(END_MARKER.len() / 2);
+        self.write_line(
+            format!(
+                "echo '{}''{}'; {}; echo; echo '{}''{}'",
+                start_head, start_tail, command, end_head, end_tail
+            )
+            .as_str(),
+        )
+        .await?;
+        let text = self
+            .read_until(|text| text.contains(format!("{}\r\n", END_MARKER).as_str()))
+            .await?;
+        // the prompt printed after the command is left to be skipped by the next one
+        self.text.clear();
+        Ok(TelnetSession::command_output(text.as_str()))
+    }
+
+    // everything printed between the markers, with the newline echoed before the end marker removed
+    fn command_output(text: &str) -> String {
+        let output = text
+            .split_once(format!("{}\r\n", START_MARKER).as_str())
+            .map_or("", |(_, output)| output);
+        let output = output
+            .split_once(END_MARKER)
+            .map_or(output, |(output, _)| output);
+        let output = output.replace("\r\n", "\n");
+        output
+            .strip_suffix('\n')
+            .unwrap_or(output.as_str())
+            .to_string()
+    }
+
+    async fn write_line(&mut self, line: &str) -> Result<(), Error> {
+        self.stream
+            .write_all(format!("{}\r\n", line).as_bytes())
+            .await
+    }
+
+    // reads until the text received since the last call satisfies done, and returns that text
+    async fn read_until(&mut self, done: impl Fn(&str) -> bool) -> Result<String, Error> {
+        let mut buf = [0u8; 4096];
+        while !done(self.text.as_str()) {
+            let read = self.stream.read(&mut buf).await?;
+            if read == 0 {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "telnet connection closed",
+                ));
+            }
+            self.pending.extend_from_slice(&buf[..read]);
+            let (text, replies, consumed) = negotiate(self.pending.as_slice());
+            self.pending.drain(..consumed);
+            self.text
+                .push_str(String::from_utf8_lossy(text.as_slice()).as_ref());
+            if !replies.is_empty() {
+                self.stream.write_all(replies.as_slice()).await?;
+            }
+        }
+        Ok(std::mem::take(&mut self.text))
+    }
+}
+
+// splits received bytes into the text they carry and the replies to the option negotiation mixed in
+// with it.  the server may enable whatever options it likes, but this end refuses to enable any of
+// its own.  returns how many bytes were used, leaving an incomplete command at the end for later
+fn negotiate(raw: &[u8]) -> (Vec<u8>, Vec<u8>, usize) {
+    let mut text: Vec<u8> = Vec::new();
+    let mut replies: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] != IAC {
+            text.push(raw[i]);
+            i += 1;
+            continue;
+        }
+        match raw.get(i + 1) {
+            None => break,
+            Some(&IAC) => {
+                text.push(IAC);
+                i += 2;
+            }
+            Some(&command @ (DO | DONT | WILL | WONT)) => {
+                let Some(&option) = raw.get(i + 2) else {
+                    break;
+                };
+                match command {
+                    DO => replies.extend_from_slice(&[IAC, WONT, option]),
+                    WILL => replies.extend_from_slice(&[IAC, DO, option]),
+                    _ => {}
+                }
+                i += 3;
+            }
+            Some(&SB) => match raw[i..].windows(2).position(|window| window == [IAC, SE]) {
+                Some(end) => i += end + 2,
+                None => break,
+            },
+            Some(_) => i += 2,
+        }
+    }
+    (text, replies, i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let raw = [
+            IAC, DO, 24, IAC, WILL, 1, b'l', b'o', IAC, SB, 31, 0, 80, IAC, SE, b'g', IAC, IAC,
+            IAC, DO,
+        ];
+        let (text, replies, consumed) = negotiate(&raw);
+        assert_eq!(text, vec![b'l', b'o', b'g', IAC]);
+        assert_eq!(replies, vec![IAC, WONT, 24, IAC, DO, 1]);
+        assert_eq!(consumed, raw.len() - 2);
+    }
+
+    #[test]
+    fn test_command_output() {
+        let text = "echo '::tomato-expo''rter-start::'; cat /proc/loadavg; echo; echo '::tomato-e''xporter-end::'\r\n\
+            ::tomato-exporter-start::\r\n\
+            0.08 0.03 0.01 1/52 1234\r\n\
+            \r\n\
+            ::tomato-exporter-end::\r\n";
+        assert_eq!(
+            TelnetSession::command_output(text),
+            "0.08 0.03 0.01 1/52 1234\n".to_string()
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use regex::Regex;
 
-use crate::client::{Scraper, TomatoClientInternal};
+use crate::client::{parse_capture, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
 use crate::prometheus::{PromMetric, PromMetricType, PromSample};
 
 #[derive(Clone)]
@@ -19,62 +20,47 @@ impl TimeClient {
         TimeClient { client }
     }
 
-    async fn get_time(&self) -> Result<Times, reqwest::Error> {
+    async fn get_time(&self) -> Result<Times, ExporterError> {
         let body = self
             .client
             .run_command("date +%s && cat /proc/uptime".to_string())
             .await?;
-        Ok(TimeClient::parse_body(body))
+        TimeClient::parse_body(body)
     }
 
-    fn parse_body(body: String) -> Times {
+    fn parse_body(body: String) -> Result<Times, ExporterError> {
         let body_parser_re =
             Regex::new(r"(?s)(?P<timestamp>[0-9]+)\n(?P<up_seconds>[0-9]+\.[0-9]+) [0-9]+\.[0-9]+")
                 .unwrap();
-        body_parser_re
+        let capture = body_parser_re
             .captures(body.as_str().trim())
-            .map(|capture| {
-                let curr_timestamp = capture
-                    .name("timestamp")
-                    .unwrap()
-                    .as_str()
-                    .parse::<u64>()
-                    .unwrap();
-                let up_seconds = capture
-                    .name("up_seconds")
-                    .unwrap()
-                    .as_str()
-                    .parse::<f64>()
-                    .unwrap() as u64;
-                Times {
-                    curr_timestamp,
-                    up_timestamp: curr_timestamp - up_seconds,
-                }
-            })
-            .expect("Unable to parse times")
+            .ok_or_else(|| ExporterError::Parse("unable to parse times".to_string()))?;
+        let curr_timestamp: u64 = parse_capture(&capture, "timestamp")?;
+        let up_seconds: f64 = parse_capture(&capture, "up_seconds")?;
+        // a router whose clock hasn't been set can be up for longer than it thinks has passed since
+        // the epoch
+        Ok(Times {
+            curr_timestamp,
+            up_timestamp: curr_timestamp.saturating_sub(up_seconds as u64),
+        })
     }
 
+    // the samples are left for the scraper to stamp, since node_time_seconds is compared against
+    // the scrape time to spot a router whose clock is off
     fn raw_to_prom(raw_metrics: Times) -> Vec<PromMetric> {
+        let sample = |value: u64| PromSample::new(Vec::new(), value as f64, None);
         vec![
             PromMetric::new(
                 "node_time_seconds",
                 "System time in seconds since epoch (1970)",
                 PromMetricType::Gauge,
-                vec![PromSample::new(
-                    Vec::new(),
-                    raw_metrics.curr_timestamp as f64,
-                    None,
-                )],
+                vec![sample(raw_metrics.curr_timestamp)],
             ),
             PromMetric::new(
                 "node_boot_time_seconds",
                 "Node boot time, in unixtime",
                 PromMetricType::Gauge,
-                vec![PromSample::new(
-                    Vec::new(),
-                    raw_metrics.up_timestamp as f64,
-                    None,
-                )],
+                vec![sample(raw_metrics.up_timestamp)],
             ),
         ]
     }
@@ -82,7 +68,7 @@ impl TimeClient {
 
 #[async_trait]
 impl Scraper for TimeClient {
-    async fn get_metrics(&self) -> Result<Vec<PromMetric>, reqwest::Error> {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
         let raw_metrics = self.get_time().await?;
         Ok(TimeClient::raw_to_prom(raw_metrics))
     }
@@ -101,7 +87,7 @@ mod test {
         let body = "1598394934
 1810779.30 1804583.20";
         assert_eq!(
-            TimeClient::parse_body(body.to_string()),
+            TimeClient::parse_body(body.to_string()).unwrap(),
             Times {
                 curr_timestamp: 1598394934u64,
                 up_timestamp: 1598394934u64 - 1810779u64,
@@ -1,7 +1,8 @@
 use regex::Regex;
 
-use crate::client::{Scraper, TomatoClientInternal};
-use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+use crate::client::{capture_str, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric};
 
 #[derive(Clone)]
 pub struct UnameClient {
@@ -23,53 +24,48 @@ impl UnameClient {
         UnameClient { client }
     }
 
-    async fn get_uname(&self) -> Result<Uname, reqwest::Error> {
+    async fn get_uname(&self) -> Result<Uname, ExporterError> {
         let body = self.client.run_command("uname -a".to_string()).await?;
-        Ok(UnameClient::parse_body(body))
+        UnameClient::parse_body(body)
     }
 
-    fn parse_body(body: String) -> Uname {
+    fn parse_body(body: String) -> Result<Uname, ExporterError> {
         let uname_re = Regex::new(
             r"^(?P<sysname>[a-zA-Z]+) (?P<nodename>[a-zA-Z0-9_-]+) (?P<release>[0-9a-z._]+) (?P<version>.+) (?P<machine>[a-zA-Z0-9_-]+) [a-zA-Z0-9]+$",
         )
         .unwrap();
-        uname_re
-            .captures(body.as_str().trim())
-            .map(|caps| Uname {
-                domainname: "(none)".to_string(),
-                machine: caps.name("machine").unwrap().as_str().to_string(),
-                nodename: caps.name("nodename").unwrap().as_str().to_string(),
-                release: caps.name("release").unwrap().as_str().to_string(),
-                sysname: caps.name("sysname").unwrap().as_str().to_string(),
-                version: caps.name("version").unwrap().as_str().to_string(),
-            })
-            .expect("Unable to parse uname data from command output")
+        let caps = uname_re.captures(body.as_str().trim()).ok_or_else(|| {
+            ExporterError::Parse("unable to parse uname data from command output".to_string())
+        })?;
+        Ok(Uname {
+            domainname: "(none)".to_string(),
+            machine: capture_str(&caps, "machine")?.to_string(),
+            nodename: capture_str(&caps, "nodename")?.to_string(),
+            release: capture_str(&caps, "release")?.to_string(),
+            sysname: capture_str(&caps, "sysname")?.to_string(),
+            version: capture_str(&caps, "version")?.to_string(),
+        })
     }
 
     fn raw_to_prom(uname: Uname) -> Vec<PromMetric> {
-        vec![PromMetric::new(
+        vec![PromMetric::info(
             "node_uname_info",
             "Labeled system information as provided by the uname system call",
-            PromMetricType::Gauge,
-            vec![PromSample::new(
-                vec![
-                    PromLabel::new("domainname", uname.domainname),
-                    PromLabel::new("machine", uname.machine),
-                    PromLabel::new("nodename", uname.nodename),
-                    PromLabel::new("release", uname.release),
-                    PromLabel::new("sysname", uname.sysname),
-                    PromLabel::new("version", uname.version),
-                ],
-                1f64,
-                None,
-            )],
+            vec![vec![
+                PromLabel::new("domainname", uname.domainname),
+                PromLabel::new("machine", uname.machine),
+                PromLabel::new("nodename", uname.nodename),
+                PromLabel::new("release", uname.release),
+                PromLabel::new("sysname", uname.sysname),
+                PromLabel::new("version", uname.version),
+            ]],
         )]
     }
 }
 
 #[async_trait]
 impl Scraper for UnameClient {
-    async fn get_metrics(&self) -> Result<Vec<PromMetric>, reqwest::Error> {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
         let raw_metrics = self.get_uname().await?;
         Ok(UnameClient::raw_to_prom(raw_metrics))
     }
@@ -82,13 +78,15 @@ impl Scraper for UnameClient {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::prometheus::{PromMetricType, PromSample};
 
     #[test]
     fn test_parse_body() {
         assert_eq!(
             UnameClient::parse_body(
                 "Linux karabor 2.6.36.4brcmarm #19 SMP PREEMPT Sat Jun 5 15:55:45 CEST 2021 armv7l Tomato".to_string()
-            ),
+            )
+            .unwrap(),
             Uname {
                 domainname: "(none)".to_string(),
                 machine: "armv7l".to_string(),
@@ -114,7 +112,7 @@ mod test {
             vec![PromMetric::new(
                 "node_uname_info",
                 "Labeled system information as provided by the uname system call",
-                PromMetricType::Gauge,
+                PromMetricType::Info,
                 vec![PromSample::new(
                     vec![
                         PromLabel::new("domainname", "(none)".to_string()),
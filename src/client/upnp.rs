@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use crate::client::portforward::{PortForward, PortForwardClient};
+use crate::client::{Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct UpnpClient {
+    client: TomatoClientInternal,
+    mapping_info: bool,
+}
+
+impl UpnpClient {
+    pub fn new(client: TomatoClientInternal, mapping_info: bool) -> UpnpClient {
+        UpnpClient {
+            client,
+            mapping_info,
+        }
+    }
+
+    // miniupnpd adds a DNAT rule to its own chain for every UPnP and NAT-PMP mapping; the chain is
+    // missing entirely while the service is disabled, which reads as no mappings
+    async fn get_mappings(&self) -> Result<BTreeMap<PortForward, u64>, ExporterError> {
+        let body = self
+            .client
+            .run_command("iptables -t nat -L MINIUPNPD -v -n -x".to_string())
+            .await?;
+        Ok(PortForwardClient::parse_body(body))
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<PortForward, u64>, mapping_info: bool) -> Vec<PromMetric> {
+        let mut metrics = vec![PromMetric::new(
+            "upnp_active_mappings",
+            "Number of active UPnP and NAT-PMP port mappings",
+            PromMetricType::Gauge,
+            vec![PromSample::new(Vec::new(), raw_metrics.len() as f64, None)],
+        )];
+        if mapping_info {
+            metrics.push(PromMetric::info(
+                "upnp_mapping_info",
+                "Labeled information about an active UPnP or NAT-PMP port mapping",
+                raw_metrics
+                    .into_keys()
+                    .map(|mapping| {
+                        vec![
+                            PromLabel::new("proto", mapping.proto),
+                            PromLabel::new("ext_port", mapping.ext_port),
+                            PromLabel::new("int_addr", mapping.int_addr),
+                        ]
+                    })
+                    .collect(),
+            ));
+        }
+        metrics
+    }
+}
+
+#[async_trait]
+impl Scraper for UpnpClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_mappings().await?;
+        Ok(UpnpClient::raw_to_prom(raw_metrics, self.mapping_info))
+    }
+
+    fn get_name(&self) -> String {
+        "upnp".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mappings() -> BTreeMap<PortForward, u64> {
+        btreemap! {
+            PortForward {
+                proto: "tcp".to_string(),
+                ext_port: "51413".to_string(),
+                int_addr: "192.168.1.5:51413".to_string(),
+            } => 120,
+            PortForward {
+                proto: "udp".to_string(),
+                ext_port: "3074".to_string(),
+                int_addr: "192.168.1.40:3074".to_string(),
+            } => 0,
+        }
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            UpnpClient::raw_to_prom(mappings(), false),
+            vec![PromMetric::new(
+                "upnp_active_mappings",
+                "Number of active UPnP and NAT-PMP port mappings",
+                PromMetricType::Gauge,
+                vec![PromSample::new(Vec::new(), 2f64, None)],
+            )]
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom_mapping_info() {
+        assert_eq!(
+            UpnpClient::raw_to_prom(mappings(), true),
+            vec![
+                PromMetric::new(
+                    "upnp_active_mappings",
+                    "Number of active UPnP and NAT-PMP port mappings",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 2f64, None)],
+                ),
+                PromMetric::new(
+                    "upnp_mapping_info",
+                    "Labeled information about an active UPnP or NAT-PMP port mapping",
+                    PromMetricType::Info,
+                    vec![
+                        PromSample::new(
+                            vec![
+                                PromLabel::new("proto", "tcp".to_string()),
+                                PromLabel::new("ext_port", "51413".to_string()),
+                                PromLabel::new("int_addr", "192.168.1.5:51413".to_string()),
+                            ],
+                            1f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![
+                                PromLabel::new("proto", "udp".to_string()),
+                                PromLabel::new("ext_port", "3074".to_string()),
+                                PromLabel::new("int_addr", "192.168.1.40:3074".to_string()),
+                            ],
+                            1f64,
+                            None
+                        ),
+                    ]
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct VmstatClient {
+    client: TomatoClientInternal,
+}
+
+impl VmstatClient {
+    pub fn new(client: TomatoClientInternal) -> VmstatClient {
+        VmstatClient { client }
+    }
+
+    async fn get_vmstat(&self) -> Result<BTreeMap<String, u64>, ExporterError> {
+        let body = self
+            .client
+            .run_command("cat /proc/vmstat".to_string())
+            .await?;
+        Ok(VmstatClient::parse_body(body))
+    }
+
+    // same default field set as node_exporter's vmstat collector
+    fn parse_body(body: String) -> BTreeMap<String, u64> {
+        let field_re = Regex::new(
+            r"(?m)^(?P<name>oom_kill|pgpg(?:in|out)|pswp(?:in|out)|pg[a-z_]*fault) (?P<value>[0-9]+)$",
+        )
+        .unwrap();
+        field_re
+            .captures_iter(body.as_str())
+            .filter_map(|capture| {
+                capture_str(&capture, "name")
+                    .and_then(|name| Ok((name.to_string(), parse_capture(&capture, "value")?)))
+                    .inspect_err(|_| parse_warning(capture.get(0).map_or("", |m| m.as_str())))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, u64>) -> Vec<PromMetric> {
+        raw_metrics
+            .into_iter()
+            .map(|(name, value)| {
+                PromMetric::new(
+                    format!("node_vmstat_{}", name).as_str(),
+                    format!("/proc/vmstat information field {}", name).as_str(),
+                    PromMetricType::Untyped,
+                    vec![PromSample::new(Vec::new(), value as f64, None)],
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Scraper for VmstatClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_vmstat().await?;
+        Ok(VmstatClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "vmstat".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "nr_free_pages 5126
+nr_inactive_anon 12
+pgpgin 834560
+pgpgout 12044
+pswpin 0
+pswpout 0
+pgalloc_normal 9876543
+pgfault 4567890
+pgmajfault 1234
+pgrefill_normal 0";
+        assert_eq!(
+            VmstatClient::parse_body(body.to_string()),
+            btreemap! {
+                "pgfault".to_string() => 4567890,
+                "pgmajfault".to_string() => 1234,
+                "pgpgin".to_string() => 834560,
+                "pgpgout".to_string() => 12044,
+                "pswpin".to_string() => 0,
+                "pswpout".to_string() => 0,
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "pgpgin 834560
+pgpgout 99999999999999999999
+pgfault 4567890";
+        assert_eq!(
+            VmstatClient::parse_body(body.to_string()),
+            btreemap! {
+                "pgfault".to_string() => 4567890,
+                "pgpgin".to_string() => 834560,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            VmstatClient::raw_to_prom(btreemap! {
+                "pgfault".to_string() => 4567890,
+                "pgmajfault".to_string() => 1234,
+            }),
+            vec![
+                PromMetric::new(
+                    "node_vmstat_pgfault",
+                    "/proc/vmstat information field pgfault",
+                    PromMetricType::Untyped,
+                    vec![PromSample::new(Vec::new(), 4567890f64, None)],
+                ),
+                PromMetric::new(
+                    "node_vmstat_pgmajfault",
+                    "/proc/vmstat information field pgmajfault",
+                    PromMetricType::Untyped,
+                    vec![PromSample::new(Vec::new(), 1234f64, None)],
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::network::{NetworkClient, NetworkInterface};
+use crate::client::{parse_capture, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct VpnClientClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct VpnTunnel {
+    pub ifname: Option<String>,
+    pub connected_since: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+struct VpnClientInfo {
+    pub now: u64,
+    pub tunnels: BTreeMap<String, VpnTunnel>,
+    pub interfaces: BTreeMap<String, NetworkInterface>,
+}
+
+impl VpnClientClient {
+    pub fn new(client: TomatoClientInternal) -> VpnClientClient {
+        VpnClientClient { client }
+    }
+
+    // L2TP is set up as the WAN connection rather than a client of its own, with xl2tpd bringing up
+    // the ppp interface named in wan_iface.  xl2tpd keeps no record of when the link came up, so it
+    // has no uptime
+    async fn get_tunnels(&self) -> Result<VpnClientInfo, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "p=/var/run/ppp-pptpc.pid; \
+                e=$(nvram get pptp_client_enable); \
+                i=$(sed -n 2p $p 2>/dev/null); \
+                s=$(date -r $p +%s 2>/dev/null); \
+                echo \"tunnel pptp ${e:-0} ${i:--} ${s:--}\"; \
+                e=$([ \"$(nvram get wan_proto)\" = l2tp ] && echo 1); \
+                i=$(nvram get wan_iface); \
+                echo \"tunnel l2tp ${e:-0} ${i:--} -\"; \
+                for n in wg0 wg1 wg2; do \
+                    e=$(nvram get ${n}_enable); \
+                    echo \"tunnel $n ${e:-0} $n -\"; \
+                done; \
+                echo \"now $(date +%s)\"; \
+                cat /proc/net/dev"
+                    .to_string(),
+            )
+            .await?;
+        VpnClientClient::parse_body(body)
+    }
+
+    fn parse_body(body: String) -> Result<VpnClientInfo, ExporterError> {
+        let tunnel_re = Regex::new(
+            r"(?m)^tunnel (?P<name>\S+) (?P<enabled>\S+) (?P<ifname>\S+) (?P<since>\S+)$",
+        )
+        .unwrap();
+        let now_re = Regex::new(r"(?m)^now (?P<now>[0-9]+)$").unwrap();
+        let optional = |val: &str| {
+            if val == "-" {
+                None
+            } else {
+                Some(val.to_string())
+            }
+        };
+
+        let now = now_re
+            .captures(body.as_str())
+            .ok_or_else(|| ExporterError::Parse("unable to parse current time".to_string()))?;
+        Ok(VpnClientInfo {
+            now: parse_capture(&now, "now")?,
+            tunnels: tunnel_re
+                .captures_iter(body.as_str())
+                .filter(|capture| capture.name("enabled").unwrap().as_str() == "1")
+                .map(|capture| {
+                    Ok((
+                        capture.name("name").unwrap().as_str().to_string(),
+                        VpnTunnel {
+                            ifname: optional(capture.name("ifname").unwrap().as_str()),
+                            connected_since: match capture.name("since").unwrap().as_str() {
+                                "-" => None,
+                                _ => Some(parse_capture(&capture, "since")?),
+                            },
+                        },
+                    ))
+                })
+                .collect::<Result<_, ExporterError>>()?,
+            interfaces: NetworkClient::parse_body(body),
+        })
+    }
+
+    fn raw_to_prom(raw_metrics: VpnClientInfo) -> Vec<PromMetric> {
+        let tunnels: Vec<(&String, Option<&NetworkInterface>, &VpnTunnel)> = raw_metrics
+            .tunnels
+            .iter()
+            .map(|(name, tunnel)| {
+                (
+                    name,
+                    tunnel
+                        .ifname
+                        .as_ref()
+                        .and_then(|ifname| raw_metrics.interfaces.get(ifname)),
+                    tunnel,
+                )
+            })
+            .collect();
+        let tunnel_label = |name: &String| vec![PromLabel::new("tunnel", name.to_string())];
+
+        vec![
+            PromMetric::new(
+                "vpn_client_up",
+                "Whether the VPN client tunnel interface is up",
+                PromMetricType::Gauge,
+                tunnels
+                    .iter()
+                    .map(|(name, iface, _)| {
+                        PromSample::new(
+                            tunnel_label(name),
+                            if iface.is_some() { 1f64 } else { 0f64 },
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "vpn_client_uptime_seconds",
+                "Seconds since the VPN client tunnel connected",
+                PromMetricType::Gauge,
+                tunnels
+                    .iter()
+                    .filter(|(_, iface, _)| iface.is_some())
+                    .filter_map(|(name, _, tunnel)| {
+                        tunnel.connected_since.map(|since| {
+                            PromSample::new(
+                                tunnel_label(name),
+                                raw_metrics.now.saturating_sub(since) as f64,
+                                None,
+                            )
+                        })
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "vpn_client_receive_bytes_total",
+                "Bytes received over the VPN client tunnel",
+                PromMetricType::Counter,
+                tunnels
+                    .iter()
+                    .filter_map(|(name, iface, _)| {
+                        iface.map(|iface| {
+                            PromSample::new(tunnel_label(name), iface.rx_bytes as f64, None)
+                        })
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "vpn_client_transmit_bytes_total",
+                "Bytes transmitted over the VPN client tunnel",
+                PromMetricType::Counter,
+                tunnels
+                    .iter()
+                    .filter_map(|(name, iface, _)| {
+                        iface.map(|iface| {
+                            PromSample::new(tunnel_label(name), iface.tx_bytes as f64, None)
+                        })
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for VpnClientClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_tunnels().await?;
+        Ok(VpnClientClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "vpnclient".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "tunnel pptp 1 ppp3 1622894400
+tunnel l2tp 1 ppp0 -
+tunnel wg0 1 wg0 -
+tunnel wg1 0 wg1 -
+tunnel wg2 0 wg2 -
+now 1622901345
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop  fifo colls carrier compressed
+    lo:   20551     116    0    0    0     0          0         0    20551     116    0    0    0     0       0          0
+  ppp0:  987654    4000    0    0    0     0          0         0   456789    3000    0    0    0     0       0          0
+  ppp3:  123456    1000    0    0    0     0          0         0    65432     800    0    0    0     0       0          0";
+        let parsed = VpnClientClient::parse_body(body.to_string()).unwrap();
+        assert_eq!(parsed.now, 1622901345);
+        assert_eq!(
+            parsed.tunnels,
+            btreemap! {
+                "l2tp".to_string() => VpnTunnel {
+                    ifname: Some("ppp0".to_string()),
+                    connected_since: None,
+                },
+                "pptp".to_string() => VpnTunnel {
+                    ifname: Some("ppp3".to_string()),
+                    connected_since: Some(1622894400),
+                },
+                "wg0".to_string() => VpnTunnel {
+                    ifname: Some("wg0".to_string()),
+                    connected_since: None,
+                },
+            }
+        );
+        assert_eq!(
+            parsed.interfaces.keys().collect::<Vec<&String>>(),
+            vec!["lo", "ppp0", "ppp3"]
+        );
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        let body = "tunnel pptp 1 ppp3 1622894400
+tunnel l2tp 1 ppp0 -
+tunnel wg0 1 wg0 -
+now 1622901345
+  ppp0:  987654    4000    0    0    0     0          0         0   456789    3000    0    0    0     0       0          0
+    lo:   20551     116    0    0    0     0          0         0    20551     116    0    0    0     0       0          0
+  ppp3:  123456    1000    0    0    0     0          0         0    65432     800    0    0    0     0       0          0";
+        assert_eq!(
+            VpnClientClient::raw_to_prom(VpnClientClient::parse_body(body.to_string()).unwrap()),
+            vec![
+                PromMetric::new(
+                    "vpn_client_up",
+                    "Whether the VPN client tunnel interface is up",
+                    PromMetricType::Gauge,
+                    vec![
+                        PromSample::new(
+                            vec![PromLabel::new("tunnel", "l2tp".to_string())],
+                            1f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("tunnel", "pptp".to_string())],
+                            1f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("tunnel", "wg0".to_string())],
+                            0f64,
+                            None
+                        ),
+                    ],
+                ),
+                PromMetric::new(
+                    "vpn_client_uptime_seconds",
+                    "Seconds since the VPN client tunnel connected",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("tunnel", "pptp".to_string())],
+                        6945f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "vpn_client_receive_bytes_total",
+                    "Bytes received over the VPN client tunnel",
+                    PromMetricType::Counter,
+                    vec![
+                        PromSample::new(
+                            vec![PromLabel::new("tunnel", "l2tp".to_string())],
+                            987654f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("tunnel", "pptp".to_string())],
+                            123456f64,
+                            None
+                        ),
+                    ],
+                ),
+                PromMetric::new(
+                    "vpn_client_transmit_bytes_total",
+                    "Bytes transmitted over the VPN client tunnel",
+                    PromMetricType::Counter,
+                    vec![
+                        PromSample::new(
+                            vec![PromLabel::new("tunnel", "l2tp".to_string())],
+                            456789f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("tunnel", "pptp".to_string())],
+                            65432f64,
+                            None
+                        ),
+                    ],
+                ),
+            ]
+        )
+    }
+}
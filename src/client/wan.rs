@@ -0,0 +1,224 @@
+use regex::Regex;
+
+use crate::client::jsx::{parse_assignment, parse_nvram, unquote};
+use crate::client::{Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct WanClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct WanStatus {
+    pub up: bool,
+    pub uptime_seconds: Option<u64>,
+    pub ip: String,
+    pub gateway: String,
+    pub proto: String,
+}
+
+impl WanClient {
+    pub fn new(client: TomatoClientInternal) -> WanClient {
+        WanClient { client }
+    }
+
+    async fn get_wan(&self) -> Result<WanStatus, ExporterError> {
+        let body = self
+            .client
+            .make_request("status-data.jsx".to_string(), None)
+            .await?;
+        WanClient::parse_body(body)
+    }
+
+    fn parse_uptime(uptime: &str) -> Option<u64> {
+        let uptime_re = Regex::new(
+            r"^(?:(?P<days>[0-9]+) days?, )?(?P<hours>[0-9]+):(?P<minutes>[0-9]+):(?P<seconds>[0-9]+)$",
+        )
+        .unwrap();
+        uptime_re.captures(uptime.trim()).and_then(|capture| {
+            let field = |name: &str| {
+                capture
+                    .name(name)
+                    .map_or(Some(0), |val| val.as_str().parse::<u64>().ok())
+            };
+            Some(
+                field("days")? * 86400
+                    + field("hours")? * 3600
+                    + field("minutes")? * 60
+                    + field("seconds")?,
+            )
+        })
+    }
+
+    fn parse_body(body: String) -> Result<WanStatus, ExporterError> {
+        let nvram = parse_nvram(body.as_str());
+        let nvram_get = |key: &str| nvram.get(key).cloned().unwrap_or_default();
+        let gateway = match nvram_get("wan_gateway_get").as_str() {
+            "" | "0.0.0.0" => nvram_get("wan_gateway"),
+            gateway => gateway.to_string(),
+        };
+        Ok(WanStatus {
+            up: parse_assignment(body.as_str(), "stats.wanup")
+                .map(|wanup| wanup.starts_with("'1'") || wanup == "true")
+                .ok_or_else(|| ExporterError::Parse("unable to parse WAN status".to_string()))?,
+            uptime_seconds: parse_assignment(body.as_str(), "stats.wanuptime")
+                .and_then(|uptime| WanClient::parse_uptime(unquote(uptime.as_str()).as_str())),
+            ip: nvram_get("wan_ipaddr"),
+            gateway,
+            proto: nvram_get("wan_proto"),
+        })
+    }
+
+    fn raw_to_prom(raw_metrics: WanStatus) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "wan_up",
+                "Whether the WAN connection is up",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    Vec::new(),
+                    if raw_metrics.up { 1f64 } else { 0f64 },
+                    None,
+                )],
+            ),
+            PromMetric::new(
+                "wan_uptime_seconds",
+                "Seconds since the WAN connection was established",
+                PromMetricType::Gauge,
+                raw_metrics.uptime_seconds.map_or_else(Vec::new, |uptime| {
+                    vec![PromSample::new(Vec::new(), uptime as f64, None)]
+                }),
+            ),
+            PromMetric::info(
+                "wan_info",
+                "Labeled WAN connection information",
+                vec![vec![
+                    PromLabel::new("ip", raw_metrics.ip),
+                    PromLabel::new("gateway", raw_metrics.gateway),
+                    PromLabel::new("proto", raw_metrics.proto),
+                ]],
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for WanClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_wan().await?;
+        Ok(WanClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "wan".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "
+nvram = {
+	'wan_proto': 'dhcp',
+	'wan_ipaddr': '203.0.113.45',
+	'wan_netmask': '255.255.255.0',
+	'wan_gateway': '0.0.0.0',
+	'wan_gateway_get': '203.0.113.1',
+	'wan_hwaddr': '00:11:22:33:44:55'};
+
+sysinfo = {
+	uptime: 1810779,
+	uptime_s: '20 days, 22:59:39'};
+
+stats = { };
+
+do {
+	stats.wanip = nvram.wan_ipaddr;
+	stats.wangateway = nvram.wan_gateway_get;
+	stats.wanup = '1' == '1';
+	stats.wanuptime = '2 days, 03:04:05';
+	stats.wanlease = '0 days, 11:58:02';
+} while (0);
+";
+        assert_eq!(
+            WanClient::parse_body(body.to_string()).unwrap(),
+            WanStatus {
+                up: true,
+                uptime_seconds: Some(2 * 86400 + 3 * 3600 + 4 * 60 + 5),
+                ip: "203.0.113.45".to_string(),
+                gateway: "203.0.113.1".to_string(),
+                proto: "dhcp".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_down() {
+        let body = "
+nvram = {
+	'wan_proto': 'pppoe',
+	'wan_ipaddr': '0.0.0.0',
+	'wan_gateway': '10.0.0.1',
+	'wan_gateway_get': '0.0.0.0'};
+
+	stats.wanup = '0' == '1';
+	stats.wanuptime = '-';
+";
+        assert_eq!(
+            WanClient::parse_body(body.to_string()).unwrap(),
+            WanStatus {
+                up: false,
+                uptime_seconds: None,
+                ip: "0.0.0.0".to_string(),
+                gateway: "10.0.0.1".to_string(),
+                proto: "pppoe".to_string(),
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            WanClient::raw_to_prom(WanStatus {
+                up: true,
+                uptime_seconds: Some(183845),
+                ip: "203.0.113.45".to_string(),
+                gateway: "203.0.113.1".to_string(),
+                proto: "dhcp".to_string(),
+            }),
+            vec![
+                PromMetric::new(
+                    "wan_up",
+                    "Whether the WAN connection is up",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 1f64, None)],
+                ),
+                PromMetric::new(
+                    "wan_uptime_seconds",
+                    "Seconds since the WAN connection was established",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(Vec::new(), 183845f64, None)],
+                ),
+                PromMetric::new(
+                    "wan_info",
+                    "Labeled WAN connection information",
+                    PromMetricType::Info,
+                    vec![PromSample::new(
+                        vec![
+                            PromLabel::new("ip", "203.0.113.45".to_string()),
+                            PromLabel::new("gateway", "203.0.113.1".to_string()),
+                            PromLabel::new("proto", "dhcp".to_string()),
+                        ],
+                        1f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
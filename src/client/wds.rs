@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct WdsClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct WdsLink {
+    pub up: bool,
+    pub signal_dbm: Option<i32>,
+}
+
+impl WdsClient {
+    pub fn new(client: TomatoClientInternal) -> WdsClient {
+        WdsClient { client }
+    }
+
+    async fn get_wds(&self) -> Result<BTreeMap<String, WdsLink>, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "for i in $(nvram get wl_ifnames); do \
+                    for m in $(wl -i $i wds | cut -d' ' -f2); do \
+                        wl -i $i assoclist | grep -qi $m && s=1 || s=0; \
+                        echo \"wds $m $s $(wl -i $i rssi $m 2>/dev/null)\"; \
+                    done; \
+                done"
+                    .to_string(),
+            )
+            .await?;
+        Ok(WdsClient::parse_body(body))
+    }
+
+    // the signal is only meaningful while the peer is associated
+    fn parse_link(capture: &Captures) -> Result<(String, WdsLink), ExporterError> {
+        let up = capture_str(capture, "up")? == "1";
+        Ok((
+            capture_str(capture, "mac")?.to_lowercase(),
+            WdsLink {
+                up,
+                signal_dbm: match capture.name("rssi") {
+                    Some(_) if up => Some(parse_capture(capture, "rssi")?),
+                    _ => None,
+                },
+            },
+        ))
+    }
+
+    fn parse_body(body: String) -> BTreeMap<String, WdsLink> {
+        let link_re =
+            Regex::new(r"(?m)^wds (?P<mac>[0-9A-Fa-f:]{17}) (?P<up>[01]) ?(?P<rssi>-?[0-9]+)?\s*$")
+                .unwrap();
+        link_re
+            .captures_iter(body.as_str())
+            .filter_map(|capture| {
+                WdsClient::parse_link(&capture)
+                    .inspect_err(|_| parse_warning(capture.get(0).map_or("", |m| m.as_str())))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, WdsLink>) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "wds_link_up",
+                "Whether the WDS peer is associated",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .iter()
+                    .map(|(mac, link)| {
+                        PromSample::new(
+                            vec![PromLabel::new("peer_mac", mac.to_string())],
+                            if link.up { 1f64 } else { 0f64 },
+                            None,
+                        )
+                    })
+                    .collect(),
+            ),
+            PromMetric::new(
+                "wds_link_signal_dbm",
+                "Received signal strength of the WDS peer in dBm",
+                PromMetricType::Gauge,
+                raw_metrics
+                    .iter()
+                    .filter_map(|(mac, link)| {
+                        link.signal_dbm.map(|signal| {
+                            PromSample::new(
+                                vec![PromLabel::new("peer_mac", mac.to_string())],
+                                signal as f64,
+                                None,
+                            )
+                        })
+                    })
+                    .collect(),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for WdsClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_wds().await?;
+        Ok(WdsClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "wds".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "wds 00:11:22:33:44:55 1 -58
+wds 00:11:22:33:44:66 0
+wds AA:BB:CC:DD:EE:FF 0 0
+";
+        assert_eq!(
+            WdsClient::parse_body(body.to_string()),
+            btreemap! {
+                "00:11:22:33:44:55".to_string() => WdsLink { up: true, signal_dbm: Some(-58) },
+                "00:11:22:33:44:66".to_string() => WdsLink { up: false, signal_dbm: None },
+                "aa:bb:cc:dd:ee:ff".to_string() => WdsLink { up: false, signal_dbm: None },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "wds 00:11:22:33:44:55 1 -58
+wds 00:11:22:33:44:66 1 -99999999999
+";
+        assert_eq!(
+            WdsClient::parse_body(body.to_string()),
+            btreemap! {
+                "00:11:22:33:44:55".to_string() => WdsLink { up: true, signal_dbm: Some(-58) },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            WdsClient::raw_to_prom(btreemap! {
+                "00:11:22:33:44:55".to_string() => WdsLink { up: true, signal_dbm: Some(-58) },
+                "00:11:22:33:44:66".to_string() => WdsLink { up: false, signal_dbm: None },
+            }),
+            vec![
+                PromMetric::new(
+                    "wds_link_up",
+                    "Whether the WDS peer is associated",
+                    PromMetricType::Gauge,
+                    vec![
+                        PromSample::new(
+                            vec![PromLabel::new("peer_mac", "00:11:22:33:44:55".to_string())],
+                            1f64,
+                            None
+                        ),
+                        PromSample::new(
+                            vec![PromLabel::new("peer_mac", "00:11:22:33:44:66".to_string())],
+                            0f64,
+                            None
+                        ),
+                    ],
+                ),
+                PromMetric::new(
+                    "wds_link_signal_dbm",
+                    "Received signal strength of the WDS peer in dBm",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("peer_mac", "00:11:22:33:44:55".to_string())],
+                        -58f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use regex::{Captures, Regex};
+
+use crate::client::{capture_str, parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct WifiClientsClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct WirelessInterface {
+    pub iface: String,
+    pub ssid: String,
+}
+
+impl WifiClientsClient {
+    pub fn new(client: TomatoClientInternal) -> WifiClientsClient {
+        WifiClientsClient { client }
+    }
+
+    async fn get_clients(&self) -> Result<BTreeMap<WirelessInterface, u32>, ExporterError> {
+        // virtual interfaces such as guest networks are listed in wlN_vifs rather than wl_ifnames
+        let body = self
+            .client
+            .run_command(
+                "for i in $(nvram get wl_ifnames) $(nvram get wl0_vifs) $(nvram get wl1_vifs) $(nvram get wl2_vifs); do \
+                    echo \"$i $(wl -i $i assoclist 2>/dev/null | wc -l) $(wl -i $i ssid 2>/dev/null)\"; \
+                done"
+                    .to_string(),
+            )
+            .await?;
+        Ok(WifiClientsClient::parse_body(body))
+    }
+
+    fn parse_iface(capture: &Captures) -> Result<(WirelessInterface, u32), ExporterError> {
+        Ok((
+            WirelessInterface {
+                iface: capture_str(capture, "iface")?.to_string(),
+                ssid: capture_str(capture, "ssid")?.to_string(),
+            },
+            parse_capture(capture, "clients")?,
+        ))
+    }
+
+    fn parse_body(body: String) -> BTreeMap<WirelessInterface, u32> {
+        let iface_re =
+            Regex::new(r#"(?m)^(?P<iface>\S+) +(?P<clients>[0-9]+) Current SSID: "(?P<ssid>.*)"$"#)
+                .unwrap();
+        iface_re
+            .captures_iter(body.as_str())
+            .filter_map(|capture| {
+                WifiClientsClient::parse_iface(&capture)
+                    .inspect_err(|_| parse_warning(capture.get(0).map_or("", |m| m.as_str())))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<WirelessInterface, u32>) -> Vec<PromMetric> {
+        vec![PromMetric::new(
+            "wifi_clients",
+            "Number of clients associated with a wireless interface",
+            PromMetricType::Gauge,
+            raw_metrics
+                .into_iter()
+                .map(|(iface, clients)| {
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("iface", iface.iface),
+                            PromLabel::new("ssid", iface.ssid),
+                        ],
+                        clients as f64,
+                        None,
+                    )
+                })
+                .collect(),
+        )]
+    }
+}
+
+#[async_trait]
+impl Scraper for WifiClientsClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_clients().await?;
+        Ok(WifiClientsClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "wificlients".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "eth1 5 Current SSID: \"HomeNet\"
+eth2 2 Current SSID: \"HomeNet-5G\"
+wl0.1 1 Current SSID: \"HomeNet-Guest\"
+wl1.1 0
+";
+        assert_eq!(
+            WifiClientsClient::parse_body(body.to_string()),
+            btreemap! {
+                WirelessInterface { iface: "eth1".to_string(), ssid: "HomeNet".to_string() } => 5,
+                WirelessInterface { iface: "eth2".to_string(), ssid: "HomeNet-5G".to_string() } => 2,
+                WirelessInterface { iface: "wl0.1".to_string(), ssid: "HomeNet-Guest".to_string() } => 1,
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "eth1 5 Current SSID: \"HomeNet\"
+eth2 99999999999 Current SSID: \"HomeNet-5G\"
+";
+        assert_eq!(
+            WifiClientsClient::parse_body(body.to_string()),
+            btreemap! {
+                WirelessInterface { iface: "eth1".to_string(), ssid: "HomeNet".to_string() } => 5,
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            WifiClientsClient::raw_to_prom(btreemap! {
+                WirelessInterface { iface: "wl0.1".to_string(), ssid: "HomeNet-Guest".to_string() } => 1,
+            }),
+            vec![PromMetric::new(
+                "wifi_clients",
+                "Number of clients associated with a wireless interface",
+                PromMetricType::Gauge,
+                vec![PromSample::new(
+                    vec![
+                        PromLabel::new("iface", "wl0.1".to_string()),
+                        PromLabel::new("ssid", "HomeNet-Guest".to_string()),
+                    ],
+                    1f64,
+                    None
+                )]
+            )]
+        )
+    }
+}
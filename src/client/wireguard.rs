@@ -0,0 +1,205 @@
+use crate::client::{parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+// WireGuard tunnels were added in FreshTomato 2023.1
+#[derive(Clone)]
+pub struct WireguardClient {
+    client: TomatoClientInternal,
+}
+
+#[derive(Debug, PartialEq)]
+struct WireguardPeer {
+    pub interface: String,
+    pub public_key: String,
+    pub latest_handshake: u64,
+    pub received_bytes: u64,
+    pub sent_bytes: u64,
+}
+
+impl WireguardClient {
+    pub fn new(client: TomatoClientInternal) -> WireguardClient {
+        WireguardClient { client }
+    }
+
+    async fn get_peers(&self) -> Result<Vec<WireguardPeer>, ExporterError> {
+        let body = self
+            .client
+            .run_command("wg show all dump 2>/dev/null".to_string())
+            .await?;
+        Ok(WireguardClient::parse_body(body))
+    }
+
+    // each interface is listed with 5 fields followed by its peers with 9 fields each.  the interface
+    // lines carry the private key, so only the peer lines are read
+    fn parse_body(body: String) -> Vec<WireguardPeer> {
+        body.lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                let [interface, public_key, _, _, _, handshake, received, sent, _] =
+                    fields.as_slice()
+                else {
+                    return None;
+                };
+                let peer = handshake
+                    .parse::<u64>()
+                    .ok()
+                    .zip(received.parse::<u64>().ok())
+                    .zip(sent.parse::<u64>().ok())
+                    .map(
+                        |((latest_handshake, received_bytes), sent_bytes)| WireguardPeer {
+                            interface: interface.to_string(),
+                            public_key: public_key.to_string(),
+                            latest_handshake,
+                            received_bytes,
+                            sent_bytes,
+                        },
+                    );
+                if peer.is_none() {
+                    parse_warning(line);
+                }
+                peer
+            })
+            .collect()
+    }
+
+    fn samples(peers: &[WireguardPeer], value: impl Fn(&WireguardPeer) -> u64) -> Vec<PromSample> {
+        peers
+            .iter()
+            .map(|peer| {
+                PromSample::new(
+                    vec![
+                        PromLabel::new("interface", peer.interface.clone()),
+                        PromLabel::new("public_key", peer.public_key.clone()),
+                    ],
+                    value(peer) as f64,
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: Vec<WireguardPeer>) -> Vec<PromMetric> {
+        vec![
+            PromMetric::new(
+                "wireguard_latest_handshake_seconds",
+                "Seconds since the epoch of the latest handshake with the peer, 0 if there was none",
+                PromMetricType::Gauge,
+                WireguardClient::samples(&raw_metrics, |peer| peer.latest_handshake),
+            ),
+            PromMetric::new(
+                "wireguard_received_bytes_total",
+                "Bytes received from the peer",
+                PromMetricType::Counter,
+                WireguardClient::samples(&raw_metrics, |peer| peer.received_bytes),
+            ),
+            PromMetric::new(
+                "wireguard_sent_bytes_total",
+                "Bytes sent to the peer",
+                PromMetricType::Counter,
+                WireguardClient::samples(&raw_metrics, |peer| peer.sent_bytes),
+            ),
+        ]
+    }
+}
+
+#[async_trait]
+impl Scraper for WireguardClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_peers().await?;
+        Ok(WireguardClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "wireguard".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "wg0\tqE2vBvdqjnNxbD1bXLCkU6Hk0F3d3nbuRyzVmIuMhWY=\tm4Zx9mTJ1oUjXx4I8rKz4bJ1uW3c9oX9Vq0bB0Lk3n0=\t51820\toff
+wg0\tb3JrZXN0cmFfcGVlcl9wdWJsaWNfa2V5X2Zvcl90ZXN0PQ=\t(none)\t203.0.113.7:51820\t10.6.0.2/32\t1697040000\t1520384\t8765432\t25
+wg0\tc2Vjb25kX3BlZXJfcHVibGljX2tleV9mb3JfdGVzdHM9PQ=\t(none)\t(none)\t10.6.0.3/32\t0\t0\t0\toff";
+        assert_eq!(
+            WireguardClient::parse_body(body.to_string()),
+            vec![
+                WireguardPeer {
+                    interface: "wg0".to_string(),
+                    public_key: "b3JrZXN0cmFfcGVlcl9wdWJsaWNfa2V5X2Zvcl90ZXN0PQ=".to_string(),
+                    latest_handshake: 1697040000,
+                    received_bytes: 1520384,
+                    sent_bytes: 8765432,
+                },
+                WireguardPeer {
+                    interface: "wg0".to_string(),
+                    public_key: "c2Vjb25kX3BlZXJfcHVibGljX2tleV9mb3JfdGVzdHM9PQ=".to_string(),
+                    latest_handshake: 0,
+                    received_bytes: 0,
+                    sent_bytes: 0,
+                },
+            ]
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "wg0\tb3JrZXN0cmFfcGVlcl9wdWJsaWNfa2V5X2Zvcl90ZXN0PQ=\t(none)\t203.0.113.7:51820\t10.6.0.2/32\t1697040000\t1520384\t8765432\t25
+wg0\tc2Vjb25kX3BlZXJfcHVibGljX2tleV9mb3JfdGVzdHM9PQ=\t(none)\t(none)\t10.6.0.3/32\tnever\t0\t99999999999999999999\toff";
+        assert_eq!(
+            WireguardClient::parse_body(body.to_string()),
+            vec![WireguardPeer {
+                interface: "wg0".to_string(),
+                public_key: "b3JrZXN0cmFfcGVlcl9wdWJsaWNfa2V5X2Zvcl90ZXN0PQ=".to_string(),
+                latest_handshake: 1697040000,
+                received_bytes: 1520384,
+                sent_bytes: 8765432,
+            }]
+        )
+    }
+
+    #[test]
+    fn test_parse_body_unsupported() {
+        assert_eq!(WireguardClient::parse_body("".to_string()), vec![])
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        let labels = vec![
+            PromLabel::new("interface", "wg0".to_string()),
+            PromLabel::new("public_key", "cGVlcg==".to_string()),
+        ];
+        assert_eq!(
+            WireguardClient::raw_to_prom(vec![WireguardPeer {
+                interface: "wg0".to_string(),
+                public_key: "cGVlcg==".to_string(),
+                latest_handshake: 1697040000,
+                received_bytes: 1520384,
+                sent_bytes: 8765432,
+            }]),
+            vec![
+                PromMetric::new(
+                    "wireguard_latest_handshake_seconds",
+                    "Seconds since the epoch of the latest handshake with the peer, 0 if there was none",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(labels.clone(), 1697040000f64, None)],
+                ),
+                PromMetric::new(
+                    "wireguard_received_bytes_total",
+                    "Bytes received from the peer",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(labels.clone(), 1520384f64, None)],
+                ),
+                PromMetric::new(
+                    "wireguard_sent_bytes_total",
+                    "Bytes sent to the peer",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(labels, 8765432f64, None)],
+                ),
+            ]
+        )
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::client::{parse_capture, parse_warning, Scraper, TomatoClientInternal};
+use crate::error::ExporterError;
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+#[derive(Clone)]
+pub struct WlCountersClient {
+    client: TomatoClientInternal,
+}
+
+const COUNTERS: [(&str, &str, &str); 3] = [
+    (
+        "txfail",
+        "wifi_transmit_failures_total",
+        "Frames the radio failed to transmit",
+    ),
+    (
+        "txretrans",
+        "wifi_transmit_retransmissions_total",
+        "Frames the radio retransmitted",
+    ),
+    (
+        "rxcrc",
+        "wifi_receive_crc_errors_total",
+        "Frames the radio received with a bad CRC",
+    ),
+];
+
+impl WlCountersClient {
+    pub fn new(client: TomatoClientInternal) -> WlCountersClient {
+        WlCountersClient { client }
+    }
+
+    async fn get_counters(&self) -> Result<BTreeMap<String, BTreeMap<String, u64>>, ExporterError> {
+        let body = self
+            .client
+            .run_command(
+                "for i in $(nvram get wl_ifnames); do \
+                    echo \"==> $i <==\"; \
+                    wl -i $i counters; \
+                done"
+                    .to_string(),
+            )
+            .await?;
+        Ok(WlCountersClient::parse_body(body))
+    }
+
+    // wl prints counters as whitespace separated "name value" pairs wrapped over several lines
+    fn parse_body(body: String) -> BTreeMap<String, BTreeMap<String, u64>> {
+        let header_re = Regex::new(r"(?m)^==> (?P<iface>\S+) <==$").unwrap();
+        let counter_re = Regex::new(r"(?P<name>[a-z0-9_]+) (?P<value>[0-9]+)").unwrap();
+
+        let headers: Vec<_> = header_re.captures_iter(body.as_str()).collect();
+        headers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, capture)| {
+                let start = capture.get(0)?.end();
+                let end = headers
+                    .get(i + 1)
+                    .and_then(|next| next.get(0))
+                    .map_or(body.len(), |next| next.start());
+                Some((
+                    capture.name("iface")?.as_str().to_string(),
+                    counter_re
+                        .captures_iter(&body[start..end])
+                        .filter_map(|counter| {
+                            let name = counter.name("name")?.as_str();
+                            if !COUNTERS.iter().any(|(counter, _, _)| *counter == name) {
+                                return None;
+                            }
+                            parse_capture(&counter, "value")
+                                .inspect_err(|_| {
+                                    parse_warning(counter.get(0).map_or("", |m| m.as_str()))
+                                })
+                                .ok()
+                                .map(|value| (name.to_string(), value))
+                        })
+                        .collect(),
+                ))
+            })
+            .collect()
+    }
+
+    fn raw_to_prom(raw_metrics: BTreeMap<String, BTreeMap<String, u64>>) -> Vec<PromMetric> {
+        COUNTERS
+            .iter()
+            .map(|(counter, name, help)| {
+                PromMetric::new(
+                    name,
+                    help,
+                    PromMetricType::Counter,
+                    raw_metrics
+                        .iter()
+                        .filter_map(|(iface, counters)| {
+                            counters.get(*counter).map(|value| {
+                                PromSample::new(
+                                    vec![PromLabel::new("iface", iface.to_string())],
+                                    *value as f64,
+                                    None,
+                                )
+                            })
+                        })
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Scraper for WlCountersClient {
+    async fn get_metrics(&self) -> Result<Vec<PromMetric>, ExporterError> {
+        let raw_metrics = self.get_counters().await?;
+        Ok(WlCountersClient::raw_to_prom(raw_metrics))
+    }
+
+    fn get_name(&self) -> String {
+        "wlcounters".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_body() {
+        let body = "==> eth1 <==
+txframe 1234567 txbyte 987654321 txretrans 4321 txerror 12 txctl 0 txprshort 0 txserr 0
+txnobuf 0 txnoassoc 0 txrunt 0 txchit 0 txcmiss 0 txfail 87
+rxframe 2345678 rxbyte 123456789 rxerror 3 rxctl 0 rxnobuf 0 rxnondata 0 rxbadds 0
+rxbadcm 0 rxfragerr 0 rxrunt 0 rxgiant 0 rxnoscb 0 rxbadproto 0 rxbadsrcmac 0 rxcrc 5678
+==> eth2 <==
+txframe 42 txretrans 1 txfail 0
+rxframe 42 rxcrc 9
+";
+        assert_eq!(
+            WlCountersClient::parse_body(body.to_string()),
+            btreemap! {
+                "eth1".to_string() => btreemap! {
+                    "rxcrc".to_string() => 5678,
+                    "txfail".to_string() => 87,
+                    "txretrans".to_string() => 4321,
+                },
+                "eth2".to_string() => btreemap! {
+                    "rxcrc".to_string() => 9,
+                    "txfail".to_string() => 0,
+                    "txretrans".to_string() => 1,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_parse_body_malformed() {
+        let body = "==> eth1 <==
+txframe 1234567 txretrans 99999999999999999999 txfail 87
+rxframe 2345678 rxcrc 5678
+";
+        assert_eq!(
+            WlCountersClient::parse_body(body.to_string()),
+            btreemap! {
+                "eth1".to_string() => btreemap! {
+                    "rxcrc".to_string() => 5678,
+                    "txfail".to_string() => 87,
+                },
+            }
+        )
+    }
+
+    #[test]
+    fn test_raw_to_prom() {
+        assert_eq!(
+            WlCountersClient::raw_to_prom(btreemap! {
+                "eth1".to_string() => btreemap! {
+                    "rxcrc".to_string() => 5678,
+                    "txfail".to_string() => 87,
+                    "txretrans".to_string() => 4321,
+                },
+            }),
+            vec![
+                PromMetric::new(
+                    "wifi_transmit_failures_total",
+                    "Frames the radio failed to transmit",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("iface", "eth1".to_string())],
+                        87f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "wifi_transmit_retransmissions_total",
+                    "Frames the radio retransmitted",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("iface", "eth1".to_string())],
+                        4321f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "wifi_receive_crc_errors_total",
+                    "Frames the radio received with a bad CRC",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("iface", "eth1".to_string())],
+                        5678f64,
+                        None
+                    )],
+                ),
+            ]
+        )
+    }
+}
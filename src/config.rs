@@ -1,20 +1,2181 @@
+use std::collections::BTreeMap;
+use std::env;
 use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 
+use regex::Regex;
 use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
 
-pub fn load_conf(path: String) -> Config {
-    let conf_str = fs::read_to_string(path).expect("Unable to find config file");
-    let conf: Config = serde_json::from_str(conf_str.as_str()).expect("Unable to load config file");
-    conf
+use crate::prometheus::DEFAULT_NAMESPACE;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    pub fn from_name(name: &str) -> Option<ConfigFormat> {
+        match name {
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            "toml" => Some(ConfigFormat::Toml),
+            "json" => Some(ConfigFormat::Json),
+            _ => None,
+        }
+    }
+
+    // files without a recognized extension are assumed to be yaml, matching the default conf.yaml
+    fn from_path(path: &str) -> ConfigFormat {
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| ConfigFormat::from_name(ext.to_lowercase().as_str()))
+            .unwrap_or(ConfigFormat::Yaml)
+    }
+}
+
+pub fn load_conf(path: String, format: Option<ConfigFormat>) -> Result<RawConfig, Vec<String>> {
+    if Path::new(&path).is_dir() {
+        return match format {
+            None | Some(ConfigFormat::Yaml) => load_conf_dir(path),
+            Some(_) => Err(vec![format!(
+                "config directory {} can only contain yaml files",
+                path
+            )]),
+        };
+    }
+    let conf_str = fs::read_to_string(&path)
+        .map_err(|err| vec![format!("unable to read config file {}: {}", path, err)])?;
+    parse_conf(
+        conf_str,
+        format.unwrap_or_else(|| ConfigFormat::from_path(path.as_str())),
+    )
+}
+
+// every *.yaml file in the directory is merged in lexical order, so that later files can override
+// individual settings from earlier ones, such as keeping credentials in their own file
+fn load_conf_dir(path: String) -> Result<RawConfig, Vec<String>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(&path)
+        .map_err(|err| vec![format!("unable to read config directory {}: {}", path, err)])?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.is_file() && file.extension().is_some_and(|ext| ext == "yaml"))
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(vec![format!("no *.yaml files found in {}", path)]);
+    }
+
+    let mut merged = serde_yaml::Value::Null;
+    let mut problems: Vec<String> = Vec::new();
+    for file in files {
+        match fs::read_to_string(&file)
+            .map_err(|err| err.to_string())
+            .and_then(|conf_str| {
+                serde_yaml::from_str::<serde_yaml::Value>(conf_str.as_str())
+                    .map_err(|err| err.to_string())
+            }) {
+            Ok(value) => merge_yaml(&mut merged, value),
+            Err(err) => problems.push(format!("{}: {}", file.display(), err)),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    serde_yaml::from_value(merged).map_err(|err| vec![format!("{}: {}", path, err)])
+}
+
+// mappings are merged key by key, anything else in the overlay replaces what was there before
+fn merge_yaml(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_yaml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (_, serde_yaml::Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn parse_conf(conf_str: String, format: ConfigFormat) -> Result<RawConfig, Vec<String>> {
+    let raw: Result<RawConfig, String> = match format {
+        ConfigFormat::Yaml => {
+            serde_yaml::from_str(conf_str.as_str()).map_err(|err| err.to_string())
+        }
+        ConfigFormat::Toml => toml::from_str(conf_str.as_str()).map_err(|err| err.to_string()),
+        ConfigFormat::Json => {
+            serde_json::from_str(conf_str.as_str()).map_err(|err| err.to_string())
+        }
+    };
+    raw.map(|raw| RawConfig {
+        source: conf_str,
+        ..raw
+    })
+    .map_err(|err| vec![err])
+}
+
+const DEFAULT_COLLECTOR_TIMEOUT: f64 = 10f64;
+const DEFAULT_ROUTER_TIMEOUT: f64 = 5f64;
+const DEFAULT_TELNET_PORT: u16 = 23;
+const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+
+const EXAMPLE_CONF: &str = include_str!("../example.yaml");
+
+// the config described by example.yaml, used to work out the defaults shown by generate-config
+pub fn example_conf() -> Config {
+    parse_conf(EXAMPLE_CONF.to_string(), ConfigFormat::Yaml)
+        .and_then(RawConfig::validate)
+        .expect("example config is invalid")
+}
+
+// example.yaml with the collectors section expanded to list every collector and its default, so
+// that newly added collectors show up without having to keep the example in sync by hand
+pub fn generate_conf(collectors: &[(String, bool)]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_collectors = false;
+    for line in EXAMPLE_CONF.lines() {
+        if in_collectors && line.starts_with("#   ") {
+            continue;
+        }
+        in_collectors = line == "# collectors:";
+        lines.push(line.to_string());
+        if in_collectors {
+            lines.extend(
+                collectors
+                    .iter()
+                    .map(|(name, enabled)| format!("#   {}: {}", name, enabled)),
+            );
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+// the config as written in the file, before required fields have been checked.  command line
+// overrides are applied to this before it is validated
+#[derive(Debug, Default, Deserialize)]
+pub struct RawConfig {
+    pub ip: Option<String>,
+    pub port: Option<i64>,
+    #[serde(default)]
+    pub listen: Vec<String>,
+    pub listen_socket: Option<String>,
+    pub tls_cert_file: Option<String>,
+    pub tls_key_file: Option<String>,
+    #[serde(default)]
+    pub basic_auth_users: BTreeMap<String, String>,
+    pub slug: Option<String>,
+    pub router_ip: Option<String>,
+    #[serde(default)]
+    pub router_https: bool,
+    #[serde(default)]
+    pub router_insecure_skip_verify: bool,
+    pub router_ca_file: Option<String>,
+    pub router_auth: Option<RouterAuth>,
+    pub router_timeout: Option<f64>,
+    pub router_proxy: Option<String>,
+    #[serde(default)]
+    pub router_charset: Charset,
+    #[serde(default)]
+    pub firmware: Firmware,
+    #[serde(default)]
+    pub transport: Transport,
+    pub telnet_port: Option<i64>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    pub admin_username: Option<String>,
+    pub admin_password: Option<String>,
+    pub admin_password_file: Option<String>,
+    pub admin_password_env: Option<String>,
+    pub http_id: Option<String>,
+    #[serde(default)]
+    pub wifi_survey: bool,
+    #[serde(default)]
+    pub firewall_chains: Vec<String>,
+    #[serde(default)]
+    pub upnp_mapping_info: bool,
+    #[serde(default)]
+    pub collectors: BTreeMap<String, bool>,
+    pub collector_timeout: Option<f64>,
+    pub scrape_timeout: Option<f64>,
+    pub cache_ttl: Option<f64>,
+    pub failed_scrape_status: Option<i64>,
+    pub shutdown_timeout: Option<u64>,
+    #[serde(default)]
+    pub collector_timeouts: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub non_finite_values: BTreeMap<String, NonFiniteValues>,
+    pub log_level: Option<String>,
+    pub log_file: Option<LogFileConfig>,
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub relabel: Vec<RelabelRule>,
+    #[serde(default)]
+    pub const_labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub drop_zero_counters: bool,
+    #[serde(default)]
+    pub debug_endpoints: bool,
+    pub capture_parse_failures: Option<CaptureConfig>,
+    pub pushgateway: Option<PushgatewayConfig>,
+    pub remote_write: Option<RemoteWriteConfig>,
+    pub graphite: Option<GraphiteConfig>,
+    pub textfile: Option<TextfileConfig>,
+    pub otlp: Option<OtlpConfig>,
+    #[serde(skip)]
+    source: String,
+}
+
+impl RawConfig {
+    pub fn validate(self) -> Result<Config, Vec<String>> {
+        let admin_password = self.resolve_admin_password();
+        let mut password_problem: Option<String> = None;
+        let mut problems: Vec<String> = Vec::new();
+        let mut required = |name: &str, value: Option<String>| match value {
+            Some(value) if !value.trim().is_empty() => value,
+            Some(_) => {
+                problems.push(self.problem(name, "must not be empty"));
+                String::new()
+            }
+            None => {
+                problems.push(format!("{}: missing required field", name));
+                String::new()
+            }
+        };
+        // ip and port may be left out when every address to listen on is given in listen or
+        // listen_socket instead, or when the metrics are only pushed to a pushgateway, remote write
+        // endpoint, Carbon server or OpenTelemetry collector, or written to a file
+        let listen_on_ip = (self.listen.is_empty()
+            && self.listen_socket.is_none()
+            && self.pushgateway.is_none()
+            && self.remote_write.is_none()
+            && self.graphite.is_none()
+            && self.textfile.is_none()
+            && self.otlp.is_none())
+            || self.ip.is_some()
+            || self.port.is_some();
+        let ip = if listen_on_ip {
+            required("ip", self.ip.clone())
+        } else {
+            String::new()
+        };
+        let slug = self.slug.clone().unwrap_or_default();
+        let router_ip = required("router_ip", self.router_ip.clone());
+        let admin_username = required("admin_username", self.admin_username.clone());
+        let admin_password = match admin_password {
+            Ok(password) => required("admin_password", password),
+            Err(problem) => {
+                password_problem = Some(problem);
+                String::new()
+            }
+        };
+        // only Tomato's form posts take an http_id
+        let http_id = if matches!(
+            self.firmware,
+            Firmware::Ddwrt | Firmware::Merlin | Firmware::OpenWrt
+        ) {
+            self.http_id.clone().unwrap_or_default()
+        } else {
+            required("http_id", self.http_id.clone())
+        };
+
+        problems.extend(password_problem);
+        let ip_addr = ip.parse::<IpAddr>().ok();
+        if !ip.is_empty() && ip_addr.is_none() {
+            problems
+                .push(self.problem("ip", format!("'{}' is not a valid IP address", ip).as_str()));
+        }
+        let port = match self.port {
+            Some(port) if (1..=u16::MAX as i64).contains(&port) => port as u16,
+            Some(port) => {
+                problems.push(self.problem(
+                    "port",
+                    format!("{} is out of range (1-{})", port, u16::MAX).as_str(),
+                ));
+                0
+            }
+            None if listen_on_ip => {
+                problems.push("port: missing required field".to_string());
+                0
+            }
+            None => 0,
+        };
+        let mut listen: Vec<SocketAddr> = ip_addr
+            .filter(|_| port != 0)
+            .map(|ip_addr| SocketAddr::new(ip_addr, port))
+            .into_iter()
+            .collect();
+        for address in &self.listen {
+            match address.parse::<SocketAddr>() {
+                Ok(address) => listen.push(address),
+                Err(_) => problems.push(
+                    self.problem(
+                        "listen",
+                        format!(
+                        "'{}' is not a valid address and port, such as 0.0.0.0:8080 or [::]:8080",
+                        address
+                    )
+                        .as_str(),
+                    ),
+                ),
+            }
+        }
+
+        for (name, timeout) in [
+            ("router_timeout", self.router_timeout),
+            ("collector_timeout", self.collector_timeout),
+            ("scrape_timeout", self.scrape_timeout),
+            ("cache_ttl", self.cache_ttl),
+        ] {
+            if let Some(problem) = timeout.and_then(seconds_problem) {
+                problems.push(self.problem(name, problem.as_str()));
+            }
+        }
+        if let Some(breaker) = &self.circuit_breaker {
+            if breaker.failures == 0 {
+                problems.push(self.problem_at(
+                    "circuit_breaker.failures",
+                    "failures",
+                    "must be greater than 0",
+                ));
+            }
+            if let Some(problem) = seconds_problem(breaker.cooldown) {
+                problems.push(self.problem_at(
+                    "circuit_breaker.cooldown",
+                    "cooldown",
+                    problem.as_str(),
+                ));
+            }
+        }
+        for (name, timeout) in &self.collector_timeouts {
+            if let Some(problem) = seconds_problem(*timeout) {
+                problems.push(self.problem_at(
+                    format!("collector_timeouts.{}", name).as_str(),
+                    name,
+                    problem.as_str(),
+                ));
+            }
+        }
+
+        let telnet_port = match self.telnet_port {
+            Some(port) if (1..=u16::MAX as i64).contains(&port) => port as u16,
+            Some(port) => {
+                problems.push(self.problem(
+                    "telnet_port",
+                    format!("{} is out of range (1-{})", port, u16::MAX).as_str(),
+                ));
+                0
+            }
+            None => DEFAULT_TELNET_PORT,
+        };
+
+        let failed_scrape_status = match self.failed_scrape_status {
+            Some(status) if (200..=599).contains(&status) => Some(status as u16),
+            Some(status) => {
+                problems.push(self.problem(
+                    "failed_scrape_status",
+                    format!("{} is not an HTTP status code (200-599)", status).as_str(),
+                ));
+                None
+            }
+            None => None,
+        };
+
+        if let Some(level) = &self.log_level {
+            if let Err(err) = EnvFilter::try_new(level) {
+                problems.push(self.problem(
+                    "log_level",
+                    format!("'{}' is not a valid log filter: {}", level, err).as_str(),
+                ));
+            }
+        }
+        if let Some(log_file) = &self.log_file {
+            if log_file.path.ends_with('/')
+                || std::path::Path::new(log_file.path.as_str())
+                    .file_name()
+                    .is_none()
+            {
+                problems.push(self.problem_at(
+                    "log_file.path",
+                    "path",
+                    format!("'{}' does not name a file", log_file.path).as_str(),
+                ));
+            }
+            match (log_file.rotation, log_file.max_bytes) {
+                (LogRotation::Size, None | Some(0)) => problems.push(self.problem_at(
+                    "log_file.max_bytes",
+                    "rotation",
+                    "must be greater than 0 when rotating by size",
+                )),
+                (LogRotation::Size, Some(_)) | (_, None) => {}
+                (_, Some(_)) => problems.push(self.problem_at(
+                    "log_file.max_bytes",
+                    "max_bytes",
+                    "only applies when rotating by size",
+                )),
+            }
+            if log_file.retention == Some(0) {
+                problems.push(self.problem_at(
+                    "log_file.retention",
+                    "retention",
+                    "must keep at least 1 file",
+                ));
+            }
+        }
+        if let Some(capture) = &self.capture_parse_failures {
+            if let Some(directory) = &capture.directory {
+                if !fs::metadata(directory).is_ok_and(|meta| meta.is_dir()) {
+                    problems.push(self.problem_at(
+                        "capture_parse_failures.directory",
+                        "directory",
+                        format!("{} is not a directory", directory).as_str(),
+                    ));
+                }
+            }
+            if capture.max_bytes == 0 {
+                problems.push(self.problem_at(
+                    "capture_parse_failures.max_bytes",
+                    "max_bytes",
+                    "must be greater than 0",
+                ));
+            }
+        }
+
+        match (&self.tls_cert_file, &self.tls_key_file) {
+            (Some(_), None) => {
+                problems.push("tls_key_file: must be set along with tls_cert_file".to_string())
+            }
+            (None, Some(_)) => {
+                problems.push("tls_cert_file: must be set along with tls_key_file".to_string())
+            }
+            _ => {}
+        }
+
+        if !self.router_https {
+            for (name, set) in [
+                (
+                    "router_insecure_skip_verify",
+                    self.router_insecure_skip_verify,
+                ),
+                ("router_ca_file", self.router_ca_file.is_some()),
+            ] {
+                if set {
+                    problems.push(self.problem(name, "only applies when router_https is true"));
+                }
+            }
+        }
+        if let Some(proxy) = &self.router_proxy {
+            let scheme_supported = url::Url::parse(proxy)
+                .is_ok_and(|url| ["http", "https", "socks5", "socks5h"].contains(&url.scheme()));
+            if !scheme_supported {
+                problems.push(
+                    self.problem(
+                        "router_proxy",
+                        format!(
+                            "'{}' is not an http://, https://, socks5:// or socks5h:// URL",
+                            proxy
+                        )
+                        .as_str(),
+                    ),
+                );
+            }
+            if self.transport == Transport::Telnet {
+                problems.push(
+                    self.problem("router_proxy", "is not supported with the telnet transport"),
+                );
+            }
+        }
+        if let Some(ca_file) = &self.router_ca_file {
+            match fs::read(ca_file) {
+                Ok(pem) => {
+                    if !reqwest::Certificate::from_pem_bundle(&pem)
+                        .is_ok_and(|certs| !certs.is_empty())
+                    {
+                        problems.push(
+                            self.problem(
+                                "router_ca_file",
+                                format!("{} does not contain a PEM encoded certificate", ca_file)
+                                    .as_str(),
+                            ),
+                        );
+                    }
+                }
+                Err(err) => problems.push(self.problem(
+                    "router_ca_file",
+                    format!("unable to read {}: {}", ca_file, err).as_str(),
+                )),
+            }
+        }
+
+        for (username, hash) in &self.basic_auth_users {
+            if hash.parse::<bcrypt::HashParts>().is_err() {
+                problems.push(self.problem_at(
+                    format!("basic_auth_users.{}", username).as_str(),
+                    username,
+                    "password must be a bcrypt hash",
+                ));
+            }
+        }
+
+        if let Some(namespace) = &self.namespace {
+            if !Regex::new("^[a-zA-Z_][a-zA-Z0-9_]*$")
+                .unwrap()
+                .is_match(namespace)
+            {
+                problems.push(self.problem(
+                    "namespace",
+                    format!("'{}' is not a valid metric name prefix", namespace).as_str(),
+                ));
+            }
+        }
+
+        for (name, pattern) in [
+            ("ignored_devices", &self.network.ignored_devices),
+            ("accept_devices", &self.network.accept_devices),
+        ] {
+            if let Some(Err(err)) = pattern.as_ref().map(|pattern| Regex::new(pattern)) {
+                problems.push(self.problem_at(
+                    format!("network.{}", name).as_str(),
+                    name,
+                    format!("invalid regex: {}", err).as_str(),
+                ));
+            }
+        }
+
+        let label_re = Regex::new("^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
+        for (i, rule) in self.relabel.iter().enumerate() {
+            if let Err(err) = Regex::new(rule.metric.as_str()) {
+                problems.push(self.problem_at(
+                    format!("relabel[{}].match", i).as_str(),
+                    "relabel",
+                    format!("invalid regex: {}", err).as_str(),
+                ));
+            }
+            if let RelabelAction::AddLabel { label, .. } | RelabelAction::RemoveLabel { label } =
+                &rule.action
+            {
+                if !label_re.is_match(label) {
+                    problems.push(self.problem_at(
+                        format!("relabel[{}].label", i).as_str(),
+                        "relabel",
+                        format!("'{}' is not a valid label name", label).as_str(),
+                    ));
+                }
+            }
+        }
+        for name in self.const_labels.keys() {
+            if !label_re.is_match(name) {
+                problems.push(self.problem_at(
+                    format!("const_labels.{}", name).as_str(),
+                    "const_labels",
+                    format!("'{}' is not a valid label name", name).as_str(),
+                ));
+            }
+        }
+        if let Some(push) = &self.pushgateway {
+            if !url::Url::parse(push.url.as_str())
+                .is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https")
+            {
+                problems.push(self.problem_at(
+                    "pushgateway.url",
+                    "url",
+                    format!("'{}' is not an http or https URL", push.url).as_str(),
+                ));
+            }
+            if let Some(problem) = seconds_problem(push.interval) {
+                problems.push(self.problem_at(
+                    "pushgateway.interval",
+                    "interval",
+                    problem.as_str(),
+                ));
+            }
+            if push.job.trim().is_empty() {
+                problems.push(self.problem_at("pushgateway.job", "job", "must not be empty"));
+            }
+            for name in push.grouping.keys() {
+                if !label_re.is_match(name) || name == "job" {
+                    problems.push(self.problem_at(
+                        format!("pushgateway.grouping.{}", name).as_str(),
+                        "grouping",
+                        format!("'{}' is not a valid label name", name).as_str(),
+                    ));
+                }
+            }
+        }
+        if let Some(remote) = &self.remote_write {
+            if cfg!(not(feature = "remote_write")) {
+                problems.push(self.problem(
+                    "remote_write",
+                    "is not supported by this build, which needs the remote_write feature",
+                ));
+            }
+            if !url::Url::parse(remote.url.as_str())
+                .is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https")
+            {
+                problems.push(self.problem_at(
+                    "remote_write.url",
+                    "url",
+                    format!("'{}' is not an http or https URL", remote.url).as_str(),
+                ));
+            }
+            if let Some(problem) = seconds_problem(remote.interval) {
+                problems.push(self.problem_at(
+                    "remote_write.interval",
+                    "interval",
+                    problem.as_str(),
+                ));
+            }
+            if remote.username.is_some() != remote.password.is_some() {
+                problems.push(
+                    self.problem("remote_write", "username and password must be set together"),
+                );
+            }
+        }
+        if let Some(graphite) = &self.graphite {
+            let has_port = graphite
+                .address
+                .rsplit_once(':')
+                .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+            if !has_port {
+                problems.push(
+                    self.problem_at(
+                        "graphite.address",
+                        "address",
+                        format!(
+                            "'{}' is not a host and port, such as carbon.lan:2003",
+                            graphite.address
+                        )
+                        .as_str(),
+                    ),
+                );
+            }
+            if graphite.prefix.contains(char::is_whitespace) {
+                problems.push(self.problem_at(
+                    "graphite.prefix",
+                    "prefix",
+                    "must not contain whitespace",
+                ));
+            }
+            if let Some(problem) = seconds_problem(graphite.interval) {
+                problems.push(self.problem_at("graphite.interval", "interval", problem.as_str()));
+            }
+        }
+        if let Some(textfile) = &self.textfile {
+            // node_exporter only reads the files with this extension
+            if !textfile.path.ends_with(".prom") {
+                problems.push(self.problem_at(
+                    "textfile.path",
+                    "path",
+                    "must end in .prom for node_exporter to read it",
+                ));
+            }
+            if let Some(problem) = seconds_problem(textfile.interval) {
+                problems.push(self.problem_at("textfile.interval", "interval", problem.as_str()));
+            }
+        }
+        if let Some(otlp) = &self.otlp {
+            if !url::Url::parse(otlp.url.as_str())
+                .is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https")
+            {
+                problems.push(self.problem_at(
+                    "otlp.url",
+                    "url",
+                    format!("'{}' is not an http or https URL", otlp.url).as_str(),
+                ));
+            }
+            if let Some(problem) = seconds_problem(otlp.interval) {
+                problems.push(self.problem_at("otlp.interval", "interval", problem.as_str()));
+            }
+            for (name, value) in &otlp.headers {
+                if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+                    || reqwest::header::HeaderValue::from_str(value).is_err()
+                {
+                    problems.push(self.problem_at(
+                        format!("otlp.headers.{}", name).as_str(),
+                        "headers",
+                        "is not a valid HTTP header",
+                    ));
+                }
+            }
+        }
+
+        if cfg!(not(unix)) && self.listen_socket.is_some() {
+            problems.push(self.problem(
+                "listen_socket",
+                "unix sockets are not supported on this platform",
+            ));
+        }
+
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        Ok(Config {
+            listen,
+            listen_socket: self.listen_socket,
+            tls_cert_file: self.tls_cert_file,
+            tls_key_file: self.tls_key_file,
+            basic_auth_users: self.basic_auth_users,
+            slug,
+            router_ip,
+            router_https: self.router_https,
+            router_insecure_skip_verify: self.router_insecure_skip_verify,
+            router_ca_file: self.router_ca_file,
+            router_auth: self.router_auth.unwrap_or(match self.firmware {
+                Firmware::Merlin => RouterAuth::AsusToken,
+                _ => RouterAuth::Basic,
+            }),
+            router_timeout: self.router_timeout.unwrap_or(DEFAULT_ROUTER_TIMEOUT),
+            router_proxy: self.router_proxy,
+            router_charset: self.router_charset,
+            firmware: self.firmware,
+            transport: self.transport,
+            telnet_port,
+            circuit_breaker: self.circuit_breaker,
+            admin_username,
+            admin_password,
+            http_id,
+            wifi_survey: self.wifi_survey,
+            firewall_chains: self.firewall_chains,
+            upnp_mapping_info: self.upnp_mapping_info,
+            collectors: self.collectors,
+            collector_timeout: self.collector_timeout.unwrap_or(DEFAULT_COLLECTOR_TIMEOUT),
+            scrape_timeout: self.scrape_timeout,
+            cache_ttl: self.cache_ttl,
+            failed_scrape_status,
+            shutdown_timeout: self.shutdown_timeout.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT),
+            collector_timeouts: self.collector_timeouts,
+            non_finite_values: self.non_finite_values,
+            log_level: self.log_level,
+            log_file: self.log_file,
+            namespace: self
+                .namespace
+                .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string()),
+            network: self.network,
+            relabel: self.relabel,
+            const_labels: self.const_labels,
+            drop_zero_counters: self.drop_zero_counters,
+            debug_endpoints: self.debug_endpoints,
+            capture_parse_failures: self.capture_parse_failures,
+            pushgateway: self.pushgateway,
+            remote_write: self.remote_write,
+            graphite: self.graphite,
+            textfile: self.textfile,
+            otlp: self.otlp,
+        })
+    }
+
+    // the password can be kept out of the config file by reading it from a mounted secret or the
+    // environment instead
+    fn resolve_admin_password(&self) -> Result<Option<String>, String> {
+        let sources = [
+            self.admin_password.is_some(),
+            self.admin_password_file.is_some(),
+            self.admin_password_env.is_some(),
+        ];
+        if sources.iter().filter(|set| **set).count() > 1 {
+            return Err(
+                "admin_password: only one of admin_password, admin_password_file and \
+                admin_password_env may be set"
+                    .to_string(),
+            );
+        }
+        if let Some(path) = &self.admin_password_file {
+            return fs::read_to_string(path)
+                .map(|password| Some(password.trim_end_matches(['\r', '\n']).to_string()))
+                .map_err(|err| {
+                    self.problem(
+                        "admin_password_file",
+                        format!("unable to read {}: {}", path, err).as_str(),
+                    )
+                });
+        }
+        if let Some(name) = &self.admin_password_env {
+            return env::var(name).map(Some).map_err(|_| {
+                self.problem(
+                    "admin_password_env",
+                    format!("environment variable {} is not set", name).as_str(),
+                )
+            });
+        }
+        Ok(self.admin_password.clone())
+    }
+
+    fn problem(&self, name: &str, message: &str) -> String {
+        self.problem_at(name, name, message)
+    }
+
+    // nested fields are reported by their full path but located by the key they were written with
+    fn problem_at(&self, field: &str, key: &str, message: &str) -> String {
+        let key_re =
+            Regex::new(format!(r#"^\s*"?{}"?\s*[:=]"#, regex::escape(key)).as_str()).unwrap();
+        match self.source.lines().position(|line| key_re.is_match(line)) {
+            Some(line) => format!("{}: {} (line {})", field, message, line + 1),
+            None => format!("{}: {}", field, message),
+        }
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, Deserialize)]
+// what's wrong with a number of seconds, if anything.  YAML's .nan and .inf parse as floats, and
+// would panic when made into a Duration
+fn seconds_problem(seconds: f64) -> Option<String> {
+    if !seconds.is_finite() {
+        Some(format!("{} is not a number of seconds", seconds))
+    } else if seconds <= 0f64 {
+        Some(format!("{} must be greater than 0", seconds))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Config {
-    pub ip: String,
-    pub port: u16,
+    pub listen: Vec<SocketAddr>,
+    pub listen_socket: Option<String>,
+    pub tls_cert_file: Option<String>,
+    pub tls_key_file: Option<String>,
+    pub basic_auth_users: BTreeMap<String, String>,
     pub slug: String,
     pub router_ip: String,
+    pub router_https: bool,
+    pub router_insecure_skip_verify: bool,
+    pub router_ca_file: Option<String>,
+    pub router_auth: RouterAuth,
+    pub router_timeout: f64,
+    pub router_proxy: Option<String>,
+    pub router_charset: Charset,
+    pub firmware: Firmware,
+    pub transport: Transport,
+    pub telnet_port: u16,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
     pub admin_username: String,
     pub admin_password: String,
     pub http_id: String,
+    pub wifi_survey: bool,
+    pub firewall_chains: Vec<String>,
+    pub upnp_mapping_info: bool,
+    pub collectors: BTreeMap<String, bool>,
+    pub collector_timeout: f64,
+    pub scrape_timeout: Option<f64>,
+    pub cache_ttl: Option<f64>,
+    pub failed_scrape_status: Option<u16>,
+    pub shutdown_timeout: u64,
+    pub collector_timeouts: BTreeMap<String, f64>,
+    pub non_finite_values: BTreeMap<String, NonFiniteValues>,
+    pub log_level: Option<String>,
+    pub log_file: Option<LogFileConfig>,
+    pub namespace: String,
+    pub network: NetworkConfig,
+    pub relabel: Vec<RelabelRule>,
+    pub const_labels: BTreeMap<String, String>,
+    pub drop_zero_counters: bool,
+    pub debug_endpoints: bool,
+    pub capture_parse_failures: Option<CaptureConfig>,
+    pub pushgateway: Option<PushgatewayConfig>,
+    pub remote_write: Option<RemoteWriteConfig>,
+    pub graphite: Option<GraphiteConfig>,
+    pub textfile: Option<TextfileConfig>,
+    pub otlp: Option<OtlpConfig>,
+}
+
+// the firmware the router runs, which decides the collectors enabled by default
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Firmware {
+    #[default]
+    Tomato,
+    // adds CPU temperature, WireGuard and adblock on top of everything Tomato reports
+    #[serde(rename = "freshtomato")]
+    FreshTomato,
+    // read from the web UI's status pages instead, so only load, memory and wireless clients are
+    // reported
+    #[serde(rename = "ddwrt")]
+    Ddwrt,
+    // AsusWRT-Merlin, read through the JSON its web UI polls, logging in with its token login by
+    // default
+    Merlin,
+    // logs in to ubus itself, so router_auth is ignored
+    #[serde(rename = "openwrt")]
+    OpenWrt,
+}
+
+// how the router's pages are decoded.  whatever the page is, bytes that aren't valid in it are
+// replaced rather than failing the scrape
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Charset {
+    #[default]
+    Utf8,
+    // ISO-8859-1, which some older firmware writes its pages in
+    Latin1,
+}
+
+// how shell commands are run on the router
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    // through the web UI's shell.cgi
+    #[default]
+    Http,
+    // over a telnet console, for builds where shell.cgi doesn't work
+    Telnet,
+}
+
+// what a collector does with samples whose value is NaN or infinite
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NonFiniteValues {
+    // reported as NaN, +Inf or -Inf
+    #[default]
+    Emit,
+    Drop,
+}
+
+// how the admin credentials are presented to the router
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RouterAuth {
+    #[default]
+    Basic,
+    // the credentials are posted to a login form, and the session cookie it sets is sent with every
+    // request until the router rejects it
+    Form {
+        #[serde(default = "default_login_path")]
+        path: String,
+        #[serde(default = "default_username_field")]
+        username_field: String,
+        #[serde(default = "default_password_field")]
+        password_field: String,
+    },
+    // AsusWRT's login.cgi, which takes the base64 encoded credentials and sets an asus_token cookie
+    AsusToken,
+}
+
+fn default_login_path() -> String {
+    "login.cgi".to_string()
+}
+
+fn default_username_field() -> String {
+    "username".to_string()
+}
+
+fn default_password_field() -> String {
+    "password".to_string()
+}
+
+// when a collector's parser fails, what the router sent it is written to a file in directory, or to
+// the log when no directory is set, cut off after max_bytes and with credentials removed
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CaptureConfig {
+    pub directory: Option<String>,
+    #[serde(default = "default_capture_max_bytes")]
+    pub max_bytes: usize,
+}
+
+fn default_capture_max_bytes() -> usize {
+    64 * 1024
+}
+
+// after failures consecutive failed requests, the router isn't queried again for cooldown seconds
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CircuitBreakerConfig {
+    pub failures: u32,
+    pub cooldown: f64,
+}
+
+// every interval seconds the metrics are scraped and pushed to the pushgateway at url, replacing
+// whatever was last pushed under the same job and grouping labels
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct PushgatewayConfig {
+    pub url: String,
+    #[serde(default = "default_push_interval")]
+    pub interval: f64,
+    #[serde(default = "default_push_job")]
+    pub job: String,
+    #[serde(default)]
+    pub grouping: BTreeMap<String, String>,
+}
+
+// every interval seconds the metrics are scraped and sent to the remote write endpoint at url, such
+// as Prometheus' /api/v1/write
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RemoteWriteConfig {
+    pub url: String,
+    #[serde(default = "default_push_interval")]
+    pub interval: f64,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+// every interval seconds the metrics are scraped and sent to the Carbon server at address over its
+// plaintext protocol, named starting with prefix
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct GraphiteConfig {
+    pub address: String,
+    #[serde(default = "default_graphite_prefix")]
+    pub prefix: String,
+    #[serde(default = "default_push_interval")]
+    pub interval: f64,
+}
+
+// logs are written to the file at path as well as to stdout, starting a new file every rotation
+// period, or once it would grow past max_bytes, and deleting all but the newest retention files, if
+// set
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct LogFileConfig {
+    pub path: String,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    pub max_bytes: Option<u64>,
+    pub retention: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Weekly,
+    // a single file that grows until something else such as logrotate deals with it
+    Never,
+    // whenever the file would grow past max_bytes
+    Size,
+}
+
+// every interval seconds the metrics are scraped and written to the file at path, for node_exporter's
+// textfile collector
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct TextfileConfig {
+    pub path: String,
+    #[serde(default = "default_push_interval")]
+    pub interval: f64,
+}
+
+// every interval seconds the metrics are scraped and sent over OTLP/HTTP to the OpenTelemetry
+// collector's metrics endpoint at url, along with any headers it needs such as for authentication
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct OtlpConfig {
+    pub url: String,
+    #[serde(default = "default_push_interval")]
+    pub interval: f64,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+fn default_graphite_prefix() -> String {
+    "tomato".to_string()
+}
+
+fn default_push_interval() -> f64 {
+    60f64
+}
+
+fn default_push_job() -> String {
+    "tomato_exporter".to_string()
+}
+
+// regexes matched against network device names by the per-device collectors
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct NetworkConfig {
+    pub ignored_devices: Option<String>,
+    pub accept_devices: Option<String>,
+}
+
+// applied in order to every metric whose name matches the regex, after the namespace has been applied
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RelabelRule {
+    #[serde(rename = "match")]
+    pub metric: String,
+    #[serde(flatten)]
+    pub action: RelabelAction,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RelabelAction {
+    Drop,
+    // the name may refer to groups captured by the match regex, such as $1
+    Rename { name: String },
+    AddLabel { label: String, value: String },
+    RemoveLabel { label: String },
+}
+
+impl Config {
+    // collectors not mentioned in the collectors section keep their default state
+    pub fn collector_enabled(&self, name: &str, default: bool) -> bool {
+        self.collectors.get(name).copied().unwrap_or(default)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn expected() -> Config {
+        Config {
+            listen: vec!["0.0.0.0:8080".parse().unwrap()],
+            listen_socket: None,
+            tls_cert_file: None,
+            tls_key_file: None,
+            basic_auth_users: BTreeMap::new(),
+            slug: "metrics".to_string(),
+            router_ip: "192.168.1.1".to_string(),
+            router_https: false,
+            router_insecure_skip_verify: false,
+            router_ca_file: None,
+            router_auth: RouterAuth::Basic,
+            router_timeout: DEFAULT_ROUTER_TIMEOUT,
+            router_proxy: None,
+            router_charset: Charset::Utf8,
+            firmware: Firmware::Tomato,
+            transport: Transport::Http,
+            telnet_port: DEFAULT_TELNET_PORT,
+            circuit_breaker: None,
+            admin_username: "admin".to_string(),
+            admin_password: "password123".to_string(),
+            http_id: "TIDcf40d0bd0eba4ba0".to_string(),
+            wifi_survey: false,
+            firewall_chains: vec!["wanin".to_string()],
+            upnp_mapping_info: false,
+            collectors: BTreeMap::new(),
+            collector_timeout: DEFAULT_COLLECTOR_TIMEOUT,
+            scrape_timeout: None,
+            cache_ttl: None,
+            failed_scrape_status: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            collector_timeouts: BTreeMap::new(),
+            non_finite_values: BTreeMap::new(),
+            log_level: None,
+            log_file: None,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            network: NetworkConfig::default(),
+            relabel: Vec::new(),
+            const_labels: BTreeMap::new(),
+            drop_zero_counters: false,
+            debug_endpoints: false,
+            capture_parse_failures: None,
+            pushgateway: None,
+            remote_write: None,
+            graphite: None,
+            textfile: None,
+            otlp: None,
+        }
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ConfigFormat::from_path("conf.yaml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("conf.yml"), ConfigFormat::Yaml);
+        assert_eq!(
+            ConfigFormat::from_path("/etc/conf.TOML"),
+            ConfigFormat::Toml
+        );
+        assert_eq!(ConfigFormat::from_path("conf.json"), ConfigFormat::Json);
+        assert_eq!(ConfigFormat::from_path("conf"), ConfigFormat::Yaml);
+    }
+
+    #[test]
+    fn test_parse_conf_yaml() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+slug: metrics
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+firewall_chains: [wanin]
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Ok(expected())
+        )
+    }
+
+    #[test]
+    fn test_parse_conf_toml() {
+        let conf = "ip = \"0.0.0.0\"
+port = 8080
+slug = \"metrics\"
+router_ip = \"192.168.1.1\"
+admin_username = \"admin\"
+admin_password = \"password123\"
+http_id = \"TIDcf40d0bd0eba4ba0\"
+firewall_chains = [\"wanin\"]
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Toml).and_then(RawConfig::validate),
+            Ok(expected())
+        )
+    }
+
+    #[test]
+    fn test_parse_conf_json() {
+        let conf = "{
+  \"ip\": \"0.0.0.0\",
+  \"port\": 8080,
+  \"slug\": \"metrics\",
+  \"router_ip\": \"192.168.1.1\",
+  \"admin_username\": \"admin\",
+  \"admin_password\": \"password123\",
+  \"http_id\": \"TIDcf40d0bd0eba4ba0\",
+  \"firewall_chains\": [\"wanin\"]
+}";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Json).and_then(RawConfig::validate),
+            Ok(expected())
+        )
+    }
+
+    #[test]
+    fn test_parse_conf_example() {
+        assert_eq!(
+            parse_conf(EXAMPLE_CONF.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Ok(Config {
+                firewall_chains: Vec::new(),
+                ..expected()
+            })
+        )
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let conf = "ip: \"0.0.0.300\"
+port: 80800
+slug: metrics
+router_ip: \"192.168.1.1\"
+admin_password: password123
+http_id: \"\"
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "admin_username: missing required field".to_string(),
+                "http_id: must not be empty (line 6)".to_string(),
+                "ip: '0.0.0.300' is not a valid IP address (line 1)".to_string(),
+                "port: 80800 is out of range (1-65535) (line 2)".to_string(),
+            ])
+        )
+    }
+
+    #[test]
+    fn test_parse_conf_syntax_error() {
+        let problems = parse_conf(
+            "ip = \"0.0.0.0\"\nport = \"8080\"".to_string(),
+            ConfigFormat::Toml,
+        )
+        .unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("line 2"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn test_validate_password_file() {
+        let path = env::temp_dir().join("tomato-exporter-test-password");
+        fs::write(&path, "s3cret\n").unwrap();
+        let conf = format!(
+            "ip: \"0.0.0.0\"
+port: 8080
+slug: metrics
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password_file: {}
+http_id: TIDcf40d0bd0eba4ba0
+firewall_chains: [wanin]
+",
+            path.display()
+        );
+        let result = parse_conf(conf, ConfigFormat::Yaml).and_then(RawConfig::validate);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            result,
+            Ok(Config {
+                admin_password: "s3cret".to_string(),
+                ..expected()
+            })
+        )
+    }
+
+    #[test]
+    fn test_validate_password_env() {
+        env::set_var("TOMATO_EXPORTER_TEST_PASSWORD", "s3cret");
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+slug: metrics
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password_env: TOMATO_EXPORTER_TEST_PASSWORD
+http_id: TIDcf40d0bd0eba4ba0
+firewall_chains: [wanin]
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Ok(Config {
+                admin_password: "s3cret".to_string(),
+                ..expected()
+            })
+        )
+    }
+
+    #[test]
+    fn test_validate_password_sources() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+admin_password_env: TOMATO_EXPORTER_TEST_UNSET
+http_id: TIDcf40d0bd0eba4ba0
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "admin_password: only one of admin_password, admin_password_file and admin_password_env may be set".to_string()
+            ])
+        )
+    }
+
+    #[test]
+    fn test_collector_enabled() {
+        let conf = Config {
+            collectors: btreemap! {
+                "cpu".to_string() => false,
+                "survey".to_string() => true,
+            },
+            ..expected()
+        };
+        assert!(!conf.collector_enabled("cpu", true));
+        assert!(conf.collector_enabled("survey", false));
+        assert!(conf.collector_enabled("load", true));
+        assert!(!conf.collector_enabled("firewall", false));
+    }
+
+    #[test]
+    fn test_validate_collector_timeouts() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+collector_timeout: 0
+collector_timeouts:
+  survey: -1
+scrape_timeout: 0
+cache_ttl: -5
+router_timeout: 0
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "router_timeout: 0 must be greater than 0 (line 12)".to_string(),
+                "collector_timeout: 0 must be greater than 0 (line 7)".to_string(),
+                "scrape_timeout: 0 must be greater than 0 (line 10)".to_string(),
+                "cache_ttl: -5 must be greater than 0 (line 11)".to_string(),
+                "collector_timeouts.survey: -1 must be greater than 0 (line 9)".to_string(),
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_non_finite_timeouts() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+router_timeout: .nan
+collector_timeouts:
+  survey: .inf
+pushgateway:
+  url: http://pushgateway.lan:9091
+  interval: .nan
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "router_timeout: NaN is not a number of seconds (line 7)".to_string(),
+                "collector_timeouts.survey: inf is not a number of seconds (line 9)".to_string(),
+                "pushgateway.interval: NaN is not a number of seconds (line 12)".to_string(),
+            ])
+        )
+    }
+
+    #[test]
+    fn test_parse_non_finite_values() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+non_finite_values:
+  survey: drop
+  cpu: emit
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.non_finite_values),
+            Ok(btreemap! {
+                "cpu".to_string() => NonFiniteValues::Emit,
+                "survey".to_string() => NonFiniteValues::Drop,
+            })
+        )
+    }
+
+    #[test]
+    fn test_validate_failed_scrape_status() {
+        let conf = |status: &str| {
+            format!(
+                "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+failed_scrape_status: {}
+",
+                status
+            )
+        };
+        assert_eq!(
+            parse_conf(conf("503"), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.failed_scrape_status),
+            Ok(Some(503))
+        );
+        assert_eq!(
+            parse_conf(conf("1000"), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "failed_scrape_status: 1000 is not an HTTP status code (200-599) (line 7)"
+                    .to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate_log_level() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+log_level: \"info,tomato_exporter::client=loud\"
+";
+        let problems = parse_conf(conf.to_string(), ConfigFormat::Yaml)
+            .and_then(RawConfig::validate)
+            .unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with(
+                "log_level: 'info,tomato_exporter::client=loud' is not a valid log filter"
+            ),
+            "{}",
+            problems[0]
+        );
+    }
+
+    #[test]
+    fn test_generate_conf() {
+        let conf = generate_conf(&[
+            ("cpu".to_string(), true),
+            ("firewall".to_string(), false),
+            ("survey".to_string(), false),
+        ]);
+        assert!(
+            conf.contains(
+                "# collectors:\n#   cpu: true\n#   firewall: false\n#   survey: false\n# "
+            ),
+            "{}",
+            conf
+        );
+        assert!(!conf.contains("dnsmasq: false"), "{}", conf);
+        assert_eq!(
+            parse_conf(conf, ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Ok(example_conf())
+        )
+    }
+
+    #[test]
+    fn test_validate_listen() {
+        let conf = "listen:
+  - \"127.0.0.1:8080\"
+  - \"[::]:9100\"
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.listen),
+            Ok(vec![
+                "127.0.0.1:8080".parse().unwrap(),
+                "[::]:9100".parse().unwrap()
+            ])
+        );
+
+        let conf = "ip: \"::1\"
+port: 8080
+listen:
+  - \"::1:9100\"
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "listen: '::1:9100' is not a valid address and port, such as 0.0.0.0:8080 or [::]:8080 (line 3)".to_string()
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_listen_socket() {
+        let conf = "listen_socket: /run/tomato_exporter.sock
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+";
+        let conf = parse_conf(conf.to_string(), ConfigFormat::Yaml)
+            .and_then(RawConfig::validate)
+            .unwrap();
+        assert_eq!(conf.listen, Vec::<SocketAddr>::new());
+        assert_eq!(
+            conf.listen_socket,
+            Some("/run/tomato_exporter.sock".to_string())
+        );
+    }
+
+    #[test]
+    fn test_load_conf_dir() {
+        let dir = env::temp_dir().join("tomato-exporter-test-conf.d");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("00-main.yaml"),
+            "ip: \"0.0.0.0\"
+port: 8080
+slug: metrics
+router_ip: \"192.168.1.1\"
+admin_username: admin
+http_id: TIDcf40d0bd0eba4ba0
+collectors:
+  cpu: false
+  dnsmasq: false
+",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("10-credentials.yaml"),
+            "admin_password: password123
+collectors:
+  dnsmasq: true
+",
+        )
+        .unwrap();
+        fs::write(dir.join("README"), "not: [config").unwrap();
+        let result = load_conf(dir.display().to_string(), None).and_then(RawConfig::validate);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            result,
+            Ok(Config {
+                firewall_chains: Vec::new(),
+                collectors: btreemap! {
+                    "cpu".to_string() => false,
+                    "dnsmasq".to_string() => true,
+                },
+                ..expected()
+            })
+        )
+    }
+
+    #[test]
+    fn test_validate_namespace() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+namespace: tomato-usb
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "namespace: 'tomato-usb' is not a valid metric name prefix (line 7)".to_string()
+            ])
+        );
+        assert_eq!(
+            parse_conf(conf.replace("-usb", ""), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.namespace),
+            Ok("tomato".to_string())
+        )
+    }
+
+    #[test]
+    fn test_validate_network_devices() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+network:
+  ignored_devices: \"^imq[0-9]+$\"
+  accept_devices: \"^(eth|vlan\"
+";
+        let problems = parse_conf(conf.to_string(), ConfigFormat::Yaml)
+            .and_then(RawConfig::validate)
+            .unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].starts_with("network.accept_devices: invalid regex: "),
+            "{}",
+            problems[0]
+        );
+        assert!(problems[0].ends_with("(line 9)"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn test_validate_relabel() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+relabel:
+  - match: \"^node_network_.*_fifo_total$\"
+    action: drop
+  - match: \"^wan_(.*)$\"
+    action: rename
+    name: router_wan_$1
+  - match: \".*\"
+    action: add_label
+    label: site
+    value: home
+  - match: \"^lan_client_\"
+    action: remove_label
+    label: ip
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.relabel),
+            Ok(vec![
+                RelabelRule {
+                    metric: "^node_network_.*_fifo_total$".to_string(),
+                    action: RelabelAction::Drop,
+                },
+                RelabelRule {
+                    metric: "^wan_(.*)$".to_string(),
+                    action: RelabelAction::Rename {
+                        name: "router_wan_$1".to_string()
+                    },
+                },
+                RelabelRule {
+                    metric: ".*".to_string(),
+                    action: RelabelAction::AddLabel {
+                        label: "site".to_string(),
+                        value: "home".to_string()
+                    },
+                },
+                RelabelRule {
+                    metric: "^lan_client_".to_string(),
+                    action: RelabelAction::RemoveLabel {
+                        label: "ip".to_string()
+                    },
+                },
+            ])
+        );
+
+        let conf = conf.replace("label: site", "label: my-site");
+        assert_eq!(
+            parse_conf(conf, ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "relabel[2].label: 'my-site' is not a valid label name (line 7)".to_string()
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_const_labels() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+const_labels:
+  router: karabor
+  site: home
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.const_labels),
+            Ok(btreemap! {
+                "router".to_string() => "karabor".to_string(),
+                "site".to_string() => "home".to_string(),
+            })
+        );
+
+        let conf = conf.replace("site:", "1site:");
+        assert_eq!(
+            parse_conf(conf, ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "const_labels.1site: '1site' is not a valid label name (line 7)".to_string()
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_pushgateway() {
+        let conf = "router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+pushgateway:
+  url: http://pushgateway.lan:9091
+  grouping:
+    instance: karabor
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| (conf.listen, conf.pushgateway)),
+            Ok((
+                vec![],
+                Some(PushgatewayConfig {
+                    url: "http://pushgateway.lan:9091".to_string(),
+                    interval: 60f64,
+                    job: "tomato_exporter".to_string(),
+                    grouping: btreemap! {
+                        "instance".to_string() => "karabor".to_string(),
+                    },
+                })
+            ))
+        );
+
+        let conf = conf
+            .replace("http://", "ftp://")
+            .replace("instance:", "job:");
+        assert_eq!(
+            parse_conf(conf, ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "pushgateway.url: 'ftp://pushgateway.lan:9091' is not an http or https URL (line 6)"
+                    .to_string(),
+                "pushgateway.grouping.job: 'job' is not a valid label name (line 7)".to_string(),
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_remote_write() {
+        let conf = "router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+remote_write:
+  url: https://mimir.lan/api/v1/push
+  interval: 30
+  username: tomato
+";
+        let mut problems =
+            vec!["remote_write: username and password must be set together (line 5)".to_string()];
+        if cfg!(not(feature = "remote_write")) {
+            problems.insert(
+                0,
+                "remote_write: is not supported by this build, which needs the remote_write feature (line 5)"
+                    .to_string(),
+            );
+        }
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(problems)
+        );
+    }
+
+    #[test]
+    fn test_validate_graphite() {
+        let conf = "router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+graphite:
+  address: carbon.lan:2003
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.graphite),
+            Ok(Some(GraphiteConfig {
+                address: "carbon.lan:2003".to_string(),
+                prefix: "tomato".to_string(),
+                interval: 60f64,
+            }))
+        );
+
+        let conf = conf.replace(":2003", "");
+        assert_eq!(
+            parse_conf(conf, ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "graphite.address: 'carbon.lan' is not a host and port, such as carbon.lan:2003 (line 6)"
+                    .to_string()
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_textfile() {
+        let conf = "router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+textfile:
+  path: /var/lib/node_exporter/textfile/router.txt
+  interval: 0
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "textfile.path: must end in .prom for node_exporter to read it (line 6)"
+                    .to_string(),
+                "textfile.interval: 0 must be greater than 0 (line 7)".to_string(),
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_log_file() {
+        let conf = |log_file: &str| {
+            format!(
+                "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+log_file:
+{}
+",
+                log_file
+            )
+        };
+        assert_eq!(
+            parse_conf(
+                conf("  path: /var/log/tomato_exporter/exporter.log\n  rotation: hourly"),
+                ConfigFormat::Yaml
+            )
+            .and_then(RawConfig::validate)
+            .map(|conf| conf.log_file),
+            Ok(Some(LogFileConfig {
+                path: "/var/log/tomato_exporter/exporter.log".to_string(),
+                rotation: LogRotation::Hourly,
+                max_bytes: None,
+                retention: None,
+            }))
+        );
+        assert_eq!(
+            parse_conf(
+                conf(
+                    "  path: exporter.log\n  rotation: size\n  max_bytes: 1048576\n  retention: 5"
+                ),
+                ConfigFormat::Yaml
+            )
+            .and_then(RawConfig::validate)
+            .map(|conf| conf.log_file),
+            Ok(Some(LogFileConfig {
+                path: "exporter.log".to_string(),
+                rotation: LogRotation::Size,
+                max_bytes: Some(1048576),
+                retention: Some(5),
+            }))
+        );
+        assert_eq!(
+            parse_conf(
+                conf("  path: exporter.log\n  rotation: size"),
+                ConfigFormat::Yaml
+            )
+            .and_then(RawConfig::validate),
+            Err(vec![
+                "log_file.max_bytes: must be greater than 0 when rotating by size (line 9)"
+                    .to_string()
+            ])
+        );
+        assert_eq!(
+            parse_conf(
+                conf("  path: exporter.log\n  max_bytes: 1048576"),
+                ConfigFormat::Yaml
+            )
+            .and_then(RawConfig::validate),
+            Err(vec![
+                "log_file.max_bytes: only applies when rotating by size (line 9)".to_string()
+            ])
+        );
+        assert_eq!(
+            parse_conf(
+                conf("  path: /var/log/tomato_exporter/\n  retention: 0"),
+                ConfigFormat::Yaml
+            )
+            .and_then(RawConfig::validate),
+            Err(vec![
+                "log_file.path: '/var/log/tomato_exporter/' does not name a file (line 8)"
+                    .to_string(),
+                "log_file.retention: must keep at least 1 file (line 9)".to_string(),
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_capture_parse_failures() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+capture_parse_failures:
+  directory: /nonexistent/captures
+  max_bytes: 0
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "capture_parse_failures.directory: /nonexistent/captures is not a directory \
+                (line 8)"
+                    .to_string(),
+                "capture_parse_failures.max_bytes: must be greater than 0 (line 9)".to_string(),
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_otlp() {
+        let conf = "router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+otlp:
+  url: http://otel-collector.lan:4318/v1/metrics
+  headers:
+    Authorization: Bearer s3cret
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.otlp),
+            Ok(Some(OtlpConfig {
+                url: "http://otel-collector.lan:4318/v1/metrics".to_string(),
+                interval: 60f64,
+                headers: btreemap! {
+                    "Authorization".to_string() => "Bearer s3cret".to_string(),
+                },
+            }))
+        );
+
+        let conf = conf.replace("Authorization:", "Bad Header:");
+        assert_eq!(
+            parse_conf(conf, ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "otlp.headers.Bad Header: is not a valid HTTP header (line 7)".to_string()
+            ])
+        )
+    }
+
+    #[test]
+    fn test_validate_tls() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+tls_cert_file: /etc/tomato_exporter/cert.pem
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "tls_key_file: must be set along with tls_cert_file".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_router_auth() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+router_auth:
+  type: form
+  username_field: login_username
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.router_auth),
+            Ok(RouterAuth::Form {
+                path: "login.cgi".to_string(),
+                username_field: "login_username".to_string(),
+                password_field: "password".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_ddwrt_without_http_id() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+firmware: ddwrt
+admin_username: admin
+admin_password: password123
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| (conf.firmware, conf.http_id)),
+            Ok((Firmware::Ddwrt, String::new()))
+        );
+    }
+
+    #[test]
+    fn test_validate_merlin_defaults_to_token_login() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+firmware: merlin
+admin_username: admin
+admin_password: password123
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.router_auth),
+            Ok(RouterAuth::AsusToken)
+        );
+    }
+
+    #[test]
+    fn test_validate_router_charset() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+router_charset: latin1
+admin_username: admin
+admin_password: password123
+http_id: abcdefg
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.router_charset),
+            Ok(Charset::Latin1)
+        );
+    }
+
+    #[test]
+    fn test_validate_router_proxy() {
+        let conf = |proxy: &str, transport: &str| {
+            format!(
+                "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+router_proxy: {}
+transport: {}
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+",
+                proxy, transport
+            )
+        };
+        assert_eq!(
+            parse_conf(conf("socks5h://127.0.0.1:1080", "http"), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate)
+                .map(|conf| conf.router_proxy),
+            Ok(Some("socks5h://127.0.0.1:1080".to_string()))
+        );
+        assert_eq!(
+            parse_conf(
+                conf("socks5h://127.0.0.1:1080", "telnet"),
+                ConfigFormat::Yaml
+            )
+            .and_then(RawConfig::validate),
+            Err(vec![
+                "router_proxy: is not supported with the telnet transport (line 4)".to_string()
+            ])
+        );
+        assert_eq!(
+            parse_conf(conf("ftp://127.0.0.1", "http"), ConfigFormat::Yaml)
+                .and_then(RawConfig::validate),
+            Err(vec![
+                "router_proxy: 'ftp://127.0.0.1' is not an http://, https://, socks5:// or \
+                socks5h:// URL (line 4)"
+                    .to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate_router_https() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+router_insecure_skip_verify: true
+router_ca_file: /nonexistent/router.pem
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "router_insecure_skip_verify: only applies when router_https is true (line 4)"
+                    .to_string(),
+                "router_ca_file: only applies when router_https is true (line 5)".to_string(),
+                "router_ca_file: unable to read /nonexistent/router.pem: No such file or directory \
+                (os error 2) (line 5)"
+                    .to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate_basic_auth_users() {
+        let conf = "ip: \"0.0.0.0\"
+port: 8080
+router_ip: \"192.168.1.1\"
+admin_username: admin
+admin_password: password123
+http_id: TIDcf40d0bd0eba4ba0
+basic_auth_users:
+  prometheus: $2b$10$hyLEpuD9tyENsSGp27V2Ku3dvVKs5cTyG1mj2Fd1HsLg6FUkSjX7i
+  grafana: hunter2
+";
+        assert_eq!(
+            parse_conf(conf.to_string(), ConfigFormat::Yaml).and_then(RawConfig::validate),
+            Err(vec![
+                "basic_auth_users.grafana: password must be a bcrypt hash (line 9)".to_string()
+            ])
+        );
+    }
 }
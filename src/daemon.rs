@@ -0,0 +1,45 @@
+use std::fs::{self, OpenOptions};
+
+use daemonize::Daemonize;
+
+// detaches from the terminal for init scripts that expect the process they start to fork into the
+// background, as on OpenWrt and Entware.  only the detached process returns, with the pid file
+// locked for as long as it runs so that a second copy can't be started over it.  the working
+// directory is kept so that relative paths in the config still resolve.  the pid file is removed
+// when the returned guard is dropped
+pub fn daemonize(pid_file: Option<&String>) -> Result<Option<PidFile>, String> {
+    let daemon =
+        Daemonize::new().working_directory(std::env::current_dir().map_err(|err| err.to_string())?);
+    let daemon = match pid_file {
+        Some(pid_file) => {
+            check_pid_file(pid_file)?;
+            daemon.pid_file(pid_file)
+        }
+        None => daemon,
+    };
+    daemon.start().map_err(|err| err.to_string())?;
+    Ok(pid_file.map(|pid_file| PidFile(pid_file.clone())))
+}
+
+// held for as long as the exporter runs, so that the pid file is removed however main returns.  the
+// pid file is only left behind when the process is killed outright
+pub struct PidFile(String);
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(self.0.as_str());
+    }
+}
+
+// the pid file is only locked once the process has detached, by when the init script has already
+// been told that it started, so an exporter that is already running is looked for beforehand
+fn check_pid_file(pid_file: &str) -> Result<(), String> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(pid_file)
+        .map_err(|err| format!("unable to open {}: {}", pid_file, err))?;
+    file.try_lock()
+        .map_err(|_| format!("{} is locked, is the exporter already running?", pid_file))
+}
@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+// anything that can go wrong while scraping the router, so that failures can be told apart by kind
+// rather than by their message
+#[derive(Debug, thiserror::Error)]
+pub enum ExporterError {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("telnet: {0}")]
+    Telnet(#[from] std::io::Error),
+    // a call the router answered with an error of its own, such as a ubus status code
+    #[error("rpc: {0}")]
+    Rpc(String),
+    // the router turned down the credentials it was given
+    #[error("auth: {0}")]
+    Auth(String),
+    // a response that didn't look the way the collector expected, such as from firmware that formats
+    // a file differently
+    #[error("parse: {0}")]
+    Parse(String),
+    // an HTML page where data was expected, such as the redirect the router sends once a session is
+    // no longer valid
+    #[error("unexpected page: {0}")]
+    UnexpectedPage(String),
+    #[error("timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("skipped while the circuit breaker is open")]
+    CircuitOpen,
+    // settings that passed validation but couldn't be put to use, such as a CA file that has gone
+    // missing since
+    #[error("config: {0}")]
+    Config(String),
+}
+
+impl ExporterError {
+    // the kind of error, as reported in the kind label of node_scrape_collector_error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ExporterError::Http(err) if err.is_timeout() => "timeout",
+            ExporterError::Http(err)
+                if err.status() == Some(StatusCode::UNAUTHORIZED)
+                    || err.status() == Some(StatusCode::FORBIDDEN) =>
+            {
+                "auth"
+            }
+            ExporterError::Telnet(err) if err.kind() == std::io::ErrorKind::TimedOut => "timeout",
+            ExporterError::Http(_) | ExporterError::Telnet(_) | ExporterError::Rpc(_) => {
+                "transport"
+            }
+            ExporterError::Auth(_) => "auth",
+            ExporterError::Parse(_) => "parse",
+            ExporterError::UnexpectedPage(_) => "unexpected_page",
+            ExporterError::Timeout(_) => "timeout",
+            ExporterError::CircuitOpen => "circuit_open",
+            ExporterError::Config(_) => "config",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(
+            ExporterError::Parse("unable to parse load".to_string()).kind(),
+            "parse"
+        );
+        assert_eq!(
+            ExporterError::Telnet(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "no prompt"
+            ))
+            .kind(),
+            "timeout"
+        );
+        assert_eq!(
+            ExporterError::Telnet(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "connection refused"
+            ))
+            .kind(),
+            "transport"
+        );
+        assert_eq!(
+            ExporterError::Timeout(Duration::from_secs(5)).to_string(),
+            "timed out after 5s"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tracing::{debug, warn};
+
+use crate::client::TomatoClient;
+use crate::config::GraphiteConfig;
+
+// scrapes every collector on an interval and sends the result to a Carbon server over its plaintext
+// protocol, for setups still built around Graphite
+pub struct Graphite {
+    client: TomatoClient,
+    address: String,
+    prefix: String,
+    interval: Duration,
+}
+
+impl Graphite {
+    pub fn new(client: TomatoClient, conf: &GraphiteConfig) -> Graphite {
+        Graphite {
+            client,
+            address: conf.address.clone(),
+            prefix: conf.prefix.clone(),
+            interval: Duration::from_secs_f64(conf.interval),
+        }
+    }
+
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match self.send().await {
+                Ok(()) => debug!("Sent metrics to {}", self.address),
+                Err(err) => warn!("Sending metrics to {} failed: {}", self.address, err),
+            }
+        }
+    }
+
+    // a connection is opened for every send, since Carbon is happy to take one batch per connection
+    // and a long-lived one would need watching for the server going away in between
+    async fn send(&self) -> Result<(), String> {
+        let scrape = self
+            .client
+            .get_metrics(&[])
+            .await
+            .map_err(|err| err.to_string())?;
+        let lines = scrape.response.to_graphite(
+            self.prefix.as_str(),
+            OffsetDateTime::now_utc().unix_timestamp() as u64,
+        );
+        // a send that takes longer than the interval would only pile up behind the next one
+        tokio::time::timeout(self.interval, async {
+            let mut stream = TcpStream::connect(self.address.as_str()).await?;
+            stream.write_all(lines.as_bytes()).await?;
+            stream.shutdown().await
+        })
+        .await
+        .unwrap_or_else(|_| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("timed out after {:?}", self.interval),
+            ))
+        })
+        .map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,70 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use reqwest::ClientBuilder;
+
+use crate::web::HEALTHZ_PATH;
+
+// asks a running exporter whether it is up, for container runtimes whose images have no curl to
+// probe it with
+pub async fn check(url: &str, timeout: Duration) -> Result<(), String> {
+    // the exporter's own certificate is often self signed, and it's only being asked whether it's up
+    let client = ClientBuilder::new()
+        .timeout(timeout)
+        .danger_accept_invalid_certs(true)
+        .build()
+        .map_err(|err| err.to_string())?;
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+// the health check on the first address the exporter listens on.  an exporter listening on every
+// interface is reached over loopback
+pub fn local_url(listen: &[SocketAddr], tls: bool) -> Option<String> {
+    let mut address = *listen.first()?;
+    if address.ip().is_unspecified() {
+        address.set_ip(match address.ip() {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        });
+    }
+    Some(format!(
+        "{}://{}{}",
+        if tls { "https" } else { "http" },
+        address,
+        HEALTHZ_PATH
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_local_url() {
+        assert_eq!(
+            local_url(&["0.0.0.0:9100".parse().unwrap()], false),
+            Some("http://127.0.0.1:9100/healthz".to_string())
+        );
+        assert_eq!(
+            local_url(
+                &[
+                    "[::]:9100".parse().unwrap(),
+                    "192.168.1.2:9100".parse().unwrap()
+                ],
+                true
+            ),
+            Some("https://[::1]:9100/healthz".to_string())
+        );
+        assert_eq!(
+            local_url(&["192.168.1.2:8080".parse().unwrap()], false),
+            Some("http://192.168.1.2:8080/healthz".to_string())
+        );
+        assert_eq!(local_url(&[], false), None);
+    }
+}
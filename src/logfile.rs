@@ -0,0 +1,168 @@
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+use crate::config::{LogFileConfig, LogRotation};
+
+// the file the logs are written to, started afresh every period by tracing-appender or once it has
+// grown past max_bytes
+pub enum LogFile {
+    Rolling(RollingFileAppender),
+    Sized(SizedFile),
+}
+
+impl LogFile {
+    pub fn open(conf: &LogFileConfig) -> Result<LogFile, String> {
+        let path = Path::new(conf.path.as_str());
+        let rotation = match conf.rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Weekly => Rotation::WEEKLY,
+            LogRotation::Never => Rotation::NEVER,
+            // max_bytes has already been checked to be set when the config was validated
+            LogRotation::Size => {
+                return SizedFile::open(path, conf.max_bytes.unwrap_or(u64::MAX), conf.retention)
+                    .map(LogFile::Sized)
+                    .map_err(|err| err.to_string())
+            }
+        };
+        let mut builder = RollingFileAppender::builder()
+            .rotation(rotation)
+            // the path has already been checked to name a file when the config was validated
+            .filename_prefix(path.file_name().unwrap().to_string_lossy());
+        if let Some(retention) = conf.retention {
+            builder = builder.max_log_files(retention);
+        }
+        builder
+            .build(path.parent().unwrap_or(Path::new(".")))
+            .map(LogFile::Rolling)
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            LogFile::Rolling(appender) => appender.write(buf),
+            LogFile::Sized(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            LogFile::Rolling(appender) => appender.flush(),
+            LogFile::Sized(file) => file.flush(),
+        }
+    }
+}
+
+// logrotate's naming, where the file at path is always the current one and the older ones are moved
+// to path.1, path.2 and so on, oldest last
+pub struct SizedFile {
+    path: PathBuf,
+    max_bytes: u64,
+    retention: Option<usize>,
+    file: File,
+    written: u64,
+}
+
+impl SizedFile {
+    fn open(path: &Path, max_bytes: u64, retention: Option<usize>) -> io::Result<SizedFile> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let file = SizedFile::append(path)?;
+        Ok(SizedFile {
+            path: path.to_path_buf(),
+            max_bytes,
+            retention,
+            written: file.metadata()?.len(),
+            file,
+        })
+    }
+
+    fn append(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    // retention counts the current file, so only retention - 1 older ones are kept
+    fn rotate(&mut self) -> io::Result<()> {
+        let mut count = 0;
+        while self.rotated(count + 1).exists() {
+            count += 1;
+        }
+        let keep = self.retention.map_or(usize::MAX, |retention| retention - 1);
+        for index in (keep.max(1)..=count).rev() {
+            fs::remove_file(self.rotated(index))?;
+        }
+        for index in (1..=count.min(keep.saturating_sub(1))).rev() {
+            fs::rename(self.rotated(index), self.rotated(index + 1))?;
+        }
+        if keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = SizedFile::append(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizedFile {
+    // a line is never split across files, so one longer than max_bytes is written whole to a file
+    // of its own
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written.saturating_add(buf.len() as u64) > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sized_file() {
+        let directory =
+            std::env::temp_dir().join(format!("tomato_exporter_{}", std::process::id()));
+        let path = directory.join("exporter.log");
+        let mut file = SizedFile::open(&path, 10, Some(3)).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        let read = |name: &str| fs::read_to_string(directory.join(name)).ok();
+        assert_eq!(read("exporter.log"), Some("fourth\n".to_string()));
+        assert_eq!(read("exporter.log.1"), Some("third\n".to_string()));
+        assert_eq!(read("exporter.log.2"), Some("second\n".to_string()));
+        assert_eq!(read("exporter.log.3"), None);
+
+        // picking up where a previous run left off
+        let mut file = SizedFile::open(&path, 10, Some(1)).unwrap();
+        file.write_all(b"fifth\n").unwrap();
+        assert_eq!(read("exporter.log"), Some("fifth\n".to_string()));
+        assert_eq!(read("exporter.log.1"), None);
+        assert_eq!(read("exporter.log.2"), None);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -3,14 +3,22 @@
 extern crate actix_web;
 #[macro_use]
 extern crate async_trait;
+extern crate base64;
+extern crate bcrypt;
 extern crate clap;
 extern crate dyn_clone;
 extern crate futures;
+extern crate listenfd;
 #[macro_use]
 extern crate maplit;
 extern crate regex;
 extern crate reqwest;
+extern crate rustls;
+extern crate rustls_pki_types;
 extern crate serde_json;
+extern crate serde_yaml;
+extern crate tokio;
+extern crate toml;
 extern crate tracing;
 extern crate tracing_actix_web;
 extern crate tracing_log;
@@ -19,34 +27,64 @@ extern crate url;
 
 mod client;
 mod config;
+#[cfg(unix)]
+mod daemon;
+mod error;
+mod graphite;
+mod healthcheck;
+mod logfile;
+mod otlp;
 mod prometheus;
+mod pushgateway;
+#[cfg(feature = "remote_write")]
+mod remote_write;
+#[cfg(unix)]
+mod systemd;
+mod textfile;
 mod web;
 
-use actix_web::middleware::{Compress, Logger};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::http::StatusCode;
+use actix_web::middleware::{from_fn, Compress, Logger};
 use actix_web::web::Data;
 use actix_web::{web as a_web, App, HttpServer};
 use clap::{crate_name, crate_version};
-use tracing::{info, Level};
+use listenfd::ListenFd;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+#[cfg(unix)]
+use tracing::warn;
+use tracing::{error, info};
 use tracing_actix_web::TracingLogger;
 use tracing_log::LogTracer;
-use tracing_subscriber::FmtSubscriber;
+use tracing_subscriber::fmt::format::{DefaultFields, Format};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
 
-use web::{metrics, WebState};
+use web::{
+    authenticate, collector_metrics, healthz, index, metrics, raw_responses, WebState, HEALTHZ_PATH,
+};
 
 use client::TomatoClient;
+use graphite::Graphite;
+use logfile::LogFile;
+use otlp::Otlp;
+use pushgateway::Pushgateway;
+#[cfg(feature = "remote_write")]
+use remote_write::RemoteWrite;
+#[cfg(unix)]
+use systemd::Systemd;
+use textfile::Textfile;
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
+// the runtime is only started once the process has been daemonized, as forking leaves behind every
+// thread but the one that forked
+fn main() -> std::io::Result<()> {
     let ansi_enabled = fix_ansi_term();
     LogTracer::init().expect("routing log to tracing failed");
 
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .with_ansi(ansi_enabled)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
-
-    let matches = clap::Command::new("tomato_exporter")
+    let command = clap::Command::new("tomato_exporter")
         .version(crate_version!())
         .author("Chris Lieb")
         .arg(
@@ -55,37 +93,431 @@ async fn main() -> std::io::Result<()> {
                 .long("conf")
                 .default_value("conf.yaml"),
         )
-        .get_matches();
-
-    let conf = config::load_conf(matches.get_one::<String>("conf").unwrap().clone());
-    info!(
-        "Starting {} v{}: http://{}:{}/{}",
-        crate_name!(),
-        crate_version!(),
-        conf.ip,
-        conf.port,
-        conf.slug
-    );
+        .arg(
+            clap::Arg::new("format")
+                .long("format")
+                .value_parser(["yaml", "toml", "json"])
+                .help("Format of the config file, detected from its extension by default"),
+        )
+        .arg(
+            clap::Arg::new("ip")
+                .long("ip")
+                .help("The interface to host the service on"),
+        )
+        .arg(
+            clap::Arg::new("port")
+                .long("port")
+                .value_parser(clap::value_parser!(u16))
+                .help("The port to host the service on"),
+        )
+        .arg(
+            clap::Arg::new("slug")
+                .long("slug")
+                .help("The path the metrics are served on"),
+        )
+        .arg(
+            clap::Arg::new("router_ip")
+                .long("router-ip")
+                .help("The address of the router's admin interface"),
+        )
+        .arg(
+            clap::Arg::new("admin_username")
+                .long("admin-username")
+                .help("The username to log in to the router with"),
+        )
+        .arg(
+            clap::Arg::new("admin_password")
+                .long("admin-password")
+                .help("The password to log in to the router with"),
+        )
+        .arg(
+            clap::Arg::new("http_id")
+                .long("http-id")
+                .help("The router's http_id"),
+        )
+        .arg(
+            clap::Arg::new("log_level")
+                .long("log-level")
+                .help("Log level or filter directives such as info,tomato_exporter::client=debug"),
+        )
+        .subcommand(
+            clap::Command::new("generate-config")
+                .about("Print an annotated example config with every collector and its default"),
+        )
+        .subcommand(
+            clap::Command::new("healthcheck")
+                .about("Exit 0 if the exporter is up and 1 if it isn't, for container health checks")
+                .arg(clap::Arg::new("url").long("url").help(
+                    "The health check to ask, by default /healthz on the first address in the config",
+                ))
+                .arg(
+                    clap::Arg::new("timeout")
+                        .long("timeout")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("5")
+                        .help("Seconds to wait for an answer"),
+                ),
+        );
+    #[cfg(unix)]
+    let command = command
+        .arg(
+            clap::Arg::new("daemon")
+                .long("daemon")
+                .action(clap::ArgAction::SetTrue)
+                .help("Detach from the terminal and run in the background"),
+        )
+        .arg(
+            clap::Arg::new("pid_file")
+                .long("pid-file")
+                .requires("daemon")
+                .help("File to write the background process' PID to"),
+        );
+    let matches = command.get_matches();
 
-    let client = TomatoClient::new(
-        conf.router_ip,
-        conf.admin_username,
-        conf.admin_password,
-        conf.http_id,
-    );
+    if matches.subcommand_matches("generate-config").is_some() {
+        let defaults = TomatoClient::collector_defaults(&config::example_conf())
+            .map_err(std::io::Error::other)?;
+        print!("{}", config::generate_conf(&defaults));
+        return Ok(());
+    }
+
+    // the log file isn't known until the config has been read, so its layer starts out empty
+    let (file_layer, log_file_handle) = reload::Layer::new(None::<LogFileLayer>);
+    let (filter_layer, log_reload_handle) =
+        reload::Layer::new(log_filter(matches.get_one::<String>("log_level")));
+    tracing::subscriber::set_global_default(
+        Registry::default()
+            .with(file_layer)
+            .with(filter_layer)
+            .with(fmt::layer().with_ansi(ansi_enabled)),
+    )
+    .expect("setting default subscriber failed");
+
+    let conf_path = matches.get_one::<String>("conf").unwrap().clone();
+    let conf = config::load_conf(
+        conf_path.clone(),
+        matches
+            .get_one::<String>("format")
+            .and_then(|format| config::ConfigFormat::from_name(format)),
+    )
+    .and_then(|mut raw| {
+        override_conf(&mut raw, &matches);
+        raw.validate()
+    })
+    .unwrap_or_else(|problems| {
+        error!("Invalid configuration in {}:", conf_path);
+        for problem in problems {
+            error!("  - {}", problem);
+        }
+        std::process::exit(1);
+    });
+    if let Some(healthcheck) = matches.subcommand_matches("healthcheck") {
+        let url = healthcheck
+            .get_one::<String>("url")
+            .cloned()
+            .or_else(|| healthcheck::local_url(&conf.listen, conf.tls_cert_file.is_some()))
+            .unwrap_or_else(|| {
+                error!("No address to check in {}, pass --url instead", conf_path);
+                std::process::exit(1);
+            });
+        let timeout = Duration::try_from_secs_f64(*healthcheck.get_one::<f64>("timeout").unwrap())
+            .unwrap_or_else(|err| {
+                error!("Invalid timeout: {}", err);
+                std::process::exit(1);
+            });
+        match actix_web::rt::System::new().block_on(healthcheck::check(url.as_str(), timeout)) {
+            Ok(()) => std::process::exit(0),
+            Err(err) => {
+                error!("Health check failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    log_reload_handle
+        .reload(log_filter(conf.log_level.as_ref()))
+        .expect("reloading log filter failed");
+    if let Some(log_file) = &conf.log_file {
+        let appender = LogFile::open(log_file).unwrap_or_else(|err| {
+            error!("Unable to log to {}: {}", log_file.path, err);
+            std::process::exit(1);
+        });
+        log_file_handle
+            .reload(Some(
+                fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(appender)),
+            ))
+            .expect("reloading log file failed");
+    }
+    // nothing after this exits the process outright, so that the pid file guard is always dropped
+    #[cfg(unix)]
+    let _pid_file = if matches.get_flag("daemon") {
+        if conf.log_file.is_none() {
+            warn!("Running in the background without log_file, so nothing more will be logged");
+        }
+        daemon::daemonize(matches.get_one::<String>("pid_file")).unwrap_or_else(|err| {
+            error!("Unable to run in the background: {}", err);
+            std::process::exit(1);
+        })
+    } else {
+        None
+    };
+
+    actix_web::rt::System::new().block_on(serve(conf))
+}
+
+async fn serve(conf: config::Config) -> std::io::Result<()> {
+    info!("Starting {} v{}", crate_name!(), crate_version!());
+
+    let client = TomatoClient::new(&conf).map_err(|err| {
+        std::io::Error::other(format!("unable to create the router client: {}", err))
+    })?;
+    let otlp = conf
+        .otlp
+        .as_ref()
+        .map(|otlp| Otlp::new(client.clone(), otlp));
+    let textfile = conf
+        .textfile
+        .as_ref()
+        .map(|textfile| Textfile::new(client.clone(), textfile));
+    let graphite = conf
+        .graphite
+        .as_ref()
+        .map(|graphite| Graphite::new(client.clone(), graphite));
+    let pushgateway = conf
+        .pushgateway
+        .as_ref()
+        .map(|push| Pushgateway::new(client.clone(), push));
+    #[cfg(feature = "remote_write")]
+    let remote_write = conf
+        .remote_write
+        .as_ref()
+        .map(|remote| RemoteWrite::new(client.clone(), remote));
+    #[cfg(unix)]
+    let systemd = Systemd::new(client.clone());
 
     let path = format!("/{}", conf.slug.clone());
-    HttpServer::new(move || {
-        App::new()
+    let server_path = path.clone();
+    let debug_endpoints = conf.debug_endpoints;
+    // shared by every worker so that concurrent scrapes are deduplicated across all of them
+    let state = WebState::new(
+        client,
+        conf.slug.clone(),
+        Arc::new(conf.basic_auth_users.clone()),
+        conf.cache_ttl.map(Duration::from_secs_f64),
+        // the status has already been checked to be in range when the config was validated
+        conf.failed_scrape_status
+            .map(|status| StatusCode::from_u16(status).unwrap()),
+    );
+    let mut server = HttpServer::new(move || {
+        let app = App::new()
+            .wrap(from_fn(authenticate))
             .wrap(TracingLogger::default())
             .wrap(Logger::default())
             .wrap(Compress::default())
-            .app_data(Data::new(WebState::new(client.clone())))
-            .route(path.as_str(), a_web::get().to(metrics))
-    })
-    .bind(format!("{}:{}", conf.ip, conf.port))?
-    .run()
-    .await
+            .app_data(Data::new(state.clone()))
+            .route(server_path.as_str(), a_web::get().to(metrics))
+            .route(HEALTHZ_PATH, a_web::get().to(healthz))
+            .route(
+                format!("{}/{{collector}}", server_path.trim_end_matches('/')).as_str(),
+                a_web::get().to(collector_metrics),
+            );
+        let app = if debug_endpoints {
+            app.route("/debug/raw", a_web::get().to(raw_responses))
+        } else {
+            app
+        };
+        // with an empty slug the metrics are already served at /
+        if server_path == "/" {
+            app
+        } else {
+            app.route("/", a_web::get().to(index))
+        }
+    });
+    let tls_config = match (&conf.tls_cert_file, &conf.tls_key_file) {
+        (Some(cert_file), Some(key_file)) => Some(load_tls_config(cert_file, key_file)?),
+        _ => None,
+    };
+    // listeners handed over by systemd socket activation replace the configured addresses
+    let mut listenfd = ListenFd::from_env();
+    let socket_activated = listenfd.len() > 0;
+    for i in 0..listenfd.len() {
+        // a socket that isn't tcp is left in place to be tried as a unix socket
+        if let Ok(Some(listener)) = listenfd.take_tcp_listener(i) {
+            let address = listener.local_addr()?;
+            match &tls_config {
+                Some(tls_config) => {
+                    info!(
+                        "Serving metrics on https://{}{} (socket activated)",
+                        address, path
+                    );
+                    server = server.listen_rustls_0_23(listener, tls_config.clone())?;
+                }
+                None => {
+                    info!(
+                        "Serving metrics on http://{}{} (socket activated)",
+                        address, path
+                    );
+                    server = server.listen(listener)?;
+                }
+            }
+            continue;
+        }
+        #[cfg(unix)]
+        if let Some(listener) = listenfd.take_unix_listener(i)? {
+            info!(
+                "Serving metrics on unix:{}{} (socket activated)",
+                listener
+                    .local_addr()?
+                    .as_pathname()
+                    .map_or("<unnamed>".to_string(), |socket| socket
+                        .display()
+                        .to_string()),
+                path
+            );
+            server = server.listen_uds(listener)?;
+        }
+    }
+    if !socket_activated {
+        for address in &conf.listen {
+            match &tls_config {
+                Some(tls_config) => {
+                    info!("Serving metrics on https://{}{}", address, path);
+                    server = server.bind_rustls_0_23(address, tls_config.clone())?;
+                }
+                None => {
+                    info!("Serving metrics on http://{}{}", address, path);
+                    server = server.bind(address)?;
+                }
+            }
+        }
+        // listen_socket is rejected during validation on platforms without unix sockets
+        #[cfg(unix)]
+        if let Some(socket) = &conf.listen_socket {
+            info!("Serving metrics on unix:{}{}", socket, path);
+            remove_stale_socket(socket)?;
+            server = server.bind_uds(socket)?;
+        }
+    }
+    if let Some(pushgateway) = pushgateway {
+        info!(
+            "Pushing metrics to {}",
+            conf.pushgateway.as_ref().unwrap().url
+        );
+        actix_web::rt::spawn(pushgateway.run());
+    }
+    #[cfg(feature = "remote_write")]
+    if let Some(remote_write) = remote_write {
+        info!(
+            "Writing metrics to {}",
+            conf.remote_write.as_ref().unwrap().url
+        );
+        actix_web::rt::spawn(remote_write.run());
+    }
+    if let Some(graphite) = graphite {
+        info!(
+            "Sending metrics to {}",
+            conf.graphite.as_ref().unwrap().address
+        );
+        actix_web::rt::spawn(graphite.run());
+    }
+    if let Some(textfile) = textfile {
+        info!(
+            "Writing metrics to {}",
+            conf.textfile.as_ref().unwrap().path
+        );
+        actix_web::rt::spawn(textfile.run());
+    }
+    if let Some(otlp) = otlp {
+        info!("Exporting metrics to {}", conf.otlp.as_ref().unwrap().url);
+        actix_web::rt::spawn(otlp.run());
+    }
+    // every listener is bound by now, so only the router is left to wait for before telling systemd
+    // that the exporter is ready
+    #[cfg(unix)]
+    actix_web::rt::spawn(systemd.run());
+    if !socket_activated && conf.listen.is_empty() && conf.listen_socket.is_none() {
+        // with nowhere to listen on the metrics are only pushed, until the process is stopped
+        shutdown_signal().await;
+    } else {
+        // on SIGTERM or SIGINT new connections are refused while in-flight scrapes get up to
+        // shutdown_timeout seconds to finish
+        server.shutdown_timeout(conf.shutdown_timeout).run().await?;
+    }
+    #[cfg(unix)]
+    Systemd::stopping();
+    info!("Shut down");
+    Ok(())
+}
+
+// SIGTERM or SIGINT, as the server would otherwise have handled them
+async fn shutdown_signal() {
+    let interrupt = Box::pin(actix_web::rt::signal::ctrl_c());
+    #[cfg(unix)]
+    if let Ok(mut terminate) =
+        actix_web::rt::signal::unix::signal(actix_web::rt::signal::unix::SignalKind::terminate())
+    {
+        futures::future::select(interrupt, Box::pin(terminate.recv())).await;
+        return;
+    }
+    let _ = interrupt.await;
+}
+
+fn load_tls_config(cert_file: &str, key_file: &str) -> std::io::Result<rustls::ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(cert_file)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| std::io::Error::other(format!("unable to read {}: {:?}", cert_file, err)))?;
+    let key = PrivateKeyDer::from_pem_file(key_file)
+        .map_err(|err| std::io::Error::other(format!("unable to read {}: {:?}", key_file, err)))?;
+    rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|err| std::io::Error::other(format!("invalid TLS certificate: {}", err)))
+}
+
+// a socket left behind by a previous run that didn't shut down cleanly would otherwise make the bind
+// fail
+#[cfg(unix)]
+fn remove_stale_socket(socket: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if std::fs::metadata(socket).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(socket)?;
+    }
+    Ok(())
+}
+
+// RUST_LOG takes precedence over the configured level so that per-module filtering can be turned on
+// without editing the config
+fn log_filter(directives: Option<&String>) -> EnvFilter {
+    EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(directives.map_or("info", |directives| directives)))
+}
+
+type LogFileLayer = fmt::Layer<Registry, DefaultFields, Format, Mutex<LogFile>>;
+
+// command line flags take precedence over the values in the config file
+fn override_conf(conf: &mut config::RawConfig, matches: &clap::ArgMatches) {
+    for (name, value) in [
+        ("ip", &mut conf.ip),
+        ("slug", &mut conf.slug),
+        ("router_ip", &mut conf.router_ip),
+        ("admin_username", &mut conf.admin_username),
+        ("admin_password", &mut conf.admin_password),
+        ("http_id", &mut conf.http_id),
+        ("log_level", &mut conf.log_level),
+    ] {
+        if let Some(flag) = matches.get_one::<String>(name) {
+            *value = Some(flag.clone());
+        }
+    }
+    if let Some(port) = matches.get_one::<u16>("port") {
+        conf.port = Some(*port as i64);
+    }
+    if matches.contains_id("admin_password") {
+        conf.admin_password_file = None;
+        conf.admin_password_env = None;
+    }
 }
 
 #[cfg(target_os = "windows")]
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, ClientBuilder};
+use time::OffsetDateTime;
+use tracing::{debug, warn};
+
+use crate::client::TomatoClient;
+use crate::config::OtlpConfig;
+
+// scrapes every collector on an interval and sends the result to an OpenTelemetry collector over
+// OTLP/HTTP, using its JSON encoding
+pub struct Otlp {
+    client: TomatoClient,
+    http: Client,
+    url: String,
+    interval: Duration,
+    // when the exporter started, in nanoseconds since the epoch, which the counters and histograms
+    // are reported as counting from
+    start: u64,
+}
+
+impl Otlp {
+    pub fn new(client: TomatoClient, conf: &OtlpConfig) -> Otlp {
+        let interval = Duration::from_secs_f64(conf.interval);
+        // the headers have already been checked when the config was validated
+        let headers: HeaderMap = conf
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                    HeaderValue::from_str(value).unwrap(),
+                )
+            })
+            .collect();
+        Otlp {
+            client,
+            // an export that takes longer than the interval would only pile up behind the next one
+            http: ClientBuilder::new()
+                .timeout(interval)
+                .default_headers(headers)
+                .build()
+                .expect("Unable to construct HTTP client"),
+            url: conf.url.clone(),
+            interval,
+            start: OffsetDateTime::now_utc().unix_timestamp_nanos() as u64,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match self.export().await {
+                Ok(()) => debug!("Exported metrics to {}", self.url),
+                Err(err) => warn!("Exporting metrics to {} failed: {}", self.url, err),
+            }
+        }
+    }
+
+    async fn export(&self) -> Result<(), String> {
+        let scrape = self
+            .client
+            .get_metrics(&[])
+            .await
+            .map_err(|err| err.to_string())?;
+        let now = OffsetDateTime::now_utc().unix_timestamp_nanos() as u64;
+        self.http
+            .post(self.url.as_str())
+            .header(CONTENT_TYPE, "application/json")
+            .body(scrape.response.to_otlp(now, self.start).to_string())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
@@ -1,3 +1,59 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use time::OffsetDateTime;
+use tracing::error;
+
+use crate::config::RelabelAction;
+
+mod graphite;
+mod otlp;
+#[cfg(feature = "protobuf")]
+mod proto;
+#[cfg(feature = "remote_write")]
+mod remote_write;
+
+// the prefix shared with node_exporter's metric names
+pub const DEFAULT_NAMESPACE: &str = "node";
+
+// the units OpenMetrics recommends, announced for any metric whose name ends with one of them
+const OPENMETRICS_UNITS: [&str; 9] = [
+    "seconds", "bytes", "celsius", "ratio", "volts", "amperes", "joules", "grams", "meters",
+];
+
+// names are built from whatever the router reports, such as /proc/meminfo keys, so anything that
+// isn't allowed in a name is replaced with an underscore rather than corrupting the whole response.
+// colons are reserved for recording rules, so only metric names may contain them
+fn sanitize_name(name: &str, allow_colon: bool) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => c,
+            ':' if allow_colon => c,
+            _ => '_',
+        })
+        .collect();
+    // names can't start with a digit either
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", sanitized)
+    } else {
+        sanitized
+    }
+}
+
+// Rust writes infinities as inf and -inf, where the exposition formats expect +Inf and -Inf
+fn format_value(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
 #[derive(PartialEq, PartialOrd, Debug, Clone)]
 pub struct PromResponse {
     metrics: Vec<PromMetric>,
@@ -8,13 +64,179 @@ impl PromResponse {
         PromResponse { metrics }
     }
 
+    // only the metrics mirroring node_exporter carry the default namespace, the rest already have
+    // names of their own
+    pub fn with_namespace(self, namespace: &str) -> PromResponse {
+        if namespace == DEFAULT_NAMESPACE {
+            return self;
+        }
+        let default_prefix = format!("{}_", DEFAULT_NAMESPACE);
+        PromResponse {
+            metrics: self
+                .metrics
+                .into_iter()
+                .map(
+                    |metric| match metric.name.strip_prefix(default_prefix.as_str()) {
+                        Some(name) => PromMetric {
+                            name: format!("{}_{}", namespace, name),
+                            ..metric
+                        },
+                        None => metric,
+                    },
+                )
+                .collect(),
+        }
+    }
+
+    // counters that haven't started moving yet are dropped, gauges are kept since a zero is usually
+    // meaningful for them
+    pub fn without_zero_counters(self) -> PromResponse {
+        PromResponse {
+            metrics: self
+                .metrics
+                .into_iter()
+                .map(|metric| match metric.typ {
+                    PromMetricType::Counter => PromMetric {
+                        samples: metric
+                            .samples
+                            .into_iter()
+                            .filter(|sample| sample.value != 0f64)
+                            .collect(),
+                        ..metric
+                    },
+                    _ => metric,
+                })
+                .collect(),
+        }
+    }
+
+    // metrics reported under the same name by more than one collector are combined into one, since
+    // repeating a metric's HELP and TYPE is rejected by some scrapers.  a metric that doesn't agree with
+    // the first one of its name about its type is a bug in a collector, and is dropped, as is any
+    // sample whose labels have already been reported
+    pub fn merge_duplicates(self) -> PromResponse {
+        let mut metrics: Vec<PromMetric> = Vec::new();
+        for metric in self.metrics {
+            let Some(existing) = metrics
+                .iter_mut()
+                .find(|existing| existing.name == metric.name)
+            else {
+                metrics.push(metric);
+                continue;
+            };
+            if existing.typ != metric.typ {
+                error!(
+                    "Dropping {} reported as a {:?} when it was already reported as a {:?}",
+                    metric.name, metric.typ, existing.typ
+                );
+                continue;
+            }
+            for sample in metric.samples {
+                if !existing
+                    .samples
+                    .iter()
+                    .any(|other| other.labels == sample.labels && other.suffix == sample.suffix)
+                {
+                    existing.samples.push(sample);
+                }
+            }
+        }
+        PromResponse { metrics }
+    }
+
+    // labels identifying where the metrics came from, such as the router or the site, are added to
+    // every sample.  a sample that already carries a label of the same name keeps its own value
+    pub fn with_const_labels(self, labels: &BTreeMap<String, String>) -> PromResponse {
+        if labels.is_empty() {
+            return self;
+        }
+        PromResponse {
+            metrics: self
+                .metrics
+                .into_iter()
+                .map(|metric| PromMetric {
+                    samples: metric
+                        .samples
+                        .into_iter()
+                        .map(|mut sample| {
+                            for (name, value) in labels {
+                                if !sample.labels.iter().any(|label| &label.name == name) {
+                                    sample.labels.push(PromLabel::new(name, value.clone()));
+                                }
+                            }
+                            sample
+                        })
+                        .collect(),
+                    ..metric
+                })
+                .collect(),
+        }
+    }
+
+    // for consumers that assign every sample the time they read it, such as node_exporter's textfile
+    // collector, which rejects samples carrying a time of their own
+    pub fn without_timestamps(self) -> PromResponse {
+        PromResponse {
+            metrics: self
+                .metrics
+                .into_iter()
+                .map(|metric| PromMetric {
+                    samples: metric
+                        .samples
+                        .into_iter()
+                        .map(|sample| PromSample {
+                            timestamp: None,
+                            ..sample
+                        })
+                        .collect(),
+                    ..metric
+                })
+                .collect(),
+        }
+    }
+
+    pub fn relabel(self, rules: &[(Regex, RelabelAction)]) -> PromResponse {
+        PromResponse {
+            metrics: self
+                .metrics
+                .into_iter()
+                .filter_map(|metric| {
+                    rules.iter().try_fold(metric, |metric, (re, action)| {
+                        if !re.is_match(metric.name.as_str()) {
+                            return Some(metric);
+                        }
+                        metric.relabel(re, action)
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    // sorted by name so that the output only changes when the metrics do, whatever order the
+    // collectors finished in.  the sort is stable, so metrics sharing a name end up next to each other
+    // in the order they were collected
     pub fn to_prom(&self) -> String {
-        self.metrics
+        let mut metrics: Vec<&PromMetric> = self.metrics.iter().collect();
+        metrics.sort_by(|a, b| a.name.cmp(&b.name));
+        metrics
             .iter()
             .map(|metric| metric.to_prom())
             .collect::<Vec<String>>()
             .join("\n")
     }
+
+    // the same metrics for OpenMetrics consumers, which need every line terminated and the end of
+    // the exposition marked
+    pub fn to_openmetrics(&self) -> String {
+        let mut metrics: Vec<&PromMetric> = self.metrics.iter().collect();
+        metrics.sort_by(|a, b| a.name.cmp(&b.name));
+        metrics
+            .iter()
+            .flat_map(|metric| metric.to_openmetrics())
+            .chain(std::iter::once("# EOF".to_string()))
+            .map(|line| line + "\n")
+            .collect()
+    }
 }
 
 #[derive(Eq, PartialEq, PartialOrd, Debug, Clone)]
@@ -24,6 +246,8 @@ pub enum PromMetricType {
     Gauge,
     Histogram,
     Summary,
+    // a gauge that is always 1, carrying its information in its labels
+    Info,
     Untyped,
 }
 
@@ -43,34 +267,223 @@ impl PromMetric {
         samples: Vec<PromSample>,
     ) -> PromMetric {
         PromMetric {
-            name: name.to_string(),
+            name: sanitize_name(name, true),
             help: help.to_string(),
             typ,
             samples,
         }
     }
 
+    // for collectors set to drop the NaN and infinite values they come up with, such as from a
+    // division by zero, rather than report them
+    pub fn without_non_finite(self) -> PromMetric {
+        PromMetric {
+            samples: self
+                .samples
+                .into_iter()
+                .filter(|sample| sample.value.is_finite())
+                .collect(),
+            ..self
+        }
+    }
+
+    // the samples of a histogram or summary gathered up by their labels other than the bucket's or
+    // quantile's, in the order each set of labels first appeared
+    fn grouped(&self, label: &str) -> Vec<(Vec<PromLabel>, Vec<&PromSample>)> {
+        let mut groups: Vec<(Vec<PromLabel>, Vec<&PromSample>)> = Vec::new();
+        for sample in &self.samples {
+            let labels: Vec<PromLabel> = sample
+                .labels
+                .iter()
+                .filter(|existing| existing.name != label)
+                .cloned()
+                .collect();
+            match groups.iter_mut().find(|(existing, _)| *existing == labels) {
+                Some((_, samples)) => samples.push(sample),
+                None => groups.push((labels, vec![sample])),
+            }
+        }
+        groups
+    }
+
+    // the bounds or quantiles of a group along with their values, then its sum and count
+    fn split(samples: &[&PromSample], suffix: &str) -> (Vec<(f64, f64)>, f64, f64) {
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        let mut sum = 0f64;
+        let mut count = 0f64;
+        for sample in samples {
+            match sample.suffix {
+                "_sum" => sum = sample.value,
+                "_count" => count = sample.value,
+                other if other == suffix => {
+                    if let Some(point) = sample
+                        .labels
+                        .iter()
+                        .find(|label| label.name == "le" || label.name == "quantile")
+                        .and_then(|label| label.value.parse::<f64>().ok())
+                    {
+                        points.push((point, sample.value));
+                    }
+                }
+                _ => {}
+            }
+        }
+        (points, sum, count)
+    }
+
+    // None when the metric is dropped
+    fn relabel(self, re: &Regex, action: &RelabelAction) -> Option<PromMetric> {
+        match action {
+            RelabelAction::Drop => None,
+            RelabelAction::Rename { name } => Some(PromMetric {
+                name: sanitize_name(re.replace(self.name.as_str(), name.as_str()).as_ref(), true),
+                ..self
+            }),
+            RelabelAction::AddLabel { label, value } => Some(PromMetric {
+                samples: self
+                    .samples
+                    .into_iter()
+                    .map(|mut sample| {
+                        sample.labels.retain(|existing| existing.name != *label);
+                        sample.labels.push(PromLabel::new(label, value.clone()));
+                        sample
+                    })
+                    .collect(),
+                ..self
+            }),
+            RelabelAction::RemoveLabel { label } => Some(PromMetric {
+                samples: self
+                    .samples
+                    .into_iter()
+                    .map(|mut sample| {
+                        sample.labels.retain(|existing| existing.name != *label);
+                        sample
+                    })
+                    .collect(),
+                ..self
+            }),
+        }
+    }
+
+    // a line break in the help text would end the comment early
     pub fn to_prom(&self) -> String {
         format!(
             "# HELP {} {}\n# TYPE {} {}\n{}",
             self.name,
-            self.help,
+            self.help.replace('\\', "\\\\").replace('\n', "\\n"),
             self.name,
-            format!("{:?}", self.typ).to_lowercase(),
+            match self.typ {
+                // the text format has no type of its own for these
+                PromMetricType::Info => "gauge".to_string(),
+                _ => format!("{:?}", self.typ).to_lowercase(),
+            },
             self.samples
                 .iter()
-                .map(|sample| sample.to_prom(self.name.clone()))
+                .map(|sample| sample.to_prom(format!("{}{}", self.name, sample.suffix)))
                 .collect::<Vec<String>>()
                 .join("\n")
         )
     }
+
+    // starts a metric to be filled in with the builder's methods, untyped until given a type
+    pub fn build(name: &str) -> PromMetricBuilder {
+        PromMetricBuilder {
+            metric: PromMetric::new(name, "", PromMetricType::Untyped, Vec::new()),
+        }
+    }
+
+    // one sample per bucket followed by the sum and count, for each set of labels observed
+    pub fn histogram(name: &str, help: &str, histograms: Vec<PromHistogram>) -> PromMetric {
+        PromMetric::new(
+            name,
+            help,
+            PromMetricType::Histogram,
+            histograms
+                .into_iter()
+                .flat_map(PromHistogram::into_samples)
+                .collect(),
+        )
+    }
+
+    // one sample per quantile followed by the sum and count, for each set of labels
+    #[allow(dead_code)]
+    pub fn summary(name: &str, help: &str, summaries: Vec<PromSummary>) -> PromMetric {
+        PromMetric::new(
+            name,
+            help,
+            PromMetricType::Summary,
+            summaries
+                .into_iter()
+                .flat_map(PromSummary::into_samples)
+                .collect(),
+        )
+    }
+
+    // the name is given the _info suffix if it doesn't already have it, and every set of labels becomes
+    // one sample
+    pub fn info(name: &str, help: &str, label_sets: Vec<Vec<PromLabel>>) -> PromMetric {
+        let name = if name.ends_with("_info") {
+            name.to_string()
+        } else {
+            format!("{}_info", name)
+        };
+        PromMetric::new(
+            name.as_str(),
+            help,
+            PromMetricType::Info,
+            label_sets
+                .into_iter()
+                .map(|labels| PromSample::new(labels, 1f64, None))
+                .collect(),
+        )
+    }
+
+    // OpenMetrics names counter and info families without the _total or _info their samples have to
+    // end with, and calls untyped metrics unknown
+    fn to_openmetrics(&self) -> Vec<String> {
+        let suffix = match self.typ {
+            PromMetricType::Counter => "_total",
+            PromMetricType::Info => "_info",
+            _ => "",
+        };
+        let family = self.name.strip_suffix(suffix).unwrap_or(&self.name);
+        let sample_name = format!("{}{}", family, suffix);
+        let typ = match self.typ {
+            PromMetricType::Untyped => "unknown".to_string(),
+            _ => format!("{:?}", self.typ).to_lowercase(),
+        };
+        let mut lines = vec![format!("# TYPE {} {}", family, typ)];
+        if let Some(unit) = OPENMETRICS_UNITS
+            .iter()
+            .find(|unit| family.ends_with(format!("_{}", unit).as_str()))
+        {
+            lines.push(format!("# UNIT {} {}", family, unit));
+        }
+        lines.push(format!(
+            "# HELP {} {}",
+            family,
+            self.help
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        ));
+        lines.extend(
+            self.samples
+                .iter()
+                .map(|sample| sample.to_openmetrics(format!("{}{}", sample_name, sample.suffix))),
+        );
+        lines
+    }
 }
 
 #[derive(PartialEq, PartialOrd, Debug, Clone)]
 pub struct PromSample {
     labels: Vec<PromLabel>,
     value: f64,
+    // milliseconds since the epoch, as the exposition formats expect
     timestamp: Option<u64>,
+    // appended to the metric's name, for the _bucket, _sum and _count samples of a histogram
+    suffix: &'static str,
 }
 
 impl PromSample {
@@ -79,25 +492,273 @@ impl PromSample {
             labels,
             value,
             timestamp,
+            suffix: "",
         }
     }
 
+    // for a sample taken at a known time other than the scrape's.  times before the epoch can't be
+    // given in the exposition formats and are left at the epoch
+    #[allow(dead_code)]
+    pub fn at(labels: Vec<PromLabel>, value: f64, time: OffsetDateTime) -> PromSample {
+        let millis = time.unix_timestamp_nanos() / 1_000_000;
+        PromSample::new(labels, value, Some(millis.max(0) as u64))
+    }
+
+    // a sample with no labels and a value of 0 until told otherwise
+    pub fn build() -> PromSampleBuilder {
+        PromSampleBuilder {
+            sample: PromSample::new(Vec::new(), 0f64, None),
+        }
+    }
+
+    // the braces are left out when there are no labels, as every other exporter does
     pub fn to_prom(&self, name: String) -> String {
         format!(
-            "{}{{{}}} {}{}",
+            "{}{} {}{}",
             name,
+            self.labels_to_prom(),
+            format_value(self.value),
+            self.timestamp
+                .map_or_else(|| "".to_string(), |ts| format!(" {}", ts))
+        )
+    }
+
+    fn labels_to_prom(&self) -> String {
+        if self.labels.is_empty() {
+            return String::new();
+        }
+        format!(
+            "{{{}}}",
             self.labels
                 .iter()
                 .map(|label| label.to_prom())
                 .collect::<Vec<String>>()
-                .join(","),
-            self.value,
+                .join(",")
+        )
+    }
+
+    // OpenMetrics gives timestamps in seconds rather than milliseconds
+    fn to_openmetrics(&self, name: String) -> String {
+        format!(
+            "{}{} {}{}",
+            name,
+            self.labels_to_prom(),
+            format_value(self.value),
             self.timestamp
-                .map_or_else(|| "".to_string(), |ts| format!(" {}", ts))
+                .map_or_else(|| "".to_string(), |ts| format!(" {}", ts as f64 / 1000f64))
         )
     }
 }
 
+pub struct PromMetricBuilder {
+    metric: PromMetric,
+}
+
+#[allow(dead_code)]
+impl PromMetricBuilder {
+    pub fn help(mut self, help: &str) -> PromMetricBuilder {
+        self.metric.help = help.to_string();
+        self
+    }
+
+    pub fn counter(self) -> PromMetricBuilder {
+        self.typ(PromMetricType::Counter)
+    }
+
+    pub fn gauge(self) -> PromMetricBuilder {
+        self.typ(PromMetricType::Gauge)
+    }
+
+    pub fn typ(mut self, typ: PromMetricType) -> PromMetricBuilder {
+        self.metric.typ = typ;
+        self
+    }
+
+    pub fn sample(
+        mut self,
+        sample: impl FnOnce(PromSampleBuilder) -> PromSampleBuilder,
+    ) -> PromMetricBuilder {
+        self.metric.samples.push(sample(PromSample::build()).sample);
+        self
+    }
+
+    // one sample for each item, such as every interface of a collector's results
+    pub fn samples<T>(
+        mut self,
+        items: impl IntoIterator<Item = T>,
+        sample: impl Fn(PromSampleBuilder, T) -> PromSampleBuilder,
+    ) -> PromMetricBuilder {
+        self.metric.samples.extend(
+            items
+                .into_iter()
+                .map(|item| sample(PromSample::build(), item).sample),
+        );
+        self
+    }
+
+    pub fn finish(self) -> PromMetric {
+        self.metric
+    }
+}
+
+pub struct PromSampleBuilder {
+    sample: PromSample,
+}
+
+#[allow(dead_code)]
+impl PromSampleBuilder {
+    pub fn label(mut self, name: &str, value: impl Into<String>) -> PromSampleBuilder {
+        self.sample.labels.push(PromLabel::new(name, value.into()));
+        self
+    }
+
+    pub fn value(mut self, value: f64) -> PromSampleBuilder {
+        self.sample.value = value;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u64) -> PromSampleBuilder {
+        self.sample.timestamp = Some(timestamp);
+        self
+    }
+}
+
+// observations counted into buckets by upper bound, along with their sum and count.  the buckets are
+// cumulative, so an observation is counted in every bucket it fits in
+#[derive(PartialEq, PartialOrd, Debug, Clone)]
+pub struct PromHistogram {
+    labels: Vec<PromLabel>,
+    buckets: Vec<(f64, u64)>,
+    sum: f64,
+    count: u64,
+}
+
+impl PromHistogram {
+    // the +Inf bucket is always there and doesn't need to be included in bounds
+    pub fn new(labels: Vec<PromLabel>, bounds: &[f64]) -> PromHistogram {
+        let mut bounds: Vec<f64> = bounds
+            .iter()
+            .copied()
+            .filter(|bound| *bound != f64::INFINITY)
+            .collect();
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        PromHistogram {
+            labels,
+            buckets: bounds.into_iter().map(|bound| (bound, 0)).collect(),
+            sum: 0f64,
+            count: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        for (bound, count) in self.buckets.iter_mut() {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn into_samples(self) -> Vec<PromSample> {
+        let sample = |suffix: &'static str, labels: Vec<PromLabel>, value: f64| PromSample {
+            suffix,
+            ..PromSample::new(labels, value, None)
+        };
+        let bucket = |le: String, count: u64| {
+            let mut labels = self.labels.clone();
+            labels.push(PromLabel::new("le", le));
+            sample("_bucket", labels, count as f64)
+        };
+        self.buckets
+            .iter()
+            .map(|(bound, count)| bucket(format!("{:?}", bound), *count))
+            .chain([
+                bucket("+Inf".to_string(), self.count),
+                sample("_sum", self.labels.clone(), self.sum),
+                sample("_count", self.labels.clone(), self.count as f64),
+            ])
+            .collect()
+    }
+}
+
+// quantiles worked out from a set of observations, such as the round trip times of a batch of pings,
+// along with their sum and count
+#[derive(PartialEq, PartialOrd, Debug, Clone)]
+#[allow(dead_code)]
+pub struct PromSummary {
+    labels: Vec<PromLabel>,
+    quantiles: Vec<(f64, f64)>,
+    sum: f64,
+    count: u64,
+}
+
+#[allow(dead_code)]
+impl PromSummary {
+    pub fn new(
+        labels: Vec<PromLabel>,
+        quantiles: Vec<(f64, f64)>,
+        sum: f64,
+        count: u64,
+    ) -> PromSummary {
+        PromSummary {
+            labels,
+            quantiles,
+            sum,
+            count,
+        }
+    }
+
+    // each quantile is the nearest observation by rank.  with no observations every quantile is NaN,
+    // as client libraries report it
+    pub fn from_observations(
+        labels: Vec<PromLabel>,
+        quantiles: &[f64],
+        observations: &[f64],
+    ) -> PromSummary {
+        let mut sorted = observations.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        PromSummary {
+            labels,
+            quantiles: quantiles
+                .iter()
+                .map(|quantile| {
+                    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+                    (
+                        *quantile,
+                        sorted
+                            .get(rank.clamp(1, sorted.len().max(1)) - 1)
+                            .copied()
+                            .unwrap_or(f64::NAN),
+                    )
+                })
+                .collect(),
+            sum: sorted.iter().sum(),
+            count: sorted.len() as u64,
+        }
+    }
+
+    fn into_samples(self) -> Vec<PromSample> {
+        let sample = |suffix: &'static str, labels: Vec<PromLabel>, value: f64| PromSample {
+            suffix,
+            ..PromSample::new(labels, value, None)
+        };
+        self.quantiles
+            .iter()
+            .map(|(quantile, value)| {
+                let mut labels = self.labels.clone();
+                labels.push(PromLabel::new("quantile", format!("{:?}", quantile)));
+                sample("", labels, *value)
+            })
+            .chain([
+                sample("_sum", self.labels.clone(), self.sum),
+                sample("_count", self.labels.clone(), self.count as f64),
+            ])
+            .collect()
+    }
+}
+
 #[derive(Eq, PartialEq, PartialOrd, Debug, Clone)]
 pub struct PromLabel {
     name: String,
@@ -107,13 +768,22 @@ pub struct PromLabel {
 impl PromLabel {
     pub fn new(name: &str, value: String) -> PromLabel {
         PromLabel {
-            name: name.to_string(),
+            name: sanitize_name(name, false),
             value,
         }
     }
 
+    // values come straight from the router, so anything that would end the quoted string early has
+    // to be escaped
     pub fn to_prom(&self) -> String {
-        format!("{}=\"{}\"", self.name, self.value)
+        format!(
+            "{}=\"{}\"",
+            self.name,
+            self.value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        )
     }
 }
 
@@ -122,22 +792,87 @@ impl PromLabel {
 mod test {
     use super::*;
 
+    #[test]
+    fn test__sanitize_name() {
+        assert_eq!(
+            sanitize_name("node_memory_Active(file)_bytes", true),
+            "node_memory_Active_file__bytes"
+        );
+        assert_eq!(
+            sanitize_name("job:requests:rate5m", true),
+            "job:requests:rate5m"
+        );
+        assert_eq!(sanitize_name("job:requests", false), "job_requests");
+        assert_eq!(sanitize_name("5ghz", false), "_5ghz");
+        assert_eq!(sanitize_name("wl0.1", false), "wl0_1");
+        assert_eq!(sanitize_name("", false), "_");
+    }
+
     #[test]
     fn test__PromLabel__to_string() {
         let label = PromLabel::new("foo", "bar".to_string());
         assert_eq!(label.to_prom(), "foo=\"bar\"")
     }
 
+    #[test]
+    fn test__PromLabel__to_string__escaped() {
+        let label = PromLabel::new("ssid", "say \"hi\"\\\nbye".to_string());
+        assert_eq!(label.to_prom(), "ssid=\"say \\\"hi\\\"\\\\\\nbye\"")
+    }
+
     #[test]
     fn test__PromSample__to_string__no_labels_no_timestamp() {
         let sample = PromSample::new(vec![], 4.5, None);
-        assert_eq!(sample.to_prom("baz".to_string()), "baz{} 4.5")
+        assert_eq!(sample.to_prom("baz".to_string()), "baz 4.5")
     }
 
     #[test]
     fn test__PromSample__to_string__no_labels_with_timestamp() {
         let sample = PromSample::new(vec![], 4.5, Some(12345));
-        assert_eq!(sample.to_prom("baz".to_string()), "baz{} 4.5 12345")
+        assert_eq!(sample.to_prom("baz".to_string()), "baz 4.5 12345")
+    }
+
+    #[test]
+    fn test__PromSample__to_string__non_finite() {
+        let sample = |value: f64| PromSample::new(vec![], value, None);
+        assert_eq!(sample(f64::INFINITY).to_prom("baz".to_string()), "baz +Inf");
+        assert_eq!(
+            sample(f64::NEG_INFINITY).to_prom("baz".to_string()),
+            "baz -Inf"
+        );
+        assert_eq!(sample(f64::NAN).to_prom("baz".to_string()), "baz NaN");
+        assert_eq!(
+            sample(f64::NEG_INFINITY).to_openmetrics("baz".to_string()),
+            "baz -Inf"
+        );
+    }
+
+    #[test]
+    fn test__PromMetric__without_non_finite() {
+        let metric = |values: &[f64]| {
+            PromMetric::new(
+                "wifi_noise_floor_dbm",
+                "Noise floor",
+                PromMetricType::Gauge,
+                values
+                    .iter()
+                    .map(|value| PromSample::new(vec![], *value, None))
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            metric(&[-92f64, f64::NAN, f64::INFINITY]).without_non_finite(),
+            metric(&[-92f64])
+        )
+    }
+
+    #[test]
+    fn test__PromSample__at() {
+        let time = OffsetDateTime::from_unix_timestamp_nanos(1_598_394_934_250_000_000).unwrap();
+        assert_eq!(
+            PromSample::at(vec![], 4.5, time),
+            PromSample::new(vec![], 4.5, Some(1598394934250))
+        )
     }
 
     #[test]
@@ -171,6 +906,20 @@ mod test {
         )
     }
 
+    #[test]
+    fn test__PromMetric__to_string__escaped_help() {
+        let metric = PromMetric::new(
+            "baz",
+            "A funny\nvalue in C:\\",
+            PromMetricType::Counter,
+            vec![],
+        );
+        assert_eq!(
+            metric.to_prom(),
+            "# HELP baz A funny\\nvalue in C:\\\\\n# TYPE baz counter\n"
+        )
+    }
+
     #[test]
     fn test__PromMetric__to_string__one_sample() {
         let metric = PromMetric::new(
@@ -202,10 +951,151 @@ mod test {
         );
         assert_eq!(
             metric.to_prom(),
-            "# HELP baz A funny value\n# TYPE baz counter\nbaz{foo=\"bar\"} 4.5\nbaz{} 4.5 12345"
+            "# HELP baz A funny value\n# TYPE baz counter\nbaz{foo=\"bar\"} 4.5\nbaz 4.5 12345"
         )
     }
 
+    #[test]
+    fn test__PromMetric__histogram() {
+        let mut histogram = PromHistogram::new(
+            vec![PromLabel::new("collector", "cpu".to_string())],
+            &[1f64, 0.1, f64::INFINITY],
+        );
+        histogram.observe(0.05);
+        histogram.observe(0.5);
+        histogram.observe(2f64);
+        let metric = PromMetric::histogram(
+            "collector_duration_seconds",
+            "How long the collector took",
+            vec![histogram],
+        );
+        assert_eq!(
+            metric.to_prom(),
+            "# HELP collector_duration_seconds How long the collector took\n\
+            # TYPE collector_duration_seconds histogram\n\
+            collector_duration_seconds_bucket{collector=\"cpu\",le=\"0.1\"} 1\n\
+            collector_duration_seconds_bucket{collector=\"cpu\",le=\"1.0\"} 2\n\
+            collector_duration_seconds_bucket{collector=\"cpu\",le=\"+Inf\"} 3\n\
+            collector_duration_seconds_sum{collector=\"cpu\"} 2.55\n\
+            collector_duration_seconds_count{collector=\"cpu\"} 3"
+        )
+    }
+
+    #[test]
+    fn test__PromMetric__summary() {
+        let summary = PromSummary::from_observations(
+            vec![PromLabel::new("target", "8.8.8.8".to_string())],
+            &[0.5, 0.9, 0.99],
+            &[0.02, 0.01, 0.04, 0.03],
+        );
+        let metric = PromMetric::summary(
+            "ping_rtt_seconds",
+            "Round trip time of the pings",
+            vec![summary],
+        );
+        assert_eq!(
+            metric.to_prom(),
+            "# HELP ping_rtt_seconds Round trip time of the pings\n\
+            # TYPE ping_rtt_seconds summary\n\
+            ping_rtt_seconds{target=\"8.8.8.8\",quantile=\"0.5\"} 0.02\n\
+            ping_rtt_seconds{target=\"8.8.8.8\",quantile=\"0.9\"} 0.04\n\
+            ping_rtt_seconds{target=\"8.8.8.8\",quantile=\"0.99\"} 0.04\n\
+            ping_rtt_seconds_sum{target=\"8.8.8.8\"} 0.1\n\
+            ping_rtt_seconds_count{target=\"8.8.8.8\"} 4"
+        )
+    }
+
+    #[test]
+    fn test__PromSummary__from_observations__empty() {
+        let summary = PromSummary::from_observations(vec![], &[0.5], &[]);
+        assert!(summary.quantiles[0].1.is_nan());
+        assert_eq!((summary.sum, summary.count), (0f64, 0));
+    }
+
+    #[test]
+    fn test__PromMetric__info() {
+        let metric = PromMetric::info(
+            "wan",
+            "Labeled WAN connection information",
+            vec![vec![PromLabel::new("proto", "dhcp".to_string())]],
+        );
+        assert_eq!(
+            metric,
+            PromMetric::new(
+                "wan_info",
+                "Labeled WAN connection information",
+                PromMetricType::Info,
+                vec![PromSample::new(
+                    vec![PromLabel::new("proto", "dhcp".to_string())],
+                    1f64,
+                    None
+                )],
+            )
+        );
+        assert_eq!(
+            metric.to_prom(),
+            "# HELP wan_info Labeled WAN connection information\n\
+            # TYPE wan_info gauge\n\
+            wan_info{proto=\"dhcp\"} 1"
+        );
+        assert_eq!(
+            PromResponse::new(vec![metric]).to_openmetrics(),
+            "# TYPE wan info\n\
+            # HELP wan Labeled WAN connection information\n\
+            wan_info{proto=\"dhcp\"} 1\n\
+            # EOF\n"
+        );
+    }
+
+    #[test]
+    fn test__PromMetric__build() {
+        assert_eq!(
+            PromMetric::build("node_cpu_seconds_total")
+                .help("Seconds the cpus spent in each mode")
+                .counter()
+                .sample(|s| s.label("cpu", "0").label("mode", "idle").value(4.5))
+                .samples([("1", 5.5)], |s, (cpu, value)| {
+                    s.label("cpu", cpu).label("mode", "idle").value(value)
+                })
+                .finish(),
+            PromMetric::new(
+                "node_cpu_seconds_total",
+                "Seconds the cpus spent in each mode",
+                PromMetricType::Counter,
+                vec![
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("cpu", "0".to_string()),
+                            PromLabel::new("mode", "idle".to_string()),
+                        ],
+                        4.5,
+                        None,
+                    ),
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("cpu", "1".to_string()),
+                            PromLabel::new("mode", "idle".to_string()),
+                        ],
+                        5.5,
+                        None,
+                    ),
+                ],
+            )
+        );
+        assert_eq!(
+            PromMetric::build("node_load1")
+                .gauge()
+                .sample(|s| s.value(0.5).timestamp(12345))
+                .finish(),
+            PromMetric::new(
+                "node_load1",
+                "",
+                PromMetricType::Gauge,
+                vec![PromSample::new(vec![], 0.5, Some(12345))],
+            )
+        );
+    }
+
     #[test]
     fn test__PromResponse__to_string__no_metrics() {
         let response = PromResponse::new(vec![]);
@@ -259,4 +1149,344 @@ mod test {
             "# HELP baz A funny value\n# TYPE baz counter\nbaz{foo=\"bar\"} 4.5\n# HELP spam A silly value\n# TYPE spam counter\nspam{bar=\"foo\"} 5.4"
         )
     }
+
+    #[test]
+    fn test__PromResponse__to_string__sorted() {
+        let metric = |name: &str, value: f64| {
+            PromMetric::new(
+                name,
+                "A funny value",
+                PromMetricType::Gauge,
+                vec![PromSample::new(vec![], value, None)],
+            )
+        };
+        let response = PromResponse::new(vec![
+            metric("spam", 1f64),
+            metric("baz", 2f64),
+            metric("spam", 3f64),
+        ]);
+        assert_eq!(
+            response.to_prom(),
+            "# HELP baz A funny value\n# TYPE baz gauge\nbaz 2\n\
+            # HELP spam A funny value\n# TYPE spam gauge\nspam 1\n\
+            # HELP spam A funny value\n# TYPE spam gauge\nspam 3"
+        )
+    }
+
+    #[test]
+    fn test__PromResponse__to_openmetrics() {
+        let response = PromResponse::new(vec![
+            PromMetric::new(
+                "node_network_receive_bytes_total",
+                "Network device statistic receive_bytes",
+                PromMetricType::Counter,
+                vec![PromSample::new(
+                    vec![PromLabel::new("device", "eth0".to_string())],
+                    1024f64,
+                    Some(1697105472500),
+                )],
+            ),
+            PromMetric::new(
+                "node_load1",
+                "1m load average",
+                PromMetricType::Gauge,
+                vec![PromSample::new(vec![], 0.5, None)],
+            ),
+            PromMetric::new("odd", "Says \"hi\"", PromMetricType::Untyped, vec![]),
+        ]);
+        assert_eq!(
+            response.to_openmetrics(),
+            "# TYPE node_load1 gauge\n\
+            # HELP node_load1 1m load average\n\
+            node_load1 0.5\n\
+            # TYPE node_network_receive_bytes counter\n\
+            # UNIT node_network_receive_bytes bytes\n\
+            # HELP node_network_receive_bytes Network device statistic receive_bytes\n\
+            node_network_receive_bytes_total{device=\"eth0\"} 1024 1697105472.5\n\
+            # TYPE odd unknown\n\
+            # HELP odd Says \\\"hi\\\"\n\
+            # EOF\n"
+        )
+    }
+
+    #[test]
+    fn test__PromResponse__to_openmetrics__counter_without_total() {
+        let response = PromResponse::new(vec![PromMetric::new(
+            "wan_reconnects",
+            "Times the WAN reconnected",
+            PromMetricType::Counter,
+            vec![PromSample::new(vec![], 3f64, None)],
+        )]);
+        assert_eq!(
+            response.to_openmetrics(),
+            "# TYPE wan_reconnects counter\n\
+            # HELP wan_reconnects Times the WAN reconnected\n\
+            wan_reconnects_total 3\n\
+            # EOF\n"
+        )
+    }
+
+    #[test]
+    fn test__PromResponse__with_namespace() {
+        let response = PromResponse::new(vec![
+            PromMetric::new(
+                "node_load1",
+                "1m load average",
+                PromMetricType::Gauge,
+                vec![],
+            ),
+            PromMetric::new(
+                "wan_up",
+                "Whether the WAN is up",
+                PromMetricType::Gauge,
+                vec![],
+            ),
+        ]);
+        assert_eq!(
+            response.clone().with_namespace("tomato"),
+            PromResponse::new(vec![
+                PromMetric::new(
+                    "tomato_load1",
+                    "1m load average",
+                    PromMetricType::Gauge,
+                    vec![]
+                ),
+                PromMetric::new(
+                    "wan_up",
+                    "Whether the WAN is up",
+                    PromMetricType::Gauge,
+                    vec![]
+                ),
+            ])
+        );
+        assert_eq!(response.clone().with_namespace("node"), response)
+    }
+
+    #[test]
+    fn test__PromResponse__relabel() {
+        let response = PromResponse::new(vec![
+            PromMetric::new(
+                "node_network_receive_fifo_total",
+                "Network device statistic receive_fifo",
+                PromMetricType::Counter,
+                vec![],
+            ),
+            PromMetric::new(
+                "wan_up",
+                "Whether the WAN is up",
+                PromMetricType::Gauge,
+                vec![PromSample::new(vec![], 1f64, None)],
+            ),
+            PromMetric::new(
+                "lan_client_receive_bytes_total",
+                "IP Traffic statistic receive_bytes per LAN client",
+                PromMetricType::Counter,
+                vec![PromSample::new(
+                    vec![
+                        PromLabel::new("ip", "192.168.1.20".to_string()),
+                        PromLabel::new("site", "away".to_string()),
+                    ],
+                    42f64,
+                    None,
+                )],
+            ),
+        ]);
+        let rules = vec![
+            (Regex::new("_fifo_total$").unwrap(), RelabelAction::Drop),
+            (
+                Regex::new("^wan_(.*)$").unwrap(),
+                RelabelAction::Rename {
+                    name: "router_wan_$1".to_string(),
+                },
+            ),
+            (
+                Regex::new(".*").unwrap(),
+                RelabelAction::AddLabel {
+                    label: "site".to_string(),
+                    value: "home".to_string(),
+                },
+            ),
+            (
+                Regex::new("^lan_client_").unwrap(),
+                RelabelAction::RemoveLabel {
+                    label: "ip".to_string(),
+                },
+            ),
+        ];
+        assert_eq!(
+            response.relabel(&rules),
+            PromResponse::new(vec![
+                PromMetric::new(
+                    "router_wan_up",
+                    "Whether the WAN is up",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("site", "home".to_string())],
+                        1f64,
+                        None
+                    )],
+                ),
+                PromMetric::new(
+                    "lan_client_receive_bytes_total",
+                    "IP Traffic statistic receive_bytes per LAN client",
+                    PromMetricType::Counter,
+                    vec![PromSample::new(
+                        vec![PromLabel::new("site", "home".to_string())],
+                        42f64,
+                        None
+                    )],
+                ),
+            ])
+        )
+    }
+
+    #[test]
+    fn test__PromResponse__merge_duplicates() {
+        let sample = |cpu: &str, value: f64| {
+            PromSample::new(vec![PromLabel::new("cpu", cpu.to_string())], value, None)
+        };
+        let response = PromResponse::new(vec![
+            PromMetric::new(
+                "node_load1",
+                "1m load average",
+                PromMetricType::Gauge,
+                vec![sample("0", 0.5)],
+            ),
+            PromMetric::new(
+                "wan_up",
+                "Whether the WAN is up",
+                PromMetricType::Gauge,
+                vec![PromSample::new(vec![], 1f64, None)],
+            ),
+            PromMetric::new(
+                "node_load1",
+                "Load average over a minute",
+                PromMetricType::Gauge,
+                vec![sample("0", 0.7), sample("1", 0.25)],
+            ),
+            PromMetric::new(
+                "node_load1",
+                "1m load average",
+                PromMetricType::Counter,
+                vec![sample("2", 3f64)],
+            ),
+        ]);
+        assert_eq!(
+            response.merge_duplicates(),
+            PromResponse::new(vec![
+                PromMetric::new(
+                    "node_load1",
+                    "1m load average",
+                    PromMetricType::Gauge,
+                    vec![sample("0", 0.5), sample("1", 0.25)],
+                ),
+                PromMetric::new(
+                    "wan_up",
+                    "Whether the WAN is up",
+                    PromMetricType::Gauge,
+                    vec![PromSample::new(vec![], 1f64, None)],
+                ),
+            ])
+        )
+    }
+
+    #[test]
+    fn test__PromResponse__without_zero_counters() {
+        let sample = |value: f64| PromSample::new(vec![], value, None);
+        let response = PromResponse::new(vec![
+            PromMetric::new(
+                "node_network_receive_bytes_total",
+                "Network device statistic receive_bytes",
+                PromMetricType::Counter,
+                vec![sample(0f64), sample(42f64)],
+            ),
+            PromMetric::new(
+                "wan_up",
+                "Whether the WAN is up",
+                PromMetricType::Gauge,
+                vec![sample(0f64)],
+            ),
+        ]);
+        assert_eq!(
+            response.without_zero_counters(),
+            PromResponse::new(vec![
+                PromMetric::new(
+                    "node_network_receive_bytes_total",
+                    "Network device statistic receive_bytes",
+                    PromMetricType::Counter,
+                    vec![sample(42f64)],
+                ),
+                PromMetric::new(
+                    "wan_up",
+                    "Whether the WAN is up",
+                    PromMetricType::Gauge,
+                    vec![sample(0f64)],
+                ),
+            ])
+        )
+    }
+
+    #[test]
+    fn test__PromResponse__with_const_labels() {
+        let response = PromResponse::new(vec![PromMetric::new(
+            "wifi_clients",
+            "Number of clients associated with the interface",
+            PromMetricType::Gauge,
+            vec![
+                PromSample::new(
+                    vec![PromLabel::new("iface", "eth1".to_string())],
+                    3f64,
+                    None,
+                ),
+                PromSample::new(vec![PromLabel::new("site", "lab".to_string())], 1f64, None),
+            ],
+        )]);
+        let labels = btreemap! {
+            "router".to_string() => "karabor".to_string(),
+            "site".to_string() => "home".to_string(),
+        };
+        assert_eq!(
+            response.with_const_labels(&labels),
+            PromResponse::new(vec![PromMetric::new(
+                "wifi_clients",
+                "Number of clients associated with the interface",
+                PromMetricType::Gauge,
+                vec![
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("iface", "eth1".to_string()),
+                            PromLabel::new("router", "karabor".to_string()),
+                            PromLabel::new("site", "home".to_string()),
+                        ],
+                        3f64,
+                        None
+                    ),
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("site", "lab".to_string()),
+                            PromLabel::new("router", "karabor".to_string()),
+                        ],
+                        1f64,
+                        None
+                    ),
+                ],
+            )])
+        )
+    }
+
+    #[test]
+    fn test__PromResponse__without_timestamps() {
+        let metric = |timestamp: Option<u64>| {
+            PromMetric::new(
+                "node_time_seconds",
+                "System time in seconds since epoch (1970)",
+                PromMetricType::Gauge,
+                vec![PromSample::new(vec![], 1598394934f64, timestamp)],
+            )
+        };
+        assert_eq!(
+            PromResponse::new(vec![metric(Some(1598394934000))]).without_timestamps(),
+            PromResponse::new(vec![metric(None)])
+        )
+    }
 }
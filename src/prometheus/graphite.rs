@@ -0,0 +1,95 @@
+use crate::prometheus::PromResponse;
+
+impl PromResponse {
+    // a line of Graphite's plaintext protocol for every sample, named by the prefix, the metric's
+    // name and then each label's name and value, sorted by label name.  samples without a timestamp
+    // of their own are given now, in seconds since the epoch.  Graphite has no way to store NaN or
+    // infinite values, so those samples are left out
+    pub fn to_graphite(&self, prefix: &str, now: u64) -> String {
+        let mut metrics: Vec<_> = self.metrics.iter().collect();
+        metrics.sort_by(|a, b| a.name.cmp(&b.name));
+        metrics
+            .iter()
+            .flat_map(|metric| {
+                metric
+                    .samples
+                    .iter()
+                    .filter(|sample| sample.value.is_finite())
+                    .map(|sample| {
+                        let mut labels: Vec<_> = sample.labels.iter().collect();
+                        labels.sort_by(|a, b| a.name.cmp(&b.name));
+                        let mut path: Vec<String> = Vec::new();
+                        if !prefix.is_empty() {
+                            path.push(prefix.to_string());
+                        }
+                        path.push(format!("{}{}", metric.name, sample.suffix));
+                        for label in labels {
+                            path.push(label.name.clone());
+                            path.push(graphite_node(label.value.as_str()));
+                        }
+                        format!(
+                            "{} {} {}\n",
+                            path.join("."),
+                            sample.value,
+                            sample.timestamp.map_or(now, |ts| ts / 1000)
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+// dots would split a label value into several nodes of the path, and whitespace would end the path
+// early, so anything but letters, digits, dashes and underscores is replaced
+fn graphite_node(value: &str) -> String {
+    if value.is_empty() {
+        return "_".to_string();
+    }
+    value
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod test {
+    use super::*;
+    use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromSample};
+
+    #[test]
+    fn test__PromResponse__to_graphite() {
+        let response = PromResponse::new(vec![
+            PromMetric::new(
+                "wifi_clients",
+                "Number of clients associated with the interface",
+                PromMetricType::Gauge,
+                vec![
+                    PromSample::new(
+                        vec![
+                            PromLabel::new("ssid", "my.home net".to_string()),
+                            PromLabel::new("iface", "eth1".to_string()),
+                        ],
+                        3f64,
+                        None,
+                    ),
+                    PromSample::new(vec![], f64::NAN, None),
+                ],
+            ),
+            PromMetric::new(
+                "node_load1",
+                "1m load average",
+                PromMetricType::Gauge,
+                vec![PromSample::new(vec![], 0.5, Some(1598394934250))],
+            ),
+        ]);
+        assert_eq!(
+            response.to_graphite("tomato", 1700000000),
+            "tomato.node_load1 0.5 1598394934\n\
+            tomato.wifi_clients.iface.eth1.ssid.my_home_net 3 1700000000\n"
+        )
+    }
+}
@@ -0,0 +1,234 @@
+use clap::{crate_name, crate_version};
+use serde_json::{json, Value};
+
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromResponse, PromSample};
+
+// the cumulative aggregation temporality, which is how every Prometheus counter and histogram counts
+const CUMULATIVE: u8 = 2;
+
+impl PromResponse {
+    // an ExportMetricsServiceRequest in OTLP's JSON encoding, in which 64 bit integers are given as
+    // strings.  samples without a timestamp of their own are given now, and counters and histograms
+    // are taken to have been counting since start, both in nanoseconds since the epoch
+    pub fn to_otlp(&self, now: u64, start: u64) -> Value {
+        json!({
+            "resourceMetrics": [{
+                "resource": {
+                    "attributes": [attribute("service.name", crate_name!())],
+                },
+                "scopeMetrics": [{
+                    "scope": {"name": crate_name!(), "version": crate_version!()},
+                    "metrics": self
+                        .metrics
+                        .iter()
+                        .map(|metric| metric.to_otlp(now, start))
+                        .collect::<Vec<Value>>(),
+                }],
+            }],
+        })
+    }
+}
+
+impl PromMetric {
+    fn to_otlp(&self, now: u64, start: u64) -> Value {
+        let points = |samples: &[PromSample]| -> Vec<Value> {
+            samples
+                .iter()
+                .map(|sample| {
+                    json!({
+                        "attributes": attributes(&sample.labels),
+                        "timeUnixNano": sample.time_unix_nano(now),
+                        "asDouble": double(sample.value),
+                    })
+                })
+                .collect()
+        };
+        // receivers need to know when a cumulative series started counting to tell a reset apart
+        // from a series that has only just been seen
+        let start_time_unix_nano = start.to_string();
+        let cumulative = |mut points: Vec<Value>| -> Vec<Value> {
+            for point in points.iter_mut() {
+                point["startTimeUnixNano"] = json!(start_time_unix_nano);
+            }
+            points
+        };
+        let mut metric = json!({"name": self.name, "description": self.help});
+        match self.typ {
+            PromMetricType::Counter => {
+                metric["sum"] = json!({
+                    "dataPoints": cumulative(points(&self.samples)),
+                    "aggregationTemporality": CUMULATIVE,
+                    "isMonotonic": true,
+                });
+            }
+            PromMetricType::Gauge | PromMetricType::Info | PromMetricType::Untyped => {
+                metric["gauge"] = json!({"dataPoints": points(&self.samples)});
+            }
+            // OTLP counts each bucket on its own rather than cumulatively, and takes the last
+            // bucket's upper bound to be infinite
+            PromMetricType::Histogram => {
+                let points: Vec<Value> = self
+                    .grouped("le")
+                    .into_iter()
+                    .map(|(labels, samples)| {
+                        let (buckets, sum, count) = PromMetric::split(&samples, "_bucket");
+                        let bounds: Vec<f64> = buckets
+                            .iter()
+                            .map(|(bound, _)| *bound)
+                            .filter(|bound| bound.is_finite())
+                            .collect();
+                        let mut cumulative: Vec<f64> = buckets
+                            .iter()
+                            .filter(|(bound, _)| bound.is_finite())
+                            .map(|(_, value)| *value)
+                            .collect();
+                        cumulative.push(count);
+                        let bucket_counts: Vec<String> = cumulative
+                            .iter()
+                            .scan(0f64, |previous, value| {
+                                let bucket = (value - *previous).max(0f64);
+                                *previous = *value;
+                                Some((bucket as u64).to_string())
+                            })
+                            .collect();
+                        json!({
+                            "attributes": attributes(&labels),
+                            "timeUnixNano": samples[0].time_unix_nano(now),
+                            "count": (count as u64).to_string(),
+                            "sum": double(sum),
+                            "bucketCounts": bucket_counts,
+                            "explicitBounds": bounds,
+                        })
+                    })
+                    .collect();
+                metric["histogram"] = json!({
+                    "dataPoints": cumulative(points),
+                    "aggregationTemporality": CUMULATIVE,
+                });
+            }
+            PromMetricType::Summary => {
+                let points: Vec<Value> = self
+                    .grouped("quantile")
+                    .into_iter()
+                    .map(|(labels, samples)| {
+                        let (quantiles, sum, count) = PromMetric::split(&samples, "");
+                        json!({
+                            "attributes": attributes(&labels),
+                            "timeUnixNano": samples[0].time_unix_nano(now),
+                            "count": (count as u64).to_string(),
+                            "sum": double(sum),
+                            "quantileValues": quantiles
+                                .iter()
+                                .map(|(quantile, value)| json!({"quantile": quantile, "value": double(*value)}))
+                                .collect::<Vec<Value>>(),
+                        })
+                    })
+                    .collect();
+                metric["summary"] = json!({"dataPoints": points});
+            }
+        }
+        metric
+    }
+}
+
+impl PromSample {
+    fn time_unix_nano(&self, now: u64) -> String {
+        self.timestamp.map_or(now, |ts| ts * 1_000_000).to_string()
+    }
+}
+
+// JSON has no numbers for NaN or the infinities, which serde_json would turn into null, so they're
+// spelled out as protobuf's JSON mapping has them
+fn double(value: f64) -> Value {
+    if value.is_nan() {
+        json!("NaN")
+    } else if value == f64::INFINITY {
+        json!("Infinity")
+    } else if value == f64::NEG_INFINITY {
+        json!("-Infinity")
+    } else {
+        json!(value)
+    }
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({"key": key, "value": {"stringValue": value}})
+}
+
+fn attributes(labels: &[PromLabel]) -> Vec<Value> {
+    labels
+        .iter()
+        .map(|label| attribute(label.name.as_str(), label.value.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod test {
+    use super::*;
+    use crate::prometheus::PromHistogram;
+
+    #[test]
+    fn test__PromResponse__to_otlp() {
+        let mut histogram = PromHistogram::new(
+            vec![PromLabel::new("collector", "load".to_string())],
+            &[0.5, 1f64],
+        );
+        histogram.observe(0.25);
+        histogram.observe(0.75);
+        histogram.observe(2f64);
+        let response = PromResponse::new(vec![
+            PromMetric::new(
+                "node_network_receive_bytes_total",
+                "Network device statistic receive_bytes",
+                PromMetricType::Counter,
+                vec![PromSample::new(
+                    vec![PromLabel::new("device", "eth0".to_string())],
+                    1024f64,
+                    Some(1598394934000),
+                )],
+            ),
+            PromMetric::histogram(
+                "scrape_duration_seconds",
+                "Scrape duration",
+                vec![histogram],
+            ),
+        ]);
+        let request = response.to_otlp(1700000000000000000, 1699999000000000000);
+        assert_eq!(
+            request["resourceMetrics"][0]["scopeMetrics"][0]["metrics"],
+            json!([
+                {
+                    "name": "node_network_receive_bytes_total",
+                    "description": "Network device statistic receive_bytes",
+                    "sum": {
+                        "dataPoints": [{
+                            "attributes": [{"key": "device", "value": {"stringValue": "eth0"}}],
+                            "timeUnixNano": "1598394934000000000",
+                            "asDouble": 1024.0,
+                            "startTimeUnixNano": "1699999000000000000",
+                        }],
+                        "aggregationTemporality": 2,
+                        "isMonotonic": true,
+                    },
+                },
+                {
+                    "name": "scrape_duration_seconds",
+                    "description": "Scrape duration",
+                    "histogram": {
+                        "dataPoints": [{
+                            "attributes": [{"key": "collector", "value": {"stringValue": "load"}}],
+                            "timeUnixNano": "1700000000000000000",
+                            "count": "3",
+                            "sum": 3.0,
+                            "bucketCounts": ["1", "1", "1"],
+                            "explicitBounds": [0.5, 1.0],
+                            "startTimeUnixNano": "1699999000000000000",
+                        }],
+                        "aggregationTemporality": 2,
+                    },
+                },
+            ])
+        )
+    }
+}
@@ -0,0 +1,311 @@
+use prost::Message;
+
+use crate::prometheus::{PromLabel, PromMetric, PromMetricType, PromResponse, PromSample};
+
+// the messages of io.prometheus.client's metrics.proto, leaving out the exemplars, created
+// timestamps and native histograms that nothing here reports
+#[derive(Clone, PartialEq, Message)]
+struct LabelPair {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    value: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, prost::Enumeration)]
+#[repr(i32)]
+enum MetricType {
+    Counter = 0,
+    Gauge = 1,
+    Summary = 2,
+    Untyped = 3,
+    Histogram = 4,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Gauge {
+    #[prost(double, optional, tag = "1")]
+    value: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Counter {
+    #[prost(double, optional, tag = "1")]
+    value: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Quantile {
+    #[prost(double, optional, tag = "1")]
+    quantile: Option<f64>,
+    #[prost(double, optional, tag = "2")]
+    value: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Summary {
+    #[prost(uint64, optional, tag = "1")]
+    sample_count: Option<u64>,
+    #[prost(double, optional, tag = "2")]
+    sample_sum: Option<f64>,
+    #[prost(message, repeated, tag = "3")]
+    quantile: Vec<Quantile>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Untyped {
+    #[prost(double, optional, tag = "1")]
+    value: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Histogram {
+    #[prost(uint64, optional, tag = "1")]
+    sample_count: Option<u64>,
+    #[prost(double, optional, tag = "2")]
+    sample_sum: Option<f64>,
+    #[prost(message, repeated, tag = "3")]
+    bucket: Vec<Bucket>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Bucket {
+    #[prost(uint64, optional, tag = "1")]
+    cumulative_count: Option<u64>,
+    #[prost(double, optional, tag = "2")]
+    upper_bound: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Metric {
+    #[prost(message, repeated, tag = "1")]
+    label: Vec<LabelPair>,
+    #[prost(message, optional, tag = "2")]
+    gauge: Option<Gauge>,
+    #[prost(message, optional, tag = "3")]
+    counter: Option<Counter>,
+    #[prost(message, optional, tag = "4")]
+    summary: Option<Summary>,
+    #[prost(message, optional, tag = "5")]
+    untyped: Option<Untyped>,
+    #[prost(int64, optional, tag = "6")]
+    timestamp_ms: Option<i64>,
+    #[prost(message, optional, tag = "7")]
+    histogram: Option<Histogram>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MetricFamily {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    help: Option<String>,
+    #[prost(enumeration = "MetricType", optional, tag = "3")]
+    r#type: Option<i32>,
+    #[prost(message, repeated, tag = "4")]
+    metric: Vec<Metric>,
+}
+
+impl PromResponse {
+    // every metric as a MetricFamily message, each one preceded by its length as a varint
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        let mut metrics: Vec<&PromMetric> = self.metrics.iter().collect();
+        metrics.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut buf: Vec<u8> = Vec::new();
+        for metric in metrics {
+            // writing to a Vec only fails when it can't grow
+            metric
+                .to_protobuf()
+                .encode_length_delimited(&mut buf)
+                .unwrap();
+        }
+        buf
+    }
+}
+
+impl PromMetric {
+    fn to_protobuf(&self) -> MetricFamily {
+        let (typ, metric) = match self.typ {
+            PromMetricType::Counter => (
+                MetricType::Counter,
+                self.samples
+                    .iter()
+                    .map(|sample| Metric {
+                        counter: Some(Counter {
+                            value: Some(sample.value),
+                        }),
+                        ..sample.to_protobuf(&sample.labels)
+                    })
+                    .collect(),
+            ),
+            PromMetricType::Gauge | PromMetricType::Info => (
+                MetricType::Gauge,
+                self.samples
+                    .iter()
+                    .map(|sample| Metric {
+                        gauge: Some(Gauge {
+                            value: Some(sample.value),
+                        }),
+                        ..sample.to_protobuf(&sample.labels)
+                    })
+                    .collect(),
+            ),
+            PromMetricType::Untyped => (
+                MetricType::Untyped,
+                self.samples
+                    .iter()
+                    .map(|sample| Metric {
+                        untyped: Some(Untyped {
+                            value: Some(sample.value),
+                        }),
+                        ..sample.to_protobuf(&sample.labels)
+                    })
+                    .collect(),
+            ),
+            PromMetricType::Histogram => (
+                MetricType::Histogram,
+                self.grouped("le")
+                    .into_iter()
+                    .map(|(labels, samples)| {
+                        let (buckets, sum, count) = PromMetric::split(&samples, "_bucket");
+                        Metric {
+                            histogram: Some(Histogram {
+                                sample_count: Some(count as u64),
+                                sample_sum: Some(sum),
+                                // the +Inf bucket is implied by the count
+                                bucket: buckets
+                                    .into_iter()
+                                    .filter(|(bound, _)| *bound != f64::INFINITY)
+                                    .map(|(bound, value)| Bucket {
+                                        cumulative_count: Some(value as u64),
+                                        upper_bound: Some(bound),
+                                    })
+                                    .collect(),
+                            }),
+                            ..samples[0].to_protobuf(&labels)
+                        }
+                    })
+                    .collect(),
+            ),
+            PromMetricType::Summary => (
+                MetricType::Summary,
+                self.grouped("quantile")
+                    .into_iter()
+                    .map(|(labels, samples)| {
+                        let (quantiles, sum, count) = PromMetric::split(&samples, "");
+                        Metric {
+                            summary: Some(Summary {
+                                sample_count: Some(count as u64),
+                                sample_sum: Some(sum),
+                                quantile: quantiles
+                                    .into_iter()
+                                    .map(|(quantile, value)| Quantile {
+                                        quantile: Some(quantile),
+                                        value: Some(value),
+                                    })
+                                    .collect(),
+                            }),
+                            ..samples[0].to_protobuf(&labels)
+                        }
+                    })
+                    .collect(),
+            ),
+        };
+        MetricFamily {
+            name: Some(self.name.clone()),
+            help: Some(self.help.clone()),
+            r#type: Some(typ as i32),
+            metric,
+        }
+    }
+}
+
+impl PromSample {
+    // everything but the value, which goes in the message for the metric's type
+    fn to_protobuf(&self, labels: &[PromLabel]) -> Metric {
+        Metric {
+            label: labels
+                .iter()
+                .map(|label| LabelPair {
+                    name: Some(label.name.clone()),
+                    value: Some(label.value.clone()),
+                })
+                .collect(),
+            timestamp_ms: self.timestamp.map(|ts| ts as i64),
+            ..Metric::default()
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod test {
+    use super::*;
+    use crate::prometheus::PromHistogram;
+
+    fn decode(mut buf: &[u8]) -> Vec<MetricFamily> {
+        let mut families = Vec::new();
+        while !buf.is_empty() {
+            families.push(MetricFamily::decode_length_delimited(&mut buf).unwrap());
+        }
+        families
+    }
+
+    #[test]
+    fn test__PromResponse__to_protobuf() {
+        let mut histogram = PromHistogram::new(
+            vec![PromLabel::new("collector", "load".to_string())],
+            &[0.5],
+        );
+        histogram.observe(0.25);
+        histogram.observe(2f64);
+        let response = PromResponse::new(vec![
+            PromMetric::new(
+                "node_load1",
+                "1m load average",
+                PromMetricType::Gauge,
+                vec![PromSample::new(vec![], 0.5, Some(1700000000000))],
+            ),
+            PromMetric::histogram(
+                "scrape_duration_seconds",
+                "Scrape duration",
+                vec![histogram],
+            ),
+        ]);
+        assert_eq!(
+            decode(response.to_protobuf().as_slice()),
+            vec![
+                MetricFamily {
+                    name: Some("node_load1".to_string()),
+                    help: Some("1m load average".to_string()),
+                    r#type: Some(MetricType::Gauge as i32),
+                    metric: vec![Metric {
+                        gauge: Some(Gauge { value: Some(0.5) }),
+                        timestamp_ms: Some(1700000000000),
+                        ..Metric::default()
+                    }],
+                },
+                MetricFamily {
+                    name: Some("scrape_duration_seconds".to_string()),
+                    help: Some("Scrape duration".to_string()),
+                    r#type: Some(MetricType::Histogram as i32),
+                    metric: vec![Metric {
+                        label: vec![LabelPair {
+                            name: Some("collector".to_string()),
+                            value: Some("load".to_string()),
+                        }],
+                        histogram: Some(Histogram {
+                            sample_count: Some(2),
+                            sample_sum: Some(2.25),
+                            bucket: vec![Bucket {
+                                cumulative_count: Some(1),
+                                upper_bound: Some(0.5),
+                            }],
+                        }),
+                        ..Metric::default()
+                    }],
+                },
+            ]
+        )
+    }
+}
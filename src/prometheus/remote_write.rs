@@ -0,0 +1,180 @@
+use prost::Message;
+
+use crate::prometheus::{PromMetricType, PromResponse};
+
+// the messages of the remote write 1.0 protocol's WriteRequest, leaving out exemplars and native
+// histograms
+#[derive(Clone, PartialEq, Message)]
+struct WriteRequest {
+    #[prost(message, repeated, tag = "1")]
+    timeseries: Vec<TimeSeries>,
+    #[prost(message, repeated, tag = "3")]
+    metadata: Vec<MetricMetadata>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TimeSeries {
+    #[prost(message, repeated, tag = "1")]
+    labels: Vec<Label>,
+    #[prost(message, repeated, tag = "2")]
+    samples: Vec<Sample>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Label {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(string, tag = "2")]
+    value: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Sample {
+    #[prost(double, tag = "1")]
+    value: f64,
+    #[prost(int64, tag = "2")]
+    timestamp: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, prost::Enumeration)]
+#[repr(i32)]
+enum MetricType {
+    Unknown = 0,
+    Counter = 1,
+    Gauge = 2,
+    Histogram = 3,
+    Summary = 5,
+    Info = 6,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MetricMetadata {
+    #[prost(enumeration = "MetricType", tag = "1")]
+    r#type: i32,
+    #[prost(string, tag = "2")]
+    metric_family_name: String,
+    #[prost(string, tag = "4")]
+    help: String,
+}
+
+impl PromResponse {
+    // a WriteRequest with a series for every sample, before it's compressed.  samples without a
+    // timestamp of their own are given now, in milliseconds since the epoch, since nothing will stamp
+    // them with the time of a scrape
+    pub fn to_remote_write(&self, now: u64) -> Vec<u8> {
+        let timeseries = self
+            .metrics
+            .iter()
+            .flat_map(|metric| {
+                metric.samples.iter().map(move |sample| {
+                    // the receiving end expects the labels sorted by name, with __name__ first
+                    let mut labels: Vec<Label> = sample
+                        .labels
+                        .iter()
+                        .map(|label| Label {
+                            name: label.name.clone(),
+                            value: label.value.clone(),
+                        })
+                        .collect();
+                    labels.sort_by(|a, b| a.name.cmp(&b.name));
+                    labels.insert(
+                        0,
+                        Label {
+                            name: "__name__".to_string(),
+                            value: format!("{}{}", metric.name, sample.suffix),
+                        },
+                    );
+                    TimeSeries {
+                        labels,
+                        samples: vec![Sample {
+                            value: sample.value,
+                            timestamp: sample.timestamp.unwrap_or(now) as i64,
+                        }],
+                    }
+                })
+            })
+            .collect();
+        let metadata = self
+            .metrics
+            .iter()
+            .map(|metric| MetricMetadata {
+                r#type: match metric.typ {
+                    PromMetricType::Counter => MetricType::Counter,
+                    PromMetricType::Gauge => MetricType::Gauge,
+                    PromMetricType::Histogram => MetricType::Histogram,
+                    PromMetricType::Summary => MetricType::Summary,
+                    PromMetricType::Info => MetricType::Info,
+                    PromMetricType::Untyped => MetricType::Unknown,
+                } as i32,
+                metric_family_name: metric.name.clone(),
+                help: metric.help.clone(),
+            })
+            .collect();
+        WriteRequest {
+            timeseries,
+            metadata,
+        }
+        .encode_to_vec()
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod test {
+    use super::*;
+    use crate::prometheus::{PromLabel, PromMetric, PromSample};
+
+    #[test]
+    fn test__PromResponse__to_remote_write() {
+        let response = PromResponse::new(vec![PromMetric::new(
+            "wifi_clients",
+            "Number of clients associated with the interface",
+            PromMetricType::Gauge,
+            vec![
+                PromSample::new(
+                    vec![
+                        PromLabel::new("ssid", "home".to_string()),
+                        PromLabel::new("iface", "eth1".to_string()),
+                    ],
+                    3f64,
+                    None,
+                ),
+                PromSample::new(vec![], 5f64, Some(1598394934000)),
+            ],
+        )]);
+        let label = |name: &str, value: &str| Label {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        assert_eq!(
+            WriteRequest::decode(response.to_remote_write(1700000000000).as_slice()).unwrap(),
+            WriteRequest {
+                timeseries: vec![
+                    TimeSeries {
+                        labels: vec![
+                            label("__name__", "wifi_clients"),
+                            label("iface", "eth1"),
+                            label("ssid", "home"),
+                        ],
+                        samples: vec![Sample {
+                            value: 3f64,
+                            timestamp: 1700000000000,
+                        }],
+                    },
+                    TimeSeries {
+                        labels: vec![label("__name__", "wifi_clients")],
+                        samples: vec![Sample {
+                            value: 5f64,
+                            timestamp: 1598394934000,
+                        }],
+                    },
+                ],
+                metadata: vec![MetricMetadata {
+                    r#type: MetricType::Gauge as i32,
+                    metric_family_name: "wifi_clients".to_string(),
+                    help: "Number of clients associated with the interface".to_string(),
+                }],
+            }
+        )
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE;
+use base64::Engine;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, ClientBuilder};
+use tracing::{debug, warn};
+
+use crate::client::TomatoClient;
+use crate::config::PushgatewayConfig;
+use crate::prometheus::PromResponse;
+
+// scrapes every collector on an interval and pushes the result to a pushgateway, for when the
+// monitoring host can't reach the exporter to scrape it
+pub struct Pushgateway {
+    client: TomatoClient,
+    http: Client,
+    url: String,
+    interval: Duration,
+}
+
+impl Pushgateway {
+    pub fn new(client: TomatoClient, conf: &PushgatewayConfig) -> Pushgateway {
+        let interval = Duration::from_secs_f64(conf.interval);
+        Pushgateway {
+            client,
+            // a push that takes longer than the interval would only pile up behind the next one
+            http: ClientBuilder::new()
+                .timeout(interval)
+                .build()
+                .expect("Unable to construct HTTP client"),
+            url: Pushgateway::grouping_url(conf),
+            interval,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match self.push().await {
+                Ok(()) => debug!("Pushed metrics to {}", self.url),
+                Err(err) => warn!("Pushing metrics to {} failed: {}", self.url, err),
+            }
+        }
+    }
+
+    // a PUT replaces every metric pushed before under the same grouping, so metrics that stop being
+    // reported don't linger on the pushgateway
+    async fn push(&self) -> Result<(), String> {
+        let scrape = self
+            .client
+            .get_metrics(&[])
+            .await
+            .map_err(|err| err.to_string())?;
+        self.http
+            .put(self.url.as_str())
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")
+            .body(Pushgateway::body(scrape.response))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+
+    // the pushgateway rejects pushed samples that carry a timestamp
+    fn body(response: PromResponse) -> String {
+        format!("{}\n", response.without_timestamps().to_prom())
+    }
+
+    // the job and grouping labels become path segments.  values that can't be put in a path as they
+    // are, such as ones containing a slash, are given base64 encoded as the pushgateway allows, with
+    // an empty value given as a lone padding character
+    fn grouping_url(conf: &PushgatewayConfig) -> String {
+        let segment = |name: &str, value: &str| {
+            if value.is_empty() {
+                format!("/{}@base64/=", name)
+            } else if value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c))
+            {
+                format!("/{}/{}", name, value)
+            } else {
+                format!("/{}@base64/{}", name, URL_SAFE.encode(value))
+            }
+        };
+        let mut url = format!(
+            "{}/metrics{}",
+            conf.url.trim_end_matches('/'),
+            segment("job", conf.job.as_str())
+        );
+        for (name, value) in &conf.grouping {
+            url.push_str(segment(name, value).as_str());
+        }
+        url
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prometheus::{PromMetric, PromMetricType, PromSample};
+
+    #[test]
+    fn test_body() {
+        let response = PromResponse::new(vec![PromMetric::new(
+            "node_time_seconds",
+            "System time in seconds since epoch (1970)",
+            PromMetricType::Gauge,
+            vec![PromSample::new(
+                Vec::new(),
+                1622901345f64,
+                Some(1622901345000),
+            )],
+        )]);
+        let body = Pushgateway::body(response);
+        assert!(!body.contains("1622901345000"));
+        assert!(body.contains("node_time_seconds 1622901345\n"));
+    }
+
+    #[test]
+    fn test_grouping_url() {
+        let conf = PushgatewayConfig {
+            url: "http://pushgateway.lan:9091/".to_string(),
+            interval: 60f64,
+            job: "tomato_exporter".to_string(),
+            grouping: btreemap! {
+                "instance".to_string() => "karabor".to_string(),
+                "path".to_string() => "/var/tmp".to_string(),
+                "site".to_string() => "".to_string(),
+            },
+        };
+        assert_eq!(
+            Pushgateway::grouping_url(&conf),
+            "http://pushgateway.lan:9091/metrics/job/tomato_exporter/instance/karabor\
+            /path@base64/L3Zhci90bXA=/site@base64/="
+        )
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::{Client, ClientBuilder};
+use time::OffsetDateTime;
+use tracing::{debug, warn};
+
+use crate::client::TomatoClient;
+use crate::config::RemoteWriteConfig;
+
+// scrapes every collector on an interval and sends the result to a remote write endpoint, such as
+// Prometheus, Mimir or VictoriaMetrics, so that nothing has to scrape the exporter
+pub struct RemoteWrite {
+    client: TomatoClient,
+    http: Client,
+    url: String,
+    credentials: Option<(String, String)>,
+    interval: Duration,
+}
+
+impl RemoteWrite {
+    pub fn new(client: TomatoClient, conf: &RemoteWriteConfig) -> RemoteWrite {
+        let interval = Duration::from_secs_f64(conf.interval);
+        RemoteWrite {
+            client,
+            // a write that takes longer than the interval would only pile up behind the next one
+            http: ClientBuilder::new()
+                .timeout(interval)
+                .build()
+                .expect("Unable to construct HTTP client"),
+            url: conf.url.clone(),
+            // both have already been checked to be given together when the config was validated
+            credentials: conf.username.clone().zip(conf.password.clone()),
+            interval,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match self.write().await {
+                Ok(()) => debug!("Wrote metrics to {}", self.url),
+                Err(err) => warn!("Writing metrics to {} failed: {}", self.url, err),
+            }
+        }
+    }
+
+    async fn write(&self) -> Result<(), String> {
+        let scrape = self
+            .client
+            .get_metrics(&[])
+            .await
+            .map_err(|err| err.to_string())?;
+        let now = (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as u64;
+        // the protocol uses snappy's block format, not its framed one
+        let body = snap::raw::Encoder::new()
+            .compress_vec(scrape.response.to_remote_write(now).as_slice())
+            .map_err(|err| err.to_string())?;
+        let mut request = self
+            .http
+            .post(self.url.as_str())
+            .header(CONTENT_TYPE, "application/x-protobuf")
+            .header(CONTENT_ENCODING, "snappy")
+            .header("X-Prometheus-Remote-Write-Version", "0.1.0")
+            .body(body);
+        if let Some((username, password)) = &self.credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use sd_notify::NotifyState;
+use tracing::{debug, info, warn};
+
+use crate::client::TomatoClient;
+
+// how long to wait before asking the router again when it couldn't be reached at startup
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+// tells systemd that the exporter is ready once the router has answered a scrape, and keeps its
+// watchdog fed for as long as the runtime is still running tasks.  outside of a Type=notify unit
+// there is no NOTIFY_SOCKET and nothing is sent
+pub struct Systemd {
+    client: TomatoClient,
+}
+
+impl Systemd {
+    pub fn new(client: TomatoClient) -> Systemd {
+        Systemd { client }
+    }
+
+    pub async fn run(self) {
+        if std::env::var_os("NOTIFY_SOCKET").is_none() {
+            return;
+        }
+        let mut usec = 0;
+        if sd_notify::watchdog_enabled(false, &mut usec) {
+            // pinging at half the interval leaves room for a ping that is held up by a busy runtime
+            let interval = Duration::from_micros(usec) / 2;
+            debug!("Pinging the systemd watchdog every {:?}", interval);
+            actix_web::rt::spawn(Systemd::watchdog(interval));
+        }
+        loop {
+            match self.client.get_metrics(&[]).await {
+                Ok(scrape) if !scrape.failed => break,
+                Ok(_) => warn!(
+                    "Unable to reach the router, retrying in {:?}",
+                    RETRY_INTERVAL
+                ),
+                Err(err) => warn!(
+                    "Initial scrape failed, retrying in {:?}: {}",
+                    RETRY_INTERVAL, err
+                ),
+            }
+            Systemd::notify(&[NotifyState::Status("waiting for the router")]);
+            tokio::time::sleep(RETRY_INTERVAL).await;
+        }
+        info!("Router reachable, ready");
+        Systemd::notify(&[NotifyState::Ready, NotifyState::Status("ready")]);
+    }
+
+    async fn watchdog(interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            Systemd::notify(&[NotifyState::Watchdog]);
+        }
+    }
+
+    pub fn stopping() {
+        Systemd::notify(&[NotifyState::Stopping]);
+    }
+
+    fn notify(state: &[NotifyState]) {
+        if let Err(err) = sd_notify::notify(false, state) {
+            warn!("Unable to notify systemd: {}", err);
+        }
+    }
+}
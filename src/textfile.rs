@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::client::TomatoClient;
+use crate::config::TextfileConfig;
+
+// scrapes every collector on an interval and writes the result to a file for node_exporter's
+// textfile collector to pick up, for hosts that already run node_exporter
+pub struct Textfile {
+    client: TomatoClient,
+    path: PathBuf,
+    interval: Duration,
+}
+
+impl Textfile {
+    pub fn new(client: TomatoClient, conf: &TextfileConfig) -> Textfile {
+        Textfile {
+            client,
+            path: PathBuf::from(conf.path.as_str()),
+            interval: Duration::from_secs_f64(conf.interval),
+        }
+    }
+
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match self.write().await {
+                Ok(()) => debug!("Wrote metrics to {}", self.path.display()),
+                Err(err) => warn!("Writing metrics to {} failed: {}", self.path.display(), err),
+            }
+        }
+    }
+
+    // the metrics are written next to the file and then renamed over it, so that node_exporter never
+    // reads one that is only partly written.  the textfile collector rejects samples with
+    // timestamps, so they are left out
+    async fn write(&self) -> Result<(), String> {
+        let scrape = self
+            .client
+            .get_metrics(&[])
+            .await
+            .map_err(|err| err.to_string())?;
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(format!(".{}.tmp", std::process::id()));
+        let temp = PathBuf::from(temp);
+        let body = format!("{}\n", scrape.response.without_timestamps().to_prom());
+        std::fs::write(&temp, body)
+            .and_then(|()| std::fs::rename(&temp, &self.path))
+            .map_err(|err| {
+                let _ = std::fs::remove_file(&temp);
+                err.to_string()
+            })
+    }
+}
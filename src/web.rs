@@ -1,22 +1,484 @@
-use actix_web::{error, web};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
-use crate::client::TomatoClient;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{error, web, HttpRequest, HttpResponse};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use clap::{crate_name, crate_version};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use url::form_urlencoded;
+
+use crate::client::{RawResponse, Scrape, TomatoClient};
+use crate::error::ExporterError;
+
+// the error is shared between every scrape waiting on the same one, which it can't be cloned into
+type SharedScrape = Shared<BoxFuture<'static, Result<Scrape, Arc<ExporterError>>>>;
+type CachedScrape = (Instant, Scrape);
 
 #[derive(Clone)]
 pub struct WebState {
     client: TomatoClient,
+    slug: String,
+    users: Arc<BTreeMap<String, String>>,
+    // keyed by the collectors requested, with an empty list standing for all of them
+    in_flight: Arc<Mutex<HashMap<Vec<String>, SharedScrape>>>,
+    cache_ttl: Option<Duration>,
+    failed_status: Option<StatusCode>,
+    cached: Arc<Mutex<HashMap<Vec<String>, CachedScrape>>>,
 }
 
 impl WebState {
-    pub fn new(client: TomatoClient) -> WebState {
-        WebState { client }
+    pub fn new(
+        client: TomatoClient,
+        slug: String,
+        users: Arc<BTreeMap<String, String>>,
+        cache_ttl: Option<Duration>,
+        failed_status: Option<StatusCode>,
+    ) -> WebState {
+        WebState {
+            client,
+            slug,
+            users,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            cache_ttl,
+            failed_status,
+            cached: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn respond(
+        &self,
+        collectors: Vec<String>,
+        req: &HttpRequest,
+    ) -> Result<HttpResponse, error::Error> {
+        let scrape = self.cached_scrape(collectors).await.map_err(|err| {
+            let status = match err.kind() {
+                "timeout" => StatusCode::GATEWAY_TIMEOUT,
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            error::InternalError::new(err.to_string(), status)
+        })?;
+        let status = match self.failed_status {
+            Some(status) if scrape.failed => status,
+            _ => StatusCode::OK,
+        };
+        #[cfg(feature = "protobuf")]
+        if wants_protobuf(req) {
+            return Ok(HttpResponse::build(status)
+                .content_type(
+                    "application/vnd.google.protobuf; \
+                    proto=io.prometheus.client.MetricFamily; encoding=delimited",
+                )
+                .body(scrape.response.to_protobuf()));
+        }
+        if wants_openmetrics(req) {
+            return Ok(HttpResponse::build(status)
+                .content_type("application/openmetrics-text; version=1.0.0; charset=utf-8")
+                .body(scrape.response.to_openmetrics()));
+        }
+        Ok(HttpResponse::build(status)
+            .content_type("text/plain; charset=utf-8")
+            .body(scrape.response.to_prom()))
+    }
+
+    // scrapes arriving within cache_ttl of the last successful one are answered without touching
+    // the router
+    async fn cached_scrape(&self, collectors: Vec<String>) -> Result<Scrape, Arc<ExporterError>> {
+        let Some(cache_ttl) = self.cache_ttl else {
+            return self.scrape(collectors).await;
+        };
+        if let Some((scraped_at, response)) = self.cached.lock().unwrap().get(&collectors) {
+            if scraped_at.elapsed() < cache_ttl {
+                return Ok(response.clone());
+            }
+        }
+        let result = self.scrape(collectors.clone()).await;
+        if let Ok(response) = &result {
+            self.cached
+                .lock()
+                .unwrap()
+                .insert(collectors, (Instant::now(), response.clone()));
+        }
+        result
+    }
+
+    // concurrent scrapes share the one already in progress instead of each querying the router
+    async fn scrape(&self, collectors: Vec<String>) -> Result<Scrape, Arc<ExporterError>> {
+        let scrape = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&collectors) {
+                Some(scrape) => scrape.clone(),
+                None => {
+                    let client = self.client.clone();
+                    let selected = collectors.clone();
+                    let scrape =
+                        async move { client.get_metrics(&selected).await.map_err(Arc::new) }
+                            .boxed()
+                            .shared();
+                    in_flight.insert(collectors.clone(), scrape.clone());
+                    scrape
+                }
+            }
+        };
+        let result = scrape.clone().await;
+
+        // the next scrape after this one finishes has to query the router again
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight
+            .get(&collectors)
+            .is_some_and(|current| current.ptr_eq(&scrape))
+        {
+            in_flight.remove(&collectors);
+        }
+        result
+    }
+}
+
+// where container probes and the healthcheck subcommand check that the exporter is up
+pub const HEALTHZ_PATH: &str = "/healthz";
+
+// every request must carry the credentials of one of the configured users, if there are any.  the
+// health check is left open, as it says nothing about the router, unless the metrics are served there
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, error::Error> {
+    let users = req
+        .app_data::<web::Data<WebState>>()
+        .filter(|data| req.path() != HEALTHZ_PATH || data.slug == HEALTHZ_PATH[1..])
+        .map(|data| data.users.clone())
+        .unwrap_or_default();
+    let credentials = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_basic_auth);
+    if users.is_empty() || is_authorized(users, credentials).await {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+    Ok(req
+        .into_response(
+            HttpResponse::Unauthorized()
+                .insert_header((
+                    header::WWW_AUTHENTICATE,
+                    format!("Basic realm=\"{}\"", crate_name!()),
+                ))
+                .finish(),
+        )
+        .map_into_right_body())
+}
+
+// bcrypt is deliberately slow, so the check is kept off of the worker thread.  an unknown username
+// is checked against a dummy hash of the same cost, so that how long the answer takes doesn't give
+// away which usernames exist
+async fn is_authorized(
+    users: Arc<BTreeMap<String, String>>,
+    credentials: Option<(String, String)>,
+) -> bool {
+    let Some((username, password)) = credentials else {
+        return false;
+    };
+    web::block(move || match users.get(&username) {
+        Some(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+        None => {
+            let _ = bcrypt::verify(password, dummy_hash(&users));
+            false
+        }
+    })
+    .await
+    .unwrap_or(false)
+}
+
+// the cost is taken from one of the configured hashes, which look like $2b$12$...
+fn dummy_hash(users: &BTreeMap<String, String>) -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| {
+        let cost = users
+            .values()
+            .find_map(|hash| hash.split('$').nth(2)?.parse::<u32>().ok())
+            .unwrap_or(bcrypt::DEFAULT_COST);
+        bcrypt::hash("", cost)
+            .or_else(|_| bcrypt::hash("", bcrypt::DEFAULT_COST))
+            .unwrap_or_default()
+    })
+}
+
+fn parse_basic_auth(value: &str) -> Option<(String, String)> {
+    let decoded = STANDARD.decode(value.strip_prefix("Basic ")?.trim()).ok()?;
+    let (username, password) = std::str::from_utf8(&decoded).ok()?.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+// like node_exporter, ?collect[]=cpu&collect[]=mem limits the scrape to the collectors named
+pub async fn metrics(
+    data: web::Data<WebState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, error::Error> {
+    let collectors = requested_collectors(&data, &req)?;
+    data.respond(collectors, &req).await
+}
+
+// serves a single collector, so that expensive collectors can be scraped less often than the rest
+pub async fn collector_metrics(
+    data: web::Data<WebState>,
+    collector: web::Path<String>,
+    req: HttpRequest,
+) -> Result<HttpResponse, error::Error> {
+    let collector = collector.into_inner();
+    if !data.client.collector_names().contains(&collector) {
+        return Err(error::ErrorNotFound(format!(
+            "Unknown or disabled collector {}",
+            collector
+        )));
+    }
+    data.respond(vec![collector], &req).await
+}
+
+// Prometheus asks for OpenMetrics first when it supports it, everything else gets the text format
+fn wants_openmetrics(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/openmetrics-text"))
+}
+
+// only asked for by scrapers set up to prefer it, such as Prometheus with native histograms enabled,
+// which list it ahead of the text formats
+#[cfg(feature = "protobuf")]
+fn wants_protobuf(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| {
+            accept.contains("application/vnd.google.protobuf")
+                && accept.contains("proto=io.prometheus.client.MetricFamily")
+        })
+}
+
+fn requested_collectors(data: &WebState, req: &HttpRequest) -> Result<Vec<String>, error::Error> {
+    let collectors = selected_collectors(req.query_string());
+    let enabled = data.client.collector_names();
+    if let Some(unknown) = collectors.iter().find(|name| !enabled.contains(name)) {
+        return Err(error::ErrorBadRequest(format!(
+            "Unknown or disabled collector {}",
+            unknown
+        )));
     }
+    Ok(collectors)
 }
 
-pub async fn metrics(data: web::Data<WebState>) -> Result<String, error::Error> {
-    data.client
-        .get_metrics()
-        .await
-        .map(|resp| resp.to_prom())
-        .map_err(error::ErrorInternalServerError)
+// sorted and deduplicated so that the same selection in any order shares a scrape
+fn selected_collectors(query: &str) -> Vec<String> {
+    let mut collectors: Vec<String> = form_urlencoded::parse(query.as_bytes())
+        .filter(|(key, _)| key == "collect[]")
+        .map(|(_, value)| value.into_owned())
+        .collect();
+    collectors.sort();
+    collectors.dedup();
+    collectors
+}
+
+// only routed when debug_endpoints is set, since the responses can include anything the router
+// reports, such as client hostnames and addresses
+pub async fn raw_responses(
+    data: web::Data<WebState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, error::Error> {
+    let collectors = requested_collectors(&data, &req)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(render_raw_responses(
+            &data.client.get_raw_responses(&collectors).await,
+        )))
+}
+
+fn render_raw_responses(responses: &[RawResponse]) -> String {
+    responses
+        .iter()
+        .map(|raw| match &raw.body {
+            Ok(body) => format!("==> {}: {}\n{}\n", raw.collector, raw.request, body),
+            Err(err) => format!(
+                "==> {}: {}\nrequest failed: {}\n",
+                raw.collector, raw.request, err
+            ),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// answers without scraping the router, so that a router that is down doesn't get the exporter
+// restarted
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body("OK\n")
+}
+
+pub async fn index(data: web::Data<WebState>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(landing_page(
+            data.slug.as_str(),
+            &data.client.collector_names(),
+        ))
+}
+
+fn landing_page(slug: &str, collectors: &[String]) -> String {
+    let slug = escape_html(slug);
+    format!(
+        "<html>
+<head><title>{name}</title></head>
+<body>
+<h1>{name} v{version}</h1>
+<p><a href=\"/{slug}\">Metrics</a></p>
+<h2>Enabled collectors</h2>
+<ul>
+{collectors}
+</ul>
+</body>
+</html>
+",
+        name = crate_name!(),
+        version = crate_version!(),
+        slug = slug,
+        collectors = collectors
+            .iter()
+            .map(|name| format!("<li><a href=\"/{slug}/{name}\">{name}</a></li>"))
+            .collect::<Vec<String>>()
+            .join("\n")
+    )
+}
+
+// the slug comes from the config or the command line, so it's escaped before going into the page
+fn escape_html(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#39;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_landing_page() {
+        let page = landing_page("metrics", &["cpu".to_string(), "wan".to_string()]);
+        assert!(page.contains(format!("v{}", crate_version!()).as_str()));
+        assert!(page.contains("<a href=\"/metrics\">Metrics</a>"));
+        assert!(page.contains(
+            "<ul>\n<li><a href=\"/metrics/cpu\">cpu</a></li>\n<li><a href=\"/metrics/wan\">wan</a></li>\n</ul>"
+        ));
+
+        let page = landing_page("\"><script>alert(1)</script>", &["cpu".to_string()]);
+        assert!(!page.contains("<script>"));
+        assert!(page
+            .contains("<a href=\"/&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;/cpu\">cpu</a>"));
+    }
+
+    #[test]
+    fn test_selected_collectors() {
+        assert_eq!(selected_collectors(""), Vec::<String>::new());
+        assert_eq!(
+            selected_collectors("collect[]=mem&collect%5B%5D=cpu&name[]=wan&collect[]=mem"),
+            vec!["cpu".to_string(), "mem".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_render_raw_responses() {
+        let responses = vec![
+            RawResponse {
+                collector: "load".to_string(),
+                request: "shell.cgi command=cat /proc/loadavg".to_string(),
+                body: Ok("0.08 0.03 0.01 1/52 1234".to_string()),
+            },
+            RawResponse {
+                collector: "wan".to_string(),
+                request: "status-data.jsx".to_string(),
+                body: Err("connection refused".to_string()),
+            },
+        ];
+        assert_eq!(
+            render_raw_responses(&responses),
+            "==> load: shell.cgi command=cat /proc/loadavg
+0.08 0.03 0.01 1/52 1234
+
+==> wan: status-data.jsx
+request failed: connection refused
+"
+        );
+    }
+
+    #[test]
+    fn test_parse_basic_auth() {
+        assert_eq!(
+            parse_basic_auth("Basic cHJvbWV0aGV1czpzM2NyZXQ6d2l0aDpjb2xvbnM="),
+            Some(("prometheus".to_string(), "s3cret:with:colons".to_string()))
+        );
+        assert_eq!(parse_basic_auth("Bearer cHJvbWV0aGV1czpzM2NyZXQ="), None);
+        assert_eq!(parse_basic_auth("Basic not base64"), None);
+    }
+
+    #[test]
+    fn test_wants_openmetrics() {
+        let request = |accept: &str| {
+            actix_web::test::TestRequest::default()
+                .insert_header((header::ACCEPT, accept))
+                .to_http_request()
+        };
+        assert!(wants_openmetrics(&request(
+            "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5,*/*;q=0.1"
+        )));
+        assert!(!wants_openmetrics(&request("text/plain;version=0.0.4")));
+        assert!(!wants_openmetrics(
+            &actix_web::test::TestRequest::default().to_http_request()
+        ));
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_wants_protobuf() {
+        let request = |accept: &str| {
+            actix_web::test::TestRequest::default()
+                .insert_header((header::ACCEPT, accept))
+                .to_http_request()
+        };
+        assert!(wants_protobuf(&request(
+            "application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;encoding=delimited;q=0.8,\
+            application/openmetrics-text;version=1.0.0;q=0.5,text/plain;version=0.0.4;q=0.2"
+        )));
+        assert!(!wants_protobuf(&request(
+            "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5"
+        )));
+    }
+
+    #[actix_web::test]
+    async fn test_is_authorized() {
+        let users = Arc::new(btreemap! {
+            "prometheus".to_string() => bcrypt::hash("s3cret", 4).unwrap(),
+        });
+        let credentials =
+            |username: &str, password: &str| Some((username.to_string(), password.to_string()));
+        assert!(is_authorized(users.clone(), credentials("prometheus", "s3cret")).await);
+        assert!(!is_authorized(users.clone(), credentials("prometheus", "guess")).await);
+        assert!(!is_authorized(users.clone(), credentials("admin", "s3cret")).await);
+        // the unknown username was checked against a hash of the same cost as the real one
+        assert!(dummy_hash(&users).starts_with("$2b$04$"));
+        assert!(!is_authorized(users, None).await);
+    }
 }